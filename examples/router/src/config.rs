@@ -1 +1,19 @@
-//配制文件相关
\ No newline at end of file
+//配制文件相关
+
+use std::path::Path;
+
+use mtiny::server::{ConfigError, ServerConfig};
+
+const CONFIG_PATH: &str = "config.toml";
+
+pub fn load() -> Result<ServerConfig, ConfigError> {
+    if Path::new(CONFIG_PATH).is_file() {
+        return ServerConfig::from_file(CONFIG_PATH);
+    }
+
+    ServerConfig {
+        bind: vec![([127, 0, 0, 1], 8082).into()],
+        ..Default::default()
+    }
+    .with_env()
+}
@@ -17,8 +17,8 @@ use mtiny::{middleware, route, service_fn, BoxError, Request, Response, Router,
 use routers::user_router::{UserRouter, UserQueryParam};
 #[tokio::main]
 async fn main() {
-    Server::new(|| app())
-        .bind(([127, 0, 0, 1], 8082))
+    let config = config::load().unwrap();
+    Server::from_config(|| app(), config)
         .run()
         .await
         .unwrap();
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::net::SocketAddr;
+
+use mtiny_http::uri::Scheme;
+use mtiny_http::Version;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct PeerAddr(pub SocketAddr);
+
+impl fmt::Debug for PeerAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <SocketAddr as fmt::Debug>::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for PeerAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <SocketAddr as fmt::Display>::fmt(&self.0, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectionInfo {
+    id: u64,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
+    scheme: Scheme,
+    version: Version,
+    request_count: u64,
+}
+
+impl ConnectionInfo {
+    pub fn new(
+        id: u64,
+        peer_addr: SocketAddr,
+        local_addr: SocketAddr,
+        scheme: Scheme,
+        version: Version,
+        request_count: u64,
+    ) -> Self {
+        Self {
+            id,
+            peer_addr,
+            local_addr,
+            scheme,
+            version,
+            request_count,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn scheme(&self) -> &Scheme {
+        &self.scheme
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.scheme == Scheme::HTTPS
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn request_count(&self) -> u64 {
+        self.request_count
+    }
+}
@@ -1,4 +1,5 @@
 mod  request;
+mod connection;
 
 pub mod response;
 
@@ -6,6 +7,8 @@ pub use request::Request;
 
 pub use response::Response;
 
+pub use connection::{ConnectionInfo, PeerAddr};
+
 pub mod http {
     pub use mtiny_http::*;
 }
@@ -20,5 +23,8 @@ pub mod service {
 
 pub use service::util::{service_fn};
 
-pub type BoxError = Box<dyn std::error::Error>;
+#[cfg(feature = "tower")]
+pub mod tower;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
@@ -1,7 +1,9 @@
 use std::borrow::Cow;
 
 use crate::BoxError;
-use mtiny_http::body::{Body, BodyExt, BoxBody, Bytes, MapErr, StreamBody};
+use mtiny_http::body::{
+    Body, BodyExt, BoxBody, Bytes, FromHttpBody, LengthLimitError, MapErr, StreamBody,
+};
 use mtiny_http::{header, HeaderMap, HeaderValue, StatusCode};
 
 pub type Response<B = BoxBody> = mtiny_http::Response<B>;
@@ -47,6 +49,12 @@ impl IntoResponse for StatusCode {
     }
 }
 
+impl IntoResponse for LengthLimitError {
+    fn into_response(self) -> Response {
+        (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()).into_response()
+    }
+}
+
 impl<T> IntoResponse for (StatusCode, T)
 where
     T: IntoResponse,
@@ -115,7 +123,7 @@ impl IntoResponse for Bytes {
 }
 impl<B> IntoResponse for Response<B>
 where
-    B: Body + 'static,
+    B: Body + Send + 'static,
     B::Error: Into<BoxError>,
 {
     fn into_response(self) -> Response {
@@ -123,9 +131,19 @@ where
     }
 }
 
+impl<B> IntoResponse for http::Response<B>
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    fn into_response(self) -> Response {
+        Response::from(self).map(|body| FromHttpBody::new(body).boxed())
+    }
+}
+
 impl<S> IntoResponse for StreamBody<S>
 where
-    Self: Body + 'static,
+    Self: Body + Send + 'static,
     <Self as Body>::Error: Into<BoxError>,
 {
     fn into_response(self) -> Response {
@@ -135,8 +153,8 @@ where
 
 impl<B, F, E> IntoResponse for MapErr<B, F>
 where
-    B: Body + 'static,
-    F: FnMut(B::Error) -> E + 'static,
+    B: Body + Send + 'static,
+    F: FnMut(B::Error) -> E + Send + 'static,
     E: Into<BoxError>,
 {
     fn into_response(self) -> Response {
@@ -0,0 +1,258 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use mtiny_http::body::{BodyExt, BoxBody, Bytes, FromHttpBody, IntoHttpBody};
+use pin_project_lite::pin_project;
+
+use crate::response::IntoResponse;
+use crate::service::util::{FromTower, FromTowerFuture};
+use crate::service::{ReadyService, Service, Wrap};
+use crate::{BoxError, Request};
+
+pub use crate::service::util::{from_tower, layer, LayerWarp};
+
+#[derive(Clone, Copy)]
+pub struct FromTowerHttp<S> {
+    inner: FromTower<S>,
+}
+
+impl<S> FromTowerHttp<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner: FromTower::new(inner),
+        }
+    }
+}
+
+impl<S, B> Service<Request<B>> for FromTowerHttp<S>
+where
+    S: tower_service::Service<http::Request<IntoHttpBody<B>>> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FromTowerFuture<S, http::Request<IntoHttpBody<B>>>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        self.inner
+            .call(http::Request::from(request.map(IntoHttpBody::new)))
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for FromTowerHttp<S>
+where
+    S: tower_service::Service<http::Request<IntoHttpBody<B>>> + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+impl<S> core::fmt::Debug for FromTowerHttp<S>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FromTowerHttp")
+            .field("inner", self.inner.get_ref())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct IntoTowerHttp<S> {
+    inner: S,
+}
+
+impl<S> IntoTowerHttp<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, B> tower_service::Service<http::Request<B>> for IntoTowerHttp<S>
+where
+    S: Service<Request>,
+    S::Response: IntoResponse,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = http::Response<IntoHttpBody<BoxBody>>;
+    type Error = S::Error;
+    type Future = IntoTowerHttpFuture<S::Future>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let request = Request::from(request.map(|body| FromHttpBody::new(body).boxed()));
+        IntoTowerHttpFuture {
+            inner: self.inner.call(request),
+        }
+    }
+}
+
+impl<S> core::fmt::Debug for IntoTowerHttp<S>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IntoTowerHttp")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+pin_project! {
+    pub struct IntoTowerHttpFuture<F> {
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F, T, E> Future for IntoTowerHttpFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<http::Response<IntoHttpBody<BoxBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let response = ready!(self.project().inner.poll(cx))?;
+        Poll::Ready(Ok(http::Response::from(
+            response.into_response().map(BodyExt::into_http_body),
+        )))
+    }
+}
+
+impl<F> core::fmt::Debug for IntoTowerHttpFuture<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IntoTowerHttpFuture")
+            .field("inner", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct HttpLayerWarp<L> {
+    layer: L,
+}
+
+impl<L> HttpLayerWarp<L> {
+    pub fn new(layer: L) -> Self {
+        Self { layer }
+    }
+}
+
+impl<S, L> Wrap<S> for HttpLayerWarp<L>
+where
+    L: tower_layer::Layer<IntoTowerHttp<S>>,
+{
+    type Service = FromTowerHttp<L::Service>;
+
+    fn wrap(self, service: S) -> Self::Service {
+        FromTowerHttp::new(self.layer.layer(IntoTowerHttp::new(service)))
+    }
+}
+
+impl<L> core::fmt::Debug for HttpLayerWarp<L>
+where
+    L: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HttpLayerWarp")
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+pub fn from_tower_http<S>(service: S) -> FromTowerHttp<S> {
+    FromTowerHttp::new(service)
+}
+
+pub fn http_layer<L>(layer: L) -> HttpLayerWarp<L> {
+    HttpLayerWarp::new(layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::future::{ready, Ready};
+    use std::pin::pin;
+    use std::task::Waker;
+
+    use super::*;
+    use crate::service::ServiceExt;
+
+    #[derive(Clone, Copy)]
+    struct Echo;
+
+    impl tower_service::Service<http::Request<IntoHttpBody<BoxBody>>> for Echo {
+        type Response = http::Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<IntoHttpBody<BoxBody>>) -> Self::Future {
+            let mut response = http::Response::new(String::new());
+            if let Some(value) = request.headers().get("x-layer") {
+                response.headers_mut().insert("x-layer", value.clone());
+            }
+            ready(Ok(response))
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct TagLayer;
+
+    #[derive(Clone)]
+    struct Tagged<S>(S);
+
+    impl<S> tower_layer::Layer<S> for TagLayer {
+        type Service = Tagged<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            Tagged(inner)
+        }
+    }
+
+    impl<S, B> tower_service::Service<http::Request<B>> for Tagged<S>
+    where
+        S: tower_service::Service<http::Request<B>>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+            request
+                .headers_mut()
+                .insert("x-layer", "tower".parse().unwrap());
+            self.0.call(request)
+        }
+    }
+
+    #[test]
+    fn http_layer_round_trip() {
+        let service = from_tower_http(Echo).with(http_layer(TagLayer));
+        let request = Request::builder()
+            .uri("/tower")
+            .body(BoxBody::default())
+            .unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let response = match pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => response.into_response(),
+            _ => panic!("service did not complete"),
+        };
+        assert_eq!(response.headers()["x-layer"], "tower");
+    }
+}
@@ -7,6 +7,7 @@ use std::{
 };
 
 use bytes::Bytes;
+use http::HeaderMap;
 
 use crate::{Request, Response};
 
@@ -18,6 +19,12 @@ pub trait Body {
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>>;
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
     fn size_hint(&self) -> SizeHint {
         SizeHint::default()
     }
@@ -35,6 +42,12 @@ where
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         unsafe { self.map_unchecked_mut(Request::body_mut).poll_next(cx) }
     }
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        unsafe { self.map_unchecked_mut(Request::body_mut).poll_trailers(cx) }
+    }
     fn size_hint(&self) -> SizeHint {
         self.body().size_hint()
     }
@@ -52,6 +65,12 @@ where
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         unsafe { self.map_unchecked_mut(Response::body_mut).poll_next(cx) }
     }
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        unsafe { self.map_unchecked_mut(Response::body_mut).poll_trailers(cx) }
+    }
     fn size_hint(&self) -> SizeHint {
         self.body().size_hint()
     }
@@ -68,6 +87,12 @@ where
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         Pin::new(&mut **self).poll_next(cx)
     }
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut **self).poll_trailers(cx)
+    }
     fn size_hint(&self) -> SizeHint {
         (**self).size_hint()
     }
@@ -84,6 +109,12 @@ where
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         Pin::new(&mut **self).poll_next(cx)
     }
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut **self).poll_trailers(cx)
+    }
     fn size_hint(&self) -> SizeHint {
         (**self).size_hint()
     }
@@ -101,6 +132,12 @@ where
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         self.get_mut().as_mut().poll_next(cx)
     }
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        self.get_mut().as_mut().poll_trailers(cx)
+    }
     fn size_hint(&self) -> SizeHint {
         (**self).size_hint()
     }
@@ -110,7 +147,7 @@ impl Body for () {
     type Error = Infallible;
     fn poll_next(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         Poll::Ready(None)
     }
@@ -123,9 +160,13 @@ impl Body for &'static [u8] {
     type Error = Infallible;
     fn poll_next(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
-        Poll::Ready(Some(Ok(Bytes::from_static(std::mem::take(self.get_mut())))))
+        if self.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(Ok(Bytes::from_static(std::mem::take(self.get_mut())))))
+        }
     }
     fn size_hint(&self) -> SizeHint {
         SizeHint::with_exact(self.len() as u64)
@@ -136,7 +177,7 @@ impl Body for Vec<u8> {
     type Error = Infallible;
     fn poll_next(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         if self.is_empty() {
             Poll::Ready(None)
@@ -171,7 +212,7 @@ impl Body for &'static str {
 
     fn poll_next(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         if self.is_empty() {
             Poll::Ready(None)
@@ -191,7 +232,7 @@ impl Body for String {
 
     fn poll_next(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         if self.is_empty() {
             Poll::Ready(None)
@@ -220,7 +261,7 @@ impl Body for Bytes {
 
     fn poll_next(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
         if self.is_empty() {
             Poll::Ready(None)
@@ -3,15 +3,17 @@ use std::pin::Pin;
 use super::body::Body;
 use super::ext::BodyExt;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 pub struct BoxBody {
-    inner: Pin<Box<dyn Body<Error = Box<dyn std::error::Error>>>>,
+    inner: Pin<Box<dyn Body<Error = BoxError> + Send>>,
 }
 
 impl BoxBody {
     pub fn new<B>(body: B) -> Self
     where
-        B: Body + 'static,
-        B::Error: Into<Box<dyn std::error::Error>>,
+        B: Body + Send + 'static,
+        B::Error: Into<BoxError>,
     {
         Self {
             inner: Box::pin(body.map_err(Into::into)),
@@ -20,13 +22,19 @@ impl BoxBody {
 }
 
 impl Body for BoxBody {
-    type Error = Box<dyn std::error::Error>;
+    type Error = BoxError;
     fn poll_next(
         mut self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Result<bytes::Bytes, Self::Error>>> {
         self.inner.as_mut().poll_next(cx)
     }
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.inner.as_mut().poll_trailers(cx)
+    }
     fn size_hint(&self) -> super::size_hint::SizeHint {
         self.inner.size_hint()
     }
@@ -42,3 +50,56 @@ impl std::fmt::Debug for BoxBody {
         f.debug_struct("BoxBody").finish()
     }
 }
+
+pub struct UnsyncBoxBody {
+    inner: Pin<Box<dyn Body<Error = BoxError>>>,
+}
+
+impl UnsyncBoxBody {
+    pub fn new<B>(body: B) -> Self
+    where
+        B: Body + 'static,
+        B::Error: Into<BoxError>,
+    {
+        Self {
+            inner: Box::pin(body.map_err(Into::into)),
+        }
+    }
+}
+
+impl Body for UnsyncBoxBody {
+    type Error = BoxError;
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<bytes::Bytes, Self::Error>>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.inner.as_mut().poll_trailers(cx)
+    }
+    fn size_hint(&self) -> super::size_hint::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Default for UnsyncBoxBody {
+    fn default() -> Self {
+        UnsyncBoxBody::new(())
+    }
+}
+
+impl From<BoxBody> for UnsyncBoxBody {
+    fn from(body: BoxBody) -> Self {
+        Self { inner: body.inner }
+    }
+}
+
+impl std::fmt::Debug for UnsyncBoxBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnsyncBoxBody").finish()
+    }
+}
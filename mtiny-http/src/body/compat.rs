@@ -0,0 +1,287 @@
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf, Bytes};
+use http::HeaderMap;
+use http_body::Frame;
+use pin_project_lite::pin_project;
+
+use super::{body::Body, SizeHint};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pin_project! {
+    #[derive(Debug, Clone, Default)]
+    pub struct FromHttpBody<B> {
+        #[pin]
+        inner: B,
+        trailers: Option<HeaderMap>,
+    }
+}
+
+impl<B> FromHttpBody<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            trailers: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B> Body for FromHttpBody<B>
+where
+    B: http_body::Body,
+    B::Data: Buf,
+    B::Error: Into<BoxError>,
+{
+    type Error = BoxError;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let mut this = self.project();
+        loop {
+            match ready!(this.inner.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(mut data) => {
+                        if data.has_remaining() {
+                            return Poll::Ready(Some(Ok(data.copy_to_bytes(data.remaining()))));
+                        }
+                    }
+                    Err(frame) => {
+                        if let Ok(trailers) = frame.into_trailers() {
+                            *this.trailers = Some(trailers);
+                        }
+                    }
+                },
+                Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let mut this = self.project();
+        loop {
+            if let Some(trailers) = this.trailers.take() {
+                return Poll::Ready(Ok(Some(trailers)));
+            }
+            match ready!(this.inner.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_trailers() {
+                    Ok(trailers) => *this.trailers = Some(trailers),
+                    // data that was never read is an error, not something to
+                    // skip on the way to the trailers
+                    Err(frame) => {
+                        if frame.data_ref().is_some_and(Buf::has_remaining) {
+                            return Poll::Ready(Err(
+                                "body data left unread before polling trailers".into(),
+                            ));
+                        }
+                    }
+                },
+                Some(Err(err)) => return Poll::Ready(Err(err.into())),
+                None => return Poll::Ready(Ok(None)),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint().into()
+    }
+}
+
+pin_project! {
+    #[derive(Debug, Clone, Default)]
+    pub struct IntoHttpBody<B> {
+        #[pin]
+        inner: B,
+        data_done: bool,
+        trailers_done: bool,
+    }
+}
+
+impl<B> IntoHttpBody<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            data_done: false,
+            trailers_done: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B> http_body::Body for IntoHttpBody<B>
+where
+    B: Body,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        if !*this.data_done {
+            match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(data)) => return Poll::Ready(Some(Ok(Frame::data(data)))),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => *this.data_done = true,
+            }
+        }
+        if *this.trailers_done {
+            return Poll::Ready(None);
+        }
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        *this.trailers_done = true;
+        Poll::Ready(
+            trailers
+                .transpose()
+                .map(|trailers| trailers.map(Frame::trailers)),
+        )
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.trailers_done
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint().into()
+    }
+}
+
+impl From<http_body::SizeHint> for SizeHint {
+    fn from(hint: http_body::SizeHint) -> Self {
+        let mut size_hint = SizeHint::new();
+        size_hint.set_lower(hint.lower());
+        if let Some(upper) = hint.upper() {
+            size_hint.set_upper(upper);
+        }
+        size_hint
+    }
+}
+
+impl From<SizeHint> for http_body::SizeHint {
+    fn from(hint: SizeHint) -> Self {
+        let mut size_hint = http_body::SizeHint::new();
+        size_hint.set_lower(hint.lower());
+        if let Some(upper) = hint.upper() {
+            size_hint.set_upper(upper);
+        }
+        size_hint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use super::*;
+
+    struct Frames(VecDeque<Frame<Bytes>>);
+
+    impl http_body::Body for Frames {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+            Poll::Ready(self.0.pop_front().map(Ok))
+        }
+    }
+
+    fn body() -> FromHttpBody<Frames> {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "abc".parse().unwrap());
+        FromHttpBody::new(Frames(VecDeque::from([
+            Frame::data(Bytes::from_static(b"abc")),
+            Frame::trailers(trailers),
+        ])))
+    }
+
+    fn poll<T>(f: impl FnOnce(&mut Context<'_>) -> Poll<T>) -> T {
+        match f(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("body is pending"),
+        }
+    }
+
+    #[test]
+    fn trailers_after_data() {
+        let mut body = body();
+        let data = poll(|cx| Pin::new(&mut body).poll_next(cx));
+        assert_eq!(data.unwrap().unwrap(), "abc");
+        assert!(poll(|cx| Pin::new(&mut body).poll_next(cx)).is_none());
+        let trailers = poll(|cx| Pin::new(&mut body).poll_trailers(cx));
+        assert_eq!(trailers.unwrap().unwrap()["x-checksum"], "abc");
+    }
+
+    #[test]
+    fn unread_data_before_trailers() {
+        let mut body = body();
+        let err = poll(|cx| Pin::new(&mut body).poll_trailers(cx)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "body data left unread before polling trailers"
+        );
+    }
+
+    #[test]
+    fn into_http_body_round_trip() {
+        let mut body = IntoHttpBody::new(body());
+        let poll_frame = |body: &mut IntoHttpBody<_>| {
+            poll(|cx| http_body::Body::poll_frame(Pin::new(body), cx)).map(Result::unwrap)
+        };
+
+        let data = poll_frame(&mut body).unwrap().into_data().unwrap();
+        assert_eq!(data, "abc");
+        assert!(!http_body::Body::is_end_stream(&body));
+        let trailers = poll_frame(&mut body).unwrap().into_trailers().unwrap();
+        assert_eq!(trailers["x-checksum"], "abc");
+        assert!(poll_frame(&mut body).is_none());
+        assert!(http_body::Body::is_end_stream(&body));
+    }
+
+    #[test]
+    fn size_hint_conversion() {
+        let hint = SizeHint::from(http_body::SizeHint::with_exact(5));
+        assert_eq!((hint.lower(), hint.upper()), (5, Some(5)));
+
+        let mut hint = SizeHint::new();
+        hint.set_lower(2);
+        let hint = http_body::SizeHint::from(hint);
+        assert_eq!((hint.lower(), hint.upper()), (2, None));
+    }
+}
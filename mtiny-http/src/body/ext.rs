@@ -1,4 +1,6 @@
-use super::{body::Body, BodyStream, MapErr, Next,BoxBody};
+use super::{
+    body::Body, BodyStream, BoxBody, IntoHttpBody, Limited, MapErr, Next, Trailers, UnsyncBoxBody,
+};
 
 pub trait BodyExt: Body {
     fn map_err<F, E>(self, f: F) -> MapErr<Self, F>
@@ -9,25 +11,52 @@ pub trait BodyExt: Body {
         MapErr::new(self, f)
     }
 
+    fn limit(self, limit: u64) -> Limited<Self>
+    where
+        Self: Sized,
+    {
+        Limited::new(self, limit)
+    }
+
     fn next(&mut self) -> Next<'_, Self>
     where
         Self: Unpin,
     {
         Next(self)
     }
+
+    fn trailers(&mut self) -> Trailers<'_, Self>
+    where
+        Self: Unpin,
+    {
+        Trailers(self)
+    }
     fn stream(self) -> BodyStream<Self>
     where
         Self: Sized,
     {
         BodyStream::new(self)
     }
+    fn into_http_body(self) -> IntoHttpBody<Self>
+    where
+        Self: Sized,
+    {
+        IntoHttpBody::new(self)
+    }
     fn boxed(self) -> BoxBody
     where
-        Self: Sized + 'static,
-        Self::Error: Into<Box<dyn std::error::Error>>,
+        Self: Sized + Send + 'static,
+        Self::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
         BoxBody::new(self)
     }
+    fn boxed_unsync(self) -> UnsyncBoxBody
+    where
+        Self: Sized + 'static,
+        Self::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        UnsyncBoxBody::new(self)
+    }
 }
 
 impl<B> BodyExt for B where B: Body {}
@@ -0,0 +1,221 @@
+use std::{pin::Pin, task::Poll};
+
+use pin_project_lite::pin_project;
+
+use super::body::Body;
+use super::size_hint::SizeHint;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pin_project! {
+    #[derive(Clone, Copy)]
+    pub struct Limited<B> {
+        #[pin]
+        inner: B,
+        remaining: u64,
+        limit: u64,
+    }
+}
+
+impl<B> Limited<B> {
+    pub fn new(inner: B, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut B> {
+        self.project().inner
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B> Body for Limited<B>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Error = BoxError;
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<bytes::Bytes, Self::Error>>> {
+        let project = self.project();
+        // a body announcing more than is left fails before anything is read
+        if project.inner.size_hint().lower() > *project.remaining {
+            return Poll::Ready(Some(Err(LengthLimitError::new(*project.limit).into())));
+        }
+        match project.inner.poll_next(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(Ok(data))) => match project.remaining.checked_sub(data.len() as u64) {
+                Some(remaining) => {
+                    *project.remaining = remaining;
+                    Poll::Ready(Some(Ok(data)))
+                }
+                None => {
+                    *project.remaining = 0;
+                    Poll::Ready(Some(Err(LengthLimitError::new(*project.limit).into())))
+                }
+            },
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
+        }
+    }
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+    fn size_hint(&self) -> SizeHint {
+        let hint = self.inner.size_hint();
+        match hint.upper() {
+            Some(upper) if upper <= self.remaining => hint,
+            _ if hint.lower() <= self.remaining => {
+                let mut capped = SizeHint::new();
+                capped.set_lower(hint.lower());
+                capped.set_upper(self.remaining);
+                capped
+            }
+            _ => SizeHint::new(),
+        }
+    }
+}
+
+impl<B> std::fmt::Debug for Limited<B>
+where
+    B: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Limited")
+            .field("inner", &self.inner)
+            .field("remaining", &self.remaining)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthLimitError {
+    limit: u64,
+}
+
+impl LengthLimitError {
+    pub fn new(limit: u64) -> Self {
+        Self { limit }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl std::fmt::Display for LengthLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "length limit of {} bytes exceeded", self.limit)
+    }
+}
+
+impl std::error::Error for LengthLimitError {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::task::{Context, Waker};
+
+    use bytes::Bytes;
+    use http::HeaderMap;
+
+    use super::*;
+
+    // chunks of unknown total length, followed by optional trailers
+    struct Chunks(VecDeque<&'static str>, Option<HeaderMap>);
+
+    impl Body for Chunks {
+        type Error = Infallible;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            Poll::Ready(
+                self.0
+                    .pop_front()
+                    .map(|chunk| Ok(Bytes::from_static(chunk.as_bytes()))),
+            )
+        }
+
+        fn poll_trailers(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(self.1.take()))
+        }
+    }
+
+    fn next<B: Body + Unpin>(body: &mut B) -> Option<Result<Bytes, B::Error>> {
+        match Pin::new(body).poll_next(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(chunk) => chunk,
+            Poll::Pending => panic!("body is pending"),
+        }
+    }
+
+    fn limit_error(err: BoxError) -> u64 {
+        err.downcast::<LengthLimitError>().unwrap().limit()
+    }
+
+    #[test]
+    fn rejects_announced_length_before_reading() {
+        let mut body = Limited::new(Bytes::from_static(b"too long"), 4);
+        assert_eq!(body.size_hint().exact(), None);
+        assert_eq!(limit_error(next(&mut body).unwrap().unwrap_err()), 4);
+
+        let mut body = Limited::new(Bytes::from_static(b"fits"), 4);
+        assert_eq!(body.size_hint().exact(), Some(4));
+        assert_eq!(next(&mut body).unwrap().unwrap(), "fits");
+        assert!(next(&mut body).is_none());
+    }
+
+    #[test]
+    fn fails_once_streamed_chunks_overflow() {
+        let mut body = Limited::new(Chunks(VecDeque::from(["abc", "def", "g"]), None), 6);
+        assert_eq!(body.size_hint().upper(), Some(6));
+        assert_eq!(next(&mut body).unwrap().unwrap(), "abc");
+        assert_eq!(next(&mut body).unwrap().unwrap(), "def");
+        assert_eq!(body.size_hint().upper(), Some(0));
+        assert_eq!(limit_error(next(&mut body).unwrap().unwrap_err()), 6);
+    }
+
+    #[test]
+    fn forwards_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "abc".parse().unwrap());
+        let mut body = Limited::new(Chunks(VecDeque::from(["abc"]), Some(trailers)), 3);
+        assert_eq!(next(&mut body).unwrap().unwrap(), "abc");
+        assert!(next(&mut body).is_none());
+        let trailers =
+            match Pin::new(&mut body).poll_trailers(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(trailers) => trailers.unwrap().unwrap(),
+                Poll::Pending => panic!("trailers are pending"),
+            };
+        assert_eq!(trailers["x-checksum"], "abc");
+    }
+}
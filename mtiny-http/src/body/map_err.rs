@@ -53,6 +53,17 @@ where
             Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err((project.f)(err)))),
         }
     }
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let project = self.project();
+        match project.inner.poll_trailers(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(trailers)) => Poll::Ready(Ok(trailers)),
+            Poll::Ready(Err(err)) => Poll::Ready(Err((project.f)(err))),
+        }
+    }
     fn size_hint(&self) -> super::size_hint::SizeHint {
         self.inner.size_hint()
     }
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod body;
 mod size_hint;
 pub use size_hint::SizeHint;
@@ -5,9 +6,15 @@ pub use size_hint::SizeHint;
 mod map_err;
 pub use map_err::MapErr;
 
+mod limited;
+pub use limited::{LengthLimitError, Limited};
+
 mod next;
 pub use next::Next;
 
+mod trailers;
+pub use trailers::Trailers;
+
 mod stream;
 pub use stream::{BodyStream, StreamBody};
 
@@ -15,7 +22,10 @@ mod ext;
 pub use ext::BodyExt;
 
 mod boxed;
-pub use boxed::BoxBody;
+pub use boxed::{BoxBody, UnsyncBoxBody};
+
+mod compat;
+pub use compat::{FromHttpBody, IntoHttpBody};
 
 pub use bytes::Bytes;
 
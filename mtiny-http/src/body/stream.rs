@@ -2,6 +2,7 @@ use std::task::Poll;
 
 use bytes::Bytes;
 use futures_core::{Stream, TryStream};
+use http::HeaderMap;
 use pin_project_lite::pin_project;
 
 use super::body::Body;
@@ -11,12 +12,21 @@ pin_project! {
     pub struct StreamBody<S>{
     #[pin]
     stream: S,
+    trailers: Option<HeaderMap>,
     }
 }
 
 impl<S> StreamBody<S> {
     pub fn new(stream: S) -> Self {
-        Self { stream }
+        Self {
+            stream,
+            trailers: None,
+        }
+    }
+
+    pub fn with_trailers(mut self, trailers: HeaderMap) -> Self {
+        self.trailers = Some(trailers);
+        self
     }
 }
 
@@ -37,6 +47,12 @@ where
             Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
         }
     }
+    fn poll_trailers(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.project().trailers.take()))
+    }
 }
 pin_project! {
     #[derive(Debug, Default)]
@@ -0,0 +1,22 @@
+use std::{future::Future, pin::Pin};
+
+use http::HeaderMap;
+
+use super::body::Body;
+
+#[must_use = "futures don't do anything unless polled"]
+#[derive(Debug)]
+pub struct Trailers<'a, B: ?Sized>(pub(crate) &'a mut B);
+
+impl<'a, B> Future for Trailers<'a, B>
+where
+    B: Body + Unpin + ?Sized,
+{
+    type Output = Result<Option<HeaderMap>, B::Error>;
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        Pin::new(&mut self.0).poll_trailers(cx)
+    }
+}
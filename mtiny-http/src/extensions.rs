@@ -2,9 +2,53 @@ use std::{
     any::{Any, TypeId},
     collections::HashMap,
     hash::{BuildHasherDefault, Hasher},
+    sync::{Arc, Mutex, PoisonError},
 };
 
-type AnyType = HashMap<TypeId, Box<dyn Any>, BuildHasherDefault<IdHasher>>;
+type AnyType = HashMap<TypeId, Entry, BuildHasherDefault<IdHasher>>;
+
+// pulls a value copied into `http::Extensions` back out by its type
+type Take = fn(&mut http::Extensions) -> Option<(TypeId, Entry)>;
+
+// moves a value into `http::Extensions` and returns how to get it back
+type Carry = fn(Box<dyn Any + Send>, &mut http::Extensions) -> Take;
+
+struct Entry {
+    value: Box<dyn Any + Send>,
+    copy: Option<Carry>,
+}
+
+impl Entry {
+    fn cloned<T: Clone + Send + Sync + 'static>(value: T) -> Self {
+        Self {
+            value: Box::new(value),
+            copy: Some(copy::<T>),
+        }
+    }
+
+    fn unique<T: Send + 'static>(value: T) -> Self {
+        Self {
+            value: Box::new(value),
+            copy: None,
+        }
+    }
+}
+
+fn copy<T: Clone + Send + Sync + 'static>(
+    value: Box<dyn Any + Send>,
+    http: &mut http::Extensions,
+) -> Take {
+    if let Ok(value) = value.downcast::<T>() {
+        http.insert(*value);
+    }
+    take::<T>
+}
+
+fn take<T: Clone + Send + Sync + 'static>(http: &mut http::Extensions) -> Option<(TypeId, Entry)> {
+    http.remove::<T>()
+        .map(|value| (TypeId::of::<T>(), Entry::cloned(value)))
+}
+
 #[derive(Default)]
 struct IdHasher(u64);
 
@@ -25,6 +69,7 @@ impl Hasher for IdHasher {
 #[derive(Default)]
 pub struct Extensions {
     map: Option<Box<AnyType>>,
+    http: http::Extensions,
 }
 
 impl Extensions {
@@ -33,32 +78,51 @@ impl Extensions {
         Self::default()
     }
 
-    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+    // for values that cannot be cloned, like upgrade handles; they follow the
+    // first conversion back from `http::Extensions` instead of being copied
+    pub fn insert<T: Send + 'static>(&mut self, value: T) -> Option<T> {
+        self.insert_entry(Entry::unique(value))
+    }
+
+    // cloneable values are also copied into `http::Extensions`, where tower
+    // layers and other `http` based code can read them
+    pub fn insert_cloneable<T: Clone + Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        let foreign = self.http.remove::<T>();
+        self.insert_entry(Entry::cloned(value)).or(foreign)
+    }
+
+    fn insert_entry<T: 'static>(&mut self, entry: Entry) -> Option<T> {
         self.map
             .get_or_insert_with(|| Box::new(HashMap::default()))
-            .insert(TypeId::of::<T>(), Box::new(value))
-            .and_then(|boxed| boxed.downcast().ok().map(|boxed| *boxed))
+            .insert(TypeId::of::<T>(), entry)
+            .and_then(|entry| entry.value.downcast().ok().map(|boxed| *boxed))
     }
 
-    pub fn get<T: 'static>(&self) -> Option<&T> {
-        self.map
-            .as_ref()
-            .and_then(|map| map.get(&TypeId::of::<T>()))
-            .and_then(|boxed| boxed.downcast_ref())
+    // values inserted through `http::Extensions` by other code are looked up
+    // in place, which is why lookups need the same bounds as `http`
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        let id = TypeId::of::<T>();
+        match self.map.as_ref().and_then(|map| map.get(&id)) {
+            Some(entry) => entry.value.downcast_ref(),
+            None => self.http.get(),
+        }
     }
 
-    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
-        self.map
-            .as_mut()
-            .and_then(|map| map.get_mut(&TypeId::of::<T>()))
-            .and_then(|boxed| boxed.downcast_mut())
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        let id = TypeId::of::<T>();
+        match self.map.as_mut().and_then(|map| map.get_mut(&id)) {
+            Some(entry) => entry.value.downcast_mut(),
+            None => self.http.get_mut(),
+        }
     }
 
-    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        let foreign = self.http.remove::<T>();
         self.map
             .as_mut()
             .and_then(|map| map.remove(&TypeId::of::<T>()))
-            .and_then(|boxed| boxed.downcast().ok().map(|boxed| *boxed))
+            .and_then(|entry| entry.value.downcast().ok().map(|boxed| *boxed))
+            .or(foreign)
     }
 
     #[inline]
@@ -66,16 +130,69 @@ impl Extensions {
         if let Some(ref mut map) = self.map {
             map.clear()
         }
+        self.http.clear();
     }
 
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.map.as_ref().map_or(true, |map| map.is_empty())
+        self.map.as_ref().is_none_or(|map| map.is_empty()) && self.http.is_empty()
     }
 
     #[inline]
     pub fn len(&self) -> usize {
-        self.map.as_ref().map_or(0, |map| map.len())
+        self.map.as_ref().map_or(0, |map| map.len()) + self.http.len()
+    }
+}
+
+// `http::Extensions` only stores `Clone` values: those are copied over one by
+// one, the rest is moved together and handed to whichever clone of the
+// `http::Extensions` converts back first
+#[derive(Clone)]
+struct Carried {
+    copied: Vec<Take>,
+    unique: Arc<Mutex<Option<Box<AnyType>>>>,
+}
+
+impl From<http::Extensions> for Extensions {
+    fn from(mut http: http::Extensions) -> Self {
+        let Some(carried) = http.remove::<Carried>() else {
+            return Self { map: None, http };
+        };
+        let mut map = carried
+            .unique
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .unwrap_or_default();
+        map.extend(carried.copied.iter().filter_map(|take| take(&mut http)));
+        Self {
+            map: Some(map).filter(|map| !map.is_empty()),
+            http,
+        }
+    }
+}
+
+impl From<Extensions> for http::Extensions {
+    fn from(extensions: Extensions) -> Self {
+        let Extensions { map, mut http } = extensions;
+        let mut copied = Vec::new();
+        let mut unique = AnyType::default();
+        for (id, entry) in map.into_iter().flat_map(|map| *map) {
+            match entry.copy {
+                Some(copy) => copied.push(copy(entry.value, &mut http)),
+                None => {
+                    unique.insert(id, entry);
+                }
+            }
+        }
+        if !copied.is_empty() || !unique.is_empty() {
+            let unique = Some(Box::new(unique)).filter(|unique| !unique.is_empty());
+            http.insert(Carried {
+                copied,
+                unique: Arc::new(Mutex::new(unique)),
+            });
+        }
+        http
     }
 }
 
@@ -84,3 +201,98 @@ impl core::fmt::Debug for Extensions {
         f.debug_struct("Extensions").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Copied(u32);
+
+    #[derive(Debug, PartialEq)]
+    struct Unique(u32);
+
+    fn extensions() -> Extensions {
+        let mut extensions = Extensions::new();
+        extensions.insert_cloneable(Copied(1));
+        extensions.insert(Unique(2));
+        extensions
+    }
+
+    #[test]
+    fn insert_returns_previous_value() {
+        let mut extensions = extensions();
+        assert_eq!(extensions.insert_cloneable(Copied(3)), Some(Copied(1)));
+        assert_eq!(extensions.insert(Unique(4)), Some(Unique(2)));
+        assert_eq!(extensions.get_mut::<Copied>(), Some(&mut Copied(3)));
+        assert_eq!(extensions.remove::<Unique>(), Some(Unique(4)));
+        assert_eq!(extensions.get::<Unique>(), None);
+        assert_eq!(extensions.len(), 1);
+    }
+
+    #[test]
+    fn round_trip() {
+        let extensions = Extensions::from(http::Extensions::from(extensions()));
+        assert_eq!(extensions.get::<Copied>(), Some(&Copied(1)));
+        assert_eq!(extensions.get::<Unique>(), Some(&Unique(2)));
+        assert_eq!(extensions.len(), 2);
+    }
+
+    #[test]
+    fn cloneable_values_are_visible_in_http() {
+        let mut http = http::Extensions::from(extensions());
+        assert_eq!(http.get::<Copied>(), Some(&Copied(1)));
+
+        http.insert(Copied(5));
+        let extensions = Extensions::from(http);
+        assert_eq!(extensions.get::<Copied>(), Some(&Copied(5)));
+    }
+
+    #[test]
+    fn removed_in_http_stays_removed() {
+        let mut http = http::Extensions::from(extensions());
+        http.remove::<Copied>();
+        let extensions = Extensions::from(http);
+        assert_eq!(extensions.get::<Copied>(), None);
+        assert_eq!(extensions.get::<Unique>(), Some(&Unique(2)));
+    }
+
+    #[test]
+    fn cloned_http_extensions_keep_their_own_copies() {
+        let http = http::Extensions::from(extensions());
+        let first = Extensions::from(http.clone());
+        let second = Extensions::from(http);
+        assert_eq!(first.get::<Copied>(), Some(&Copied(1)));
+        assert_eq!(second.get::<Copied>(), Some(&Copied(1)));
+        // values that cannot be cloned go to the first conversion only
+        assert_eq!(first.get::<Unique>(), Some(&Unique(2)));
+        assert_eq!(second.get::<Unique>(), None);
+    }
+
+    #[test]
+    fn foreign_values_survive_a_round_trip() {
+        let mut http = http::Extensions::new();
+        http.insert(Copied(6));
+        let mut extensions = Extensions::from(http);
+        extensions.insert(Unique(7));
+
+        let http = http::Extensions::from(extensions);
+        assert_eq!(http.get::<Copied>(), Some(&Copied(6)));
+        assert_eq!(Extensions::from(http).get::<Unique>(), Some(&Unique(7)));
+    }
+
+    #[test]
+    fn foreign_values_are_visible() {
+        let mut http = http::Extensions::new();
+        http.insert(Copied(6));
+        let mut extensions = Extensions::from(http);
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(extensions.get::<Copied>(), Some(&Copied(6)));
+
+        extensions.get_mut::<Copied>().unwrap().0 = 7;
+        assert_eq!(extensions.insert_cloneable(Copied(8)), Some(Copied(7)));
+        assert_eq!(extensions.remove::<Copied>(), Some(Copied(8)));
+        assert_eq!(extensions.get::<Copied>(), None);
+        assert!(extensions.is_empty());
+    }
+}
@@ -2,6 +2,7 @@ mod extensions;
 pub mod request;
 pub mod response;
 pub mod body;
+pub mod upgrade;
 
 pub use extensions::Extensions;
 pub use request::Request;
@@ -3,13 +3,13 @@ use http::{HeaderMap, HeaderName, HeaderValue, Method, Result, Uri, Version};
 use crate::extensions::Extensions;
 
 #[derive(Default)]
+#[non_exhaustive]
 pub struct Head {
     pub headers: HeaderMap<HeaderValue>,
     pub method: Method,
     pub uri: Uri,
     pub version: Version,
     pub extensions: Extensions,
-    _private: (),
 }
 
 impl Head {
@@ -152,6 +152,33 @@ impl<B: std::fmt::Debug> std::fmt::Debug for Request<B> {
             .finish()
     }
 }
+
+impl<B> From<http::Request<B>> for Request<B> {
+    fn from(request: http::Request<B>) -> Self {
+        let (parts, body) = request.into_parts();
+        let head = Head {
+            headers: parts.headers,
+            method: parts.method,
+            uri: parts.uri,
+            version: parts.version,
+            extensions: parts.extensions.into(),
+        };
+        Request { head, body }
+    }
+}
+
+impl<B> From<Request<B>> for http::Request<B> {
+    fn from(request: Request<B>) -> Self {
+        let Request { head, body } = request;
+        let mut request = http::Request::new(body);
+        *request.headers_mut() = head.headers;
+        *request.method_mut() = head.method;
+        *request.uri_mut() = head.uri;
+        *request.version_mut() = head.version;
+        *request.extensions_mut() = head.extensions.into();
+        request
+    }
+}
 pub struct Builder {
     inner: Result<Head>,
 }
@@ -234,7 +261,7 @@ impl Builder {
         self.inner.as_mut().ok().map(|head| &mut head.headers)
     }
 
-    pub fn extension<T: 'static>(self, extension: T) -> Builder {
+    pub fn extension<T: Send + 'static>(self, extension: T) -> Builder {
         self.and_then(move |mut head| {
             head.extensions.insert(extension);
             Ok(head)
@@ -260,3 +287,42 @@ impl Builder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Id(u32);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Foreign(u32);
+
+    #[test]
+    fn http_round_trip() {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/path?query")
+            .version(Version::HTTP_2)
+            .header("x-key", "value")
+            .extension(Id(1))
+            .body("body")
+            .unwrap();
+
+        let mut request = http::Request::from(request);
+        // builder values are moved, not copied into `http::Extensions`
+        assert_eq!(request.extensions().get::<Id>(), None);
+        request.extensions_mut().insert(Foreign(6));
+
+        let request = Request::from(request);
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.uri(), "/path?query");
+        assert_eq!(request.version(), &Version::HTTP_2);
+        assert_eq!(request.headers()["x-key"], "value");
+        assert_eq!(request.extensions().get::<Id>(), Some(&Id(1)));
+        assert_eq!(request.extensions().get::<Foreign>(), Some(&Foreign(6)));
+        assert_eq!(request.into_body(), "body");
+    }
+}
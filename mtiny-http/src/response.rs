@@ -3,12 +3,12 @@ use http::{HeaderMap, HeaderName, HeaderValue, Result, StatusCode, Version};
 use crate::extensions::Extensions;
 
 #[derive(Default)]
+#[non_exhaustive]
 pub struct Head {
     pub headers: HeaderMap<HeaderValue>,
     pub version: Version,
     pub status: StatusCode,
     pub extensions: Extensions,
-    _private: (),
 }
 
 impl Head {
@@ -139,6 +139,31 @@ impl<B: std::fmt::Debug> std::fmt::Debug for Response<B> {
             .finish()
     }
 }
+
+impl<B> From<http::Response<B>> for Response<B> {
+    fn from(response: http::Response<B>) -> Self {
+        let (parts, body) = response.into_parts();
+        let head = Head {
+            headers: parts.headers,
+            version: parts.version,
+            status: parts.status,
+            extensions: parts.extensions.into(),
+        };
+        Response { head, body }
+    }
+}
+
+impl<B> From<Response<B>> for http::Response<B> {
+    fn from(response: Response<B>) -> Self {
+        let Response { head, body } = response;
+        let mut response = http::Response::new(body);
+        *response.headers_mut() = head.headers;
+        *response.version_mut() = head.version;
+        *response.status_mut() = head.status;
+        *response.extensions_mut() = head.extensions.into();
+        response
+    }
+}
 pub struct Builder {
     inner: Result<Head>,
 }
@@ -199,7 +224,7 @@ impl Builder {
         self.inner.as_ref().ok().map(|head| &head.version)
     }
 
-    pub fn extensions<T: 'static>(self, extension: T) -> Builder {
+    pub fn extensions<T: Send + 'static>(self, extension: T) -> Builder {
         self.and_then(move |mut head| {
             head.extensions.insert(extension);
             Ok(head)
@@ -225,3 +250,36 @@ impl Builder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Id(u32);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Foreign(u32);
+
+    #[test]
+    fn http_round_trip() {
+        let response = Response::builder()
+            .status(StatusCode::CREATED)
+            .header("x-key", "value")
+            .extensions(Id(1))
+            .body("body")
+            .unwrap();
+
+        let mut response = http::Response::from(response);
+        // builder values are moved, not copied into `http::Extensions`
+        assert_eq!(response.extensions().get::<Id>(), None);
+        response.extensions_mut().insert(Foreign(6));
+
+        let response = Response::from(response);
+        assert_eq!(response.status(), &StatusCode::CREATED);
+        assert_eq!(response.headers()["x-key"], "value");
+        assert_eq!(response.extensions().get::<Id>(), Some(&Id(1)));
+        assert_eq!(response.extensions().get::<Foreign>(), Some(&Foreign(6)));
+        assert_eq!(response.into_body(), "body");
+    }
+}
@@ -0,0 +1,323 @@
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use mtiny_core::body::{Body, BoxBody, Bytes};
+use mtiny_core::http::HeaderMap;
+use mtiny_core::BoxError;
+
+use super::Level;
+use crate::encoding::Encoding;
+
+pub(crate) enum Encoder {
+    #[cfg(feature = "compression-br")]
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    #[cfg(feature = "compression-zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    #[cfg(feature = "compression-gzip")]
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    #[cfg(feature = "compression-deflate")]
+    Deflate(flate2::write::ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    pub(crate) fn new(encoding: Encoding, level: Level) -> io::Result<Option<Self>> {
+        let encoder = match encoding {
+            #[cfg(feature = "compression-br")]
+            Encoding::Brotli => {
+                let quality = match level {
+                    Level::Fastest => 1,
+                    Level::Default => 4,
+                    Level::Best => 11,
+                };
+                Encoder::Brotli(Box::new(brotli::CompressorWriter::new(
+                    Vec::new(),
+                    4096,
+                    quality,
+                    22,
+                )))
+            }
+            #[cfg(feature = "compression-zstd")]
+            Encoding::Zstd => {
+                let level = match level {
+                    Level::Fastest => 1,
+                    Level::Default => zstd::DEFAULT_COMPRESSION_LEVEL,
+                    Level::Best => 19,
+                };
+                Encoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), level)?)
+            }
+            #[cfg(feature = "compression-gzip")]
+            Encoding::Gzip => {
+                Encoder::Gzip(flate2::write::GzEncoder::new(Vec::new(), level.flate2()))
+            }
+            #[cfg(feature = "compression-deflate")]
+            Encoding::Deflate => {
+                Encoder::Deflate(flate2::write::ZlibEncoder::new(Vec::new(), level.flate2()))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(encoder))
+    }
+
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        let buf = match self {
+            #[cfg(feature = "compression-br")]
+            Encoder::Brotli(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "compression-zstd")]
+            Encoder::Zstd(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "compression-gzip")]
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "compression-deflate")]
+            Encoder::Deflate(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(buf)))
+    }
+
+    fn flush(&mut self) -> io::Result<Bytes> {
+        let buf = match self {
+            #[cfg(feature = "compression-br")]
+            Encoder::Brotli(encoder) => {
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "compression-zstd")]
+            Encoder::Zstd(encoder) => {
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "compression-gzip")]
+            Encoder::Gzip(encoder) => {
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            #[cfg(feature = "compression-deflate")]
+            Encoder::Deflate(encoder) => {
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(buf)))
+    }
+
+    fn finish(self) -> io::Result<Bytes> {
+        let buf = match self {
+            #[cfg(feature = "compression-br")]
+            Encoder::Brotli(encoder) => encoder.into_inner(),
+            #[cfg(feature = "compression-zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            #[cfg(feature = "compression-gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "compression-deflate")]
+            Encoder::Deflate(encoder) => encoder.finish()?,
+        };
+        Ok(Bytes::from(buf))
+    }
+}
+
+pub struct CompressionBody {
+    inner: BoxBody,
+    encoder: Option<Encoder>,
+    // input written since the encoder was last flushed
+    unflushed: bool,
+}
+
+impl CompressionBody {
+    pub(crate) fn new(inner: BoxBody, encoder: Encoder) -> Self {
+        Self {
+            inner,
+            encoder: Some(encoder),
+            unflushed: false,
+        }
+    }
+}
+
+impl Body for CompressionBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = &mut *self;
+        loop {
+            let Some(encoder) = this.encoder.as_mut() else {
+                return Poll::Ready(None);
+            };
+            // the encoder is only flushed once the inner body has nothing
+            // ready, so a streamed response is not held back while chunks
+            // that arrive together still compress together
+            let encoded = match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Pending if !this.unflushed => return Poll::Pending,
+                Poll::Pending => {
+                    this.unflushed = false;
+                    encoder.flush()
+                }
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.unflushed = true;
+                    encoder.write(&chunk)
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => this.encoder.take().unwrap().finish(),
+            };
+            match encoded {
+                Ok(encoded) if encoded.is_empty() => {}
+                Ok(encoded) => return Poll::Ready(Some(Ok(encoded))),
+                Err(err) => {
+                    this.encoder = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+        }
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+}
+
+impl std::fmt::Debug for CompressionBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressionBody")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::io::Read;
+    use std::task::Waker;
+
+    use mtiny_core::body::BodyExt;
+
+    use super::*;
+
+    // `None` stands for one poll that is not ready yet
+    struct Chunks(VecDeque<Option<Bytes>>);
+
+    impl Body for Chunks {
+        type Error = Infallible;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Infallible>>> {
+            match self.0.pop_front() {
+                Some(Some(chunk)) => Poll::Ready(Some(Ok(chunk))),
+                Some(None) => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                None => Poll::Ready(None),
+            }
+        }
+    }
+
+    fn compress<I>(encoding: Encoding, chunks: I) -> Vec<Bytes>
+    where
+        I: IntoIterator<Item = Option<Bytes>>,
+    {
+        let encoder = Encoder::new(encoding, Level::Default).unwrap().unwrap();
+        let chunks = Chunks(chunks.into_iter().collect());
+        let mut body = CompressionBody::new(chunks.boxed(), encoder);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut frames = Vec::new();
+        loop {
+            match Pin::new(&mut body).poll_next(&mut cx) {
+                Poll::Ready(Some(frame)) => frames.push(frame.unwrap()),
+                Poll::Ready(None) => return frames,
+                Poll::Pending => {}
+            }
+        }
+    }
+
+    fn round_trip(encoding: Encoding, decode: impl FnOnce(&[u8]) -> Vec<u8>) {
+        let text = "hello compression ".repeat(64);
+        let chunks = text.as_bytes().chunks(100).map(Bytes::copy_from_slice);
+        let encoded = compress(encoding, chunks.map(Some)).concat();
+        assert!(encoded.len() < text.len());
+        assert_eq!(decode(&encoded), text.as_bytes());
+    }
+
+    #[cfg(feature = "compression-gzip")]
+    #[test]
+    fn gzip_round_trip() {
+        round_trip(Encoding::Gzip, |data| {
+            let mut out = Vec::new();
+            flate2::read::GzDecoder::new(data)
+                .read_to_end(&mut out)
+                .unwrap();
+            out
+        });
+    }
+
+    #[cfg(feature = "compression-deflate")]
+    #[test]
+    fn deflate_round_trip() {
+        round_trip(Encoding::Deflate, |data| {
+            let mut out = Vec::new();
+            flate2::read::ZlibDecoder::new(data)
+                .read_to_end(&mut out)
+                .unwrap();
+            out
+        });
+    }
+
+    #[cfg(feature = "compression-br")]
+    #[test]
+    fn brotli_round_trip() {
+        round_trip(Encoding::Brotli, |data| {
+            let mut out = Vec::new();
+            brotli::Decompressor::new(data, 4096)
+                .read_to_end(&mut out)
+                .unwrap();
+            out
+        });
+    }
+
+    #[cfg(feature = "compression-zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        round_trip(Encoding::Zstd, |data| {
+            zstd::stream::decode_all(data).unwrap()
+        });
+    }
+
+    #[cfg(feature = "compression-gzip")]
+    #[test]
+    fn flushes_only_when_inner_is_pending() {
+        let ready = compress(Encoding::Gzip, vec![Some(Bytes::from_static(b"a")); 100]);
+        assert!(ready.len() <= 2);
+
+        // everything before the final frame was flushed while the body was pending
+        let frames = compress(
+            Encoding::Gzip,
+            [
+                Some(Bytes::from_static(b"first")),
+                None,
+                Some(Bytes::from_static(b"second")),
+            ],
+        );
+        let mut out = Vec::new();
+        let _ = flate2::read::GzDecoder::new(&frames[..frames.len() - 1].concat()[..])
+            .read_to_end(&mut out);
+        assert_eq!(out, b"first");
+    }
+}
@@ -0,0 +1,450 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::body::{Body, BodyExt};
+use mtiny_core::http::{header, HeaderMap, HeaderValue, StatusCode};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{Request, Response};
+
+pub use crate::encoding::Encoding;
+
+mod body;
+pub use body::CompressionBody;
+
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Fastest,
+    #[default]
+    Default,
+    Best,
+}
+
+impl Level {
+    #[cfg(any(feature = "compression-gzip", feature = "compression-deflate"))]
+    fn flate2(self) -> flate2::Compression {
+        match self {
+            Level::Fastest => flate2::Compression::fast(),
+            Level::Default => flate2::Compression::default(),
+            Level::Best => flate2::Compression::best(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    min_size: u64,
+    level: Level,
+    #[cfg(feature = "compression-br")]
+    br: bool,
+    #[cfg(feature = "compression-zstd")]
+    zstd: bool,
+    #[cfg(feature = "compression-gzip")]
+    gzip: bool,
+    #[cfg(feature = "compression-deflate")]
+    deflate: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+            level: Level::Default,
+            #[cfg(feature = "compression-br")]
+            br: true,
+            #[cfg(feature = "compression-zstd")]
+            zstd: true,
+            #[cfg(feature = "compression-gzip")]
+            gzip: true,
+            #[cfg(feature = "compression-deflate")]
+            deflate: true,
+        }
+    }
+}
+
+impl Config {
+    fn enabled(&self, encoding: Encoding) -> bool {
+        match encoding {
+            #[cfg(feature = "compression-br")]
+            Encoding::Brotli => self.br,
+            #[cfg(feature = "compression-zstd")]
+            Encoding::Zstd => self.zstd,
+            #[cfg(feature = "compression-gzip")]
+            Encoding::Gzip => self.gzip,
+            #[cfg(feature = "compression-deflate")]
+            Encoding::Deflate => self.deflate,
+            Encoding::Identity => true,
+            // variants that only exist for the other direction
+            #[cfg(all(feature = "decompression-br", not(feature = "compression-br")))]
+            Encoding::Brotli => false,
+            #[cfg(all(feature = "decompression-zstd", not(feature = "compression-zstd")))]
+            Encoding::Zstd => false,
+            #[cfg(all(feature = "decompression-gzip", not(feature = "compression-gzip")))]
+            Encoding::Gzip => false,
+            #[cfg(all(feature = "decompression-deflate", not(feature = "compression-deflate")))]
+            Encoding::Deflate => false,
+        }
+    }
+
+    fn is_eligible(&self, response: &Response) -> bool {
+        let status = response.status();
+        if status.is_informational()
+            || *status == StatusCode::NO_CONTENT
+            || *status == StatusCode::NOT_MODIFIED
+            || *status == StatusCode::PARTIAL_CONTENT
+        {
+            return false;
+        }
+        let headers = response.headers();
+        if headers.contains_key(header::CONTENT_ENCODING)
+            || headers.contains_key(header::CONTENT_RANGE)
+            || has_token(headers, header::CACHE_CONTROL, "no-transform")
+            || !is_compressible(headers)
+        {
+            return false;
+        }
+        response
+            .body()
+            .size_hint()
+            .upper()
+            .is_none_or(|upper| upper >= self.min_size)
+    }
+}
+
+fn has_token(headers: &HeaderMap, name: header::HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+fn is_compressible(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return true;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if essence == "image/svg+xml" {
+        return true;
+    }
+    !([
+        "image/",
+        "audio/",
+        "video/",
+        "font/woff",
+        "application/grpc",
+    ]
+    .iter()
+    .any(|prefix| essence.starts_with(prefix))
+        || matches!(
+            essence.as_str(),
+            "text/event-stream"
+                | "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/zstd"
+                | "application/x-bzip2"
+                | "application/x-7z-compressed"
+                | "application/vnd.rar"
+        ))
+}
+
+fn compress(mut response: Response, encoding: Encoding, config: Config) -> Response {
+    if !config.is_eligible(&response) {
+        return response;
+    }
+    let headers = response.headers_mut();
+    if !has_token(headers, header::VARY, "accept-encoding")
+        && !has_token(headers, header::VARY, "*")
+    {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    if encoding == Encoding::Identity {
+        return response;
+    }
+
+    let encoder = match body::Encoder::new(encoding, config.level) {
+        Ok(Some(encoder)) => encoder,
+        _ => return response,
+    };
+
+    let (mut head, body) = response.into_head();
+    let body = body::CompressionBody::new(body, encoder);
+    head.headers.remove(header::CONTENT_LENGTH);
+    head.headers.remove(header::ACCEPT_RANGES);
+    head.headers
+        .insert(header::CONTENT_ENCODING, encoding.header_value());
+    if let Some(etag) = head.headers.get_mut(header::ETAG) {
+        if !etag.as_bytes().starts_with(b"W/") {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            if let Ok(value) = HeaderValue::from_bytes(&weak) {
+                *etag = value;
+            }
+        }
+    }
+    Response::from_head(head, body.boxed())
+}
+
+#[derive(Debug, Clone)]
+pub struct Compression<S> {
+    inner: S,
+    config: Config,
+}
+
+pub fn compression() -> CompressionWarp {
+    CompressionWarp::new()
+}
+
+impl<S, B> Service<Request<B>> for Compression<S>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = CompressionFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let encoding =
+            Encoding::negotiate(request.headers(), |encoding| self.config.enabled(encoding));
+        CompressionFuture {
+            fut: self.inner.call(request),
+            encoding,
+            config: self.config,
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for Compression<S>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    pub struct CompressionFuture<F> {
+        #[pin]
+        fut: F,
+        encoding: Encoding,
+        config: Config,
+    }
+}
+
+impl<F, T, E> Future for CompressionFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.fut.poll(cx))?.into_response();
+        Poll::Ready(Ok(compress(response, *this.encoding, *this.config)))
+    }
+}
+
+impl<F> std::fmt::Debug for CompressionFuture<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressionFuture")
+            .field("encoding", &self.encoding)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompressionWarp {
+    config: Config,
+}
+
+impl CompressionWarp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.config.min_size = min_size;
+        self
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.config.level = level;
+        self
+    }
+
+    #[cfg(feature = "compression-br")]
+    pub fn br(mut self, enable: bool) -> Self {
+        self.config.br = enable;
+        self
+    }
+
+    #[cfg(feature = "compression-zstd")]
+    pub fn zstd(mut self, enable: bool) -> Self {
+        self.config.zstd = enable;
+        self
+    }
+
+    #[cfg(feature = "compression-gzip")]
+    pub fn gzip(mut self, enable: bool) -> Self {
+        self.config.gzip = enable;
+        self
+    }
+
+    #[cfg(feature = "compression-deflate")]
+    pub fn deflate(mut self, enable: bool) -> Self {
+        self.config.deflate = enable;
+        self
+    }
+}
+
+impl<S> Wrap<S> for CompressionWarp {
+    type Service = Compression<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        Compression {
+            inner,
+            config: self.config,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "compression-gzip"))]
+mod tests {
+    use std::convert::Infallible;
+    use std::io::Read;
+    use std::task::Waker;
+
+    use mtiny_core::body::Bytes;
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    fn respond(response: fn() -> Response, accept: Option<&'static str>) -> (Response, Vec<u8>) {
+        let service =
+            service_fn(move |_: Request<()>| async move { Ok::<_, Infallible>(response()) })
+                .with(compression().min_size(64));
+        let mut request = Request::new(());
+        if let Some(accept) = accept {
+            request
+                .headers_mut()
+                .insert(header::ACCEPT_ENCODING, HeaderValue::from_static(accept));
+        }
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let Poll::Ready(Ok(mut response)) = std::pin::pin!(service.call(request)).poll(&mut cx)
+        else {
+            panic!("service did not complete");
+        };
+        let mut body = Vec::new();
+        loop {
+            match std::pin::pin!(response.body_mut().next()).poll(&mut cx) {
+                Poll::Ready(Some(chunk)) => body.extend_from_slice(&chunk.unwrap()),
+                Poll::Ready(None) => break,
+                Poll::Pending => {}
+            }
+        }
+        (response, body)
+    }
+
+    fn text() -> Response {
+        "compress me ".repeat(16).into_response()
+    }
+
+    #[test]
+    fn compresses_negotiated_responses() {
+        let (response, body) = respond(text, Some("br;q=0, gzip"));
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(headers[header::VARY], "accept-encoding");
+        assert!(!headers.contains_key(header::CONTENT_LENGTH));
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "compress me ".repeat(16));
+    }
+
+    #[test]
+    fn identity_only_adds_vary() {
+        let (response, body) = respond(text, None);
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(body, "compress me ".repeat(16).as_bytes());
+    }
+
+    #[test]
+    fn skips_ineligible_responses() {
+        let small = || Response::new(Bytes::from_static(b"too small").boxed());
+        let image = || {
+            let mut response = text();
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static("image/png"));
+            response
+        };
+        let no_transform = || {
+            let mut response = text();
+            response.headers_mut().insert(
+                header::CACHE_CONTROL,
+                HeaderValue::from_static("public, no-transform"),
+            );
+            response
+        };
+        let no_content = || StatusCode::NO_CONTENT.into_response();
+
+        for response in [small, image, no_transform, no_content] {
+            let (response, _) = respond(response, Some("gzip"));
+            assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        }
+    }
+
+    #[test]
+    fn weakens_strong_etags() {
+        let tagged = || {
+            let mut response = text();
+            response
+                .headers_mut()
+                .insert(header::ETAG, HeaderValue::from_static("\"v1\""));
+            response
+        };
+        let (response, _) = respond(tagged, Some("gzip"));
+        assert_eq!(response.headers()[header::ETAG], "W/\"v1\"");
+
+        let (response, _) = respond(tagged, None);
+        assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+    }
+
+    #[test]
+    fn keeps_streaming_chunks() {
+        let (response, body) = respond(
+            || Response::new(Bytes::from_static(&[b'x'; 256]).boxed()),
+            Some("gzip"),
+        );
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, [b'x'; 256]);
+    }
+}
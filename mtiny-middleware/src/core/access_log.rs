@@ -0,0 +1,671 @@
+use std::fmt::{self, Write as _};
+use std::future::Future;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc};
+use std::task::{ready, Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::body::{Body, BodyExt, BoxBody, Bytes, SizeHint};
+use mtiny_core::http::{header, HeaderMap, Method, StatusCode, Version};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{BoxError, ConnectionInfo, PeerAddr, Request, Response};
+
+use super::request_id::RequestId;
+use super::trusted_proxy::RealIp;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogFormat {
+    Common,
+    #[default]
+    Combined,
+    Json,
+}
+
+enum Target {
+    #[cfg(feature = "log")]
+    Log(log::Level),
+    #[cfg(feature = "tracing")]
+    Tracing,
+    Writer(Box<dyn Write + Send>),
+}
+
+impl Target {
+    fn start(self) -> Sink {
+        match self {
+            #[cfg(feature = "log")]
+            Target::Log(level) => Sink::Log(level),
+            #[cfg(feature = "tracing")]
+            Target::Tracing => Sink::Tracing,
+            Target::Writer(writer) => {
+                // lines are written on a dedicated thread so a slow terminal or
+                // pipe never blocks the executor that finishes the response,
+                // and buffered until the queue runs dry
+                let (tx, rx) = mpsc::channel::<String>();
+                let mut writer = BufWriter::new(writer);
+                thread::Builder::new()
+                    .name("mtiny-access-log".to_owned())
+                    .spawn(move || {
+                        while let Ok(line) = rx.recv() {
+                            let _ = writeln!(writer, "{line}");
+                            while let Ok(line) = rx.try_recv() {
+                                let _ = writeln!(writer, "{line}");
+                            }
+                            let _ = writer.flush();
+                        }
+                    })
+                    .expect("failed to spawn the access log thread");
+                Sink::Writer(tx)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "log")]
+            Target::Log(level) => f.debug_tuple("Log").field(level).finish(),
+            #[cfg(feature = "tracing")]
+            Target::Tracing => f.write_str("Tracing"),
+            Target::Writer(_) => f.write_str("Writer"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Sink {
+    #[cfg(feature = "log")]
+    Log(log::Level),
+    #[cfg(feature = "tracing")]
+    Tracing,
+    Writer(mpsc::Sender<String>),
+}
+
+#[derive(Debug)]
+struct Config {
+    format: LogFormat,
+    sink: Sink,
+}
+
+impl Config {
+    fn emit(&self, record: &Record) {
+        let line = record.format(self.format);
+        match &self.sink {
+            #[cfg(feature = "log")]
+            Sink::Log(level) => log::log!(target: "mtiny::access", *level, "{line}"),
+            #[cfg(feature = "tracing")]
+            Sink::Tracing => tracing::info!(
+                target: "mtiny::access",
+                method = %record.method,
+                path = %record.path,
+                status = record.status.as_u16(),
+                bytes = record.bytes,
+                latency_ms = record.latency.as_secs_f64() * 1000.0,
+                peer = record.peer.map(tracing::field::display),
+                user_agent = record.user_agent.as_deref(),
+                request_id = record.request_id.as_deref(),
+                "{line}"
+            ),
+            Sink::Writer(tx) => {
+                let _ = tx.send(line);
+            }
+        }
+    }
+}
+
+struct Record {
+    time: SystemTime,
+    method: Method,
+    path: String,
+    version: Version,
+    peer: Option<IpAddr>,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    request_id: Option<String>,
+    status: StatusCode,
+    bytes: u64,
+    latency: Duration,
+}
+
+impl Record {
+    fn new<B>(request: &Request<B>) -> Self {
+        let extensions = request.extensions();
+        let peer = extensions
+            .get::<RealIp>()
+            .map(|ip| ip.0)
+            .or_else(|| extensions.get::<PeerAddr>().map(|peer| peer.0.ip()))
+            .or_else(|| {
+                extensions
+                    .get::<ConnectionInfo>()
+                    .map(|info| info.peer_addr().ip())
+            });
+        let header_str = |name| {
+            request
+                .headers()
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        };
+        Self {
+            time: SystemTime::now(),
+            method: request.method().clone(),
+            path: request
+                .uri()
+                .path_and_query()
+                .map_or_else(|| request.uri().path().to_owned(), ToString::to_string),
+            version: *request.version(),
+            peer,
+            user_agent: header_str(header::USER_AGENT),
+            referer: header_str(header::REFERER),
+            request_id: extensions
+                .get::<RequestId>()
+                .map(|id| id.as_str().to_owned()),
+            status: StatusCode::OK,
+            bytes: 0,
+            latency: Duration::ZERO,
+        }
+    }
+
+    fn format(&self, format: LogFormat) -> String {
+        let mut line = String::new();
+        let _ = match format {
+            LogFormat::Common => self.common(&mut line),
+            LogFormat::Combined => self.common(&mut line).and_then(|_| {
+                write!(
+                    line,
+                    " \"{}\" \"{}\"",
+                    Escaped(self.referer.as_deref().unwrap_or("-")),
+                    Escaped(self.user_agent.as_deref().unwrap_or("-"))
+                )
+            }),
+            LogFormat::Json => self.json(&mut line),
+        };
+        line
+    }
+
+    fn common(&self, line: &mut String) -> fmt::Result {
+        let bytes = match self.bytes {
+            0 => "-".to_owned(),
+            bytes => bytes.to_string(),
+        };
+        write!(
+            line,
+            "{} - - [{}] \"{} {} {:?}\" {} {}",
+            self.peer
+                .map_or_else(|| "-".to_owned(), |ip| ip.to_string()),
+            Timestamp(self.time, false),
+            self.method,
+            Escaped(&self.path),
+            self.version,
+            self.status.as_u16(),
+            bytes
+        )
+    }
+
+    fn json(&self, line: &mut String) -> fmt::Result {
+        let optional = |value: Option<&str>| match value {
+            Some(value) => format!("\"{}\"", Escaped(value)),
+            None => "null".to_owned(),
+        };
+        write!(
+            line,
+            "{{\"time\":\"{}\",\"method\":\"{}\",\"path\":\"{}\",\"version\":\"{:?}\",\
+             \"status\":{},\"bytes\":{},\"latency_ms\":{:.3},\"peer\":{},\
+             \"user_agent\":{},\"referer\":{},\"request_id\":{}}}",
+            Timestamp(self.time, true),
+            Escaped(self.method.as_str()),
+            Escaped(&self.path),
+            self.version,
+            self.status.as_u16(),
+            self.bytes,
+            self.latency.as_secs_f64() * 1000.0,
+            optional(self.peer.map(|ip| ip.to_string()).as_deref()),
+            optional(self.user_agent.as_deref()),
+            optional(self.referer.as_deref()),
+            optional(self.request_id.as_deref())
+        )
+    }
+}
+
+// escapes quotes, backslashes and control characters, valid for both the
+// quoted fields of the common log format and json strings
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// utc timestamps, `10/Oct/2000:13:55:36 +0000` or rfc 3339 when `.1` is set
+struct Timestamp(SystemTime, bool);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let elapsed = self.0.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = elapsed.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        if self.1 {
+            write!(
+                f,
+                "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{:03}Z",
+                elapsed.subsec_millis()
+            )
+        } else {
+            write!(
+                f,
+                "{day:02}/{}/{year:04}:{hour:02}:{minute:02}:{second:02} +0000",
+                MONTHS[month as usize - 1]
+            )
+        }
+    }
+}
+
+// days since 1970-01-01 to a proleptic gregorian date, see
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// the de facto status for requests abandoned by the client
+const CLIENT_CLOSED_REQUEST: StatusCode = match StatusCode::from_u16(499) {
+    Ok(status) => status,
+    Err(_) => unreachable!(),
+};
+
+struct Pending {
+    record: Record,
+    start: Instant,
+    config: Arc<Config>,
+}
+
+impl Pending {
+    fn finish(mut self, bytes: u64) {
+        self.record.bytes = bytes;
+        self.record.latency = self.start.elapsed();
+        self.config.emit(&self.record);
+    }
+}
+
+pub struct AccessLogBody {
+    inner: BoxBody,
+    bytes: u64,
+    pending: Option<Pending>,
+}
+
+impl Body for AccessLogBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let chunk = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        match &chunk {
+            Some(Ok(chunk)) => self.bytes += chunk.len() as u64,
+            Some(Err(_)) | None => {
+                if let Some(pending) = self.pending.take() {
+                    pending.finish(self.bytes);
+                }
+            }
+        }
+        Poll::Ready(chunk)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// bodies that are never polled to the end, e.g. responses to HEAD requests
+// or aborted downloads, are logged with what was sent so far
+impl Drop for AccessLogBody {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.finish(self.bytes);
+        }
+    }
+}
+
+impl fmt::Debug for AccessLogBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessLogBody")
+            .field("inner", &self.inner)
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessLog<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+pub fn access_log() -> AccessLogWarp {
+    AccessLogWarp::new()
+}
+
+impl<S, B> Service<Request<B>> for AccessLog<S>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = AccessLogFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let pending = Pending {
+            record: Record::new(&request),
+            start: Instant::now(),
+            config: self.config.clone(),
+        };
+        AccessLogFuture {
+            fut: self.inner.call(request),
+            pending: Some(pending),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for AccessLog<S>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    pub struct AccessLogFuture<F> {
+        #[pin]
+        fut: F,
+        pending: Option<Pending>,
+    }
+
+    // the handler future was dropped before it produced a response, which
+    // happens when the client goes away, so the request is logged as 499
+    impl<F> PinnedDrop for AccessLogFuture<F> {
+        fn drop(this: Pin<&mut Self>) {
+            if let Some(mut pending) = this.project().pending.take() {
+                pending.record.status = CLIENT_CLOSED_REQUEST;
+                pending.finish(0);
+            }
+        }
+    }
+}
+
+impl<F, T, E> Future for AccessLogFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = ready!(this.fut.poll(cx));
+        let mut pending = this.pending.take().expect("polled after completion");
+        let response = match output {
+            Ok(response) => response.into_response(),
+            Err(err) => {
+                pending.record.status = StatusCode::INTERNAL_SERVER_ERROR;
+                pending.finish(0);
+                return Poll::Ready(Err(err));
+            }
+        };
+        pending.record.status = *response.status();
+        let (head, body) = response.into_head();
+        let body = AccessLogBody {
+            inner: body,
+            bytes: 0,
+            pending: Some(pending),
+        };
+        Poll::Ready(Ok(Response::from_head(head, body.boxed())))
+    }
+}
+
+impl<F> fmt::Debug for AccessLogFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessLogFuture")
+            .field("fut", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct AccessLogWarp {
+    format: LogFormat,
+    target: Target,
+}
+
+impl AccessLogWarp {
+    pub fn new() -> Self {
+        Self {
+            format: LogFormat::default(),
+            target: Target::Writer(Box::new(io::stdout())),
+        }
+    }
+
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    #[cfg(feature = "log")]
+    pub fn to_log(mut self, level: log::Level) -> Self {
+        self.target = Target::Log(level);
+        self
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn to_tracing(mut self) -> Self {
+        self.target = Target::Tracing;
+        self
+    }
+
+    pub fn to_writer<W>(mut self, writer: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        self.target = Target::Writer(Box::new(writer));
+        self
+    }
+}
+
+impl Default for AccessLogWarp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Wrap<S> for AccessLogWarp {
+    type Service = AccessLog<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        AccessLog {
+            inner,
+            config: Arc::new(Config {
+                format: self.format,
+                sink: self.target.start(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    struct Lines(mpsc::Sender<Vec<u8>>);
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let _ = self.0.send(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn next_line(rx: &mpsc::Receiver<Vec<u8>>) -> String {
+        let line = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn escapes_quotes_and_control_characters() {
+        assert_eq!(Escaped("plain /path").to_string(), "plain /path");
+        assert_eq!(Escaped(r#"a "b" \c"#).to_string(), r#"a \"b\" \\c"#);
+        assert_eq!(Escaped("a\nb\r\tc").to_string(), r"a\nb\r\tc");
+        assert_eq!(Escaped("\u{1b}[31m\u{7f}").to_string(), r"\u001b[31m\u007f");
+        assert_eq!(Escaped("é✓").to_string(), "é✓");
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let time = UNIX_EPOCH + Duration::from_millis(971_186_136_042);
+        assert_eq!(
+            Timestamp(time, false).to_string(),
+            "10/Oct/2000:13:55:36 +0000"
+        );
+        assert_eq!(
+            Timestamp(time, true).to_string(),
+            "2000-10-10T13:55:36.042Z"
+        );
+        assert_eq!(
+            Timestamp(UNIX_EPOCH, true).to_string(),
+            "1970-01-01T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(157114), (2400, 3, 1));
+    }
+
+    #[test]
+    fn logs_dropped_handler_futures() {
+        let (tx, rx) = mpsc::channel();
+        let service = service_fn(|_: Request<()>| std::future::pending::<Result<(), Infallible>>())
+            .with(access_log().format(LogFormat::Common).to_writer(Lines(tx)));
+        let mut request = Request::new(());
+        *request.uri_mut() = "/slow".parse().unwrap();
+
+        let mut future = Box::pin(service.call(request));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        drop(future);
+
+        let line = next_line(&rx);
+        assert!(line.starts_with("- - - ["), "{line}");
+        assert!(line.ends_with("] \"GET /slow HTTP/1.1\" 499 -\n"), "{line}");
+    }
+
+    fn logged(format: LogFormat, request: Request<()>) -> String {
+        let (tx, rx) = mpsc::channel();
+        let service = service_fn(|_: Request<()>| async { Ok::<_, Infallible>("access logged") })
+            .with(access_log().format(format).to_writer(Lines(tx)));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let Poll::Ready(Ok(response)) = std::pin::pin!(service.call(request)).poll(&mut cx) else {
+            panic!("service did not complete");
+        };
+        let mut body = response.into_body();
+        assert!(rx.try_recv().is_err(), "logged before the body finished");
+        while let Poll::Ready(Some(chunk)) = std::pin::pin!(body.next()).poll(&mut cx) {
+            chunk.unwrap();
+        }
+        next_line(&rx).trim_end().to_owned()
+    }
+
+    #[test]
+    fn json_lines_describe_the_exchange() {
+        let mut request = Request::builder()
+            .uri("/access?q=1")
+            .header(header::USER_AGENT, "agent \"1.0\"")
+            .body(())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert_cloneable(PeerAddr("127.0.0.1:4000".parse().unwrap()));
+
+        let line = logged(LogFormat::Json, request);
+        assert!(line.starts_with(r#"{"time":""#), "{line}");
+        assert!(
+            line.contains(r#""method":"GET","path":"/access?q=1","version":"HTTP/1.1","#),
+            "{line}"
+        );
+        assert!(line.contains(r#""status":200,"bytes":13,"#), "{line}");
+        assert!(
+            line.ends_with(
+                r#""peer":"127.0.0.1","user_agent":"agent \"1.0\"","referer":null,"request_id":null}"#
+            ),
+            "{line}"
+        );
+    }
+
+    #[test]
+    fn combined_lines_append_referer_and_agent() {
+        let request = Request::builder()
+            .uri("/access")
+            .header(header::REFERER, "https://a.example/")
+            .body(())
+            .unwrap();
+
+        let line = logged(LogFormat::Combined, request);
+        assert!(line.starts_with("- - - ["), "{line}");
+        assert!(
+            line.ends_with(r#""GET /access HTTP/1.1" 200 13 "https://a.example/" "-""#),
+            "{line}"
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use mtiny_core::service::{Service, Wrap};
+use std::task::{Context, Poll};
+
+use mtiny_core::service::{ReadyService, Service, Wrap};
 use mtiny_core::Request;
 
 #[derive(Clone, Copy)]
@@ -20,7 +22,7 @@ impl<S, F, B, T> Service<Request<B>> for AddExtension<S, F>
 where
     S: Service<Request<B>>,
     F: Fn() -> T,
-    T: 'static,
+    T: Clone + Send + Sync + 'static,
 {
     type Response = S::Response;
 
@@ -29,11 +31,22 @@ where
     type Future = S::Future;
 
     fn call(&self, mut request: Request<B>) -> Self::Future {
-        request.extensions_mut().insert((self.f)());
+        request.extensions_mut().insert_cloneable((self.f)());
         self.inner.call(request)
     }
 }
 
+impl<S, F, B, T> ReadyService<Request<B>> for AddExtension<S, F>
+where
+    S: ReadyService<Request<B>>,
+    F: Fn() -> T,
+    T: Clone + Send + Sync + 'static,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
 impl<S, F> core::fmt::Debug for AddExtension<S, F>
 where
     S: core::fmt::Debug,
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::body::{Body, BodyExt, BoxBody, LengthLimitError};
+use mtiny_core::http::header;
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{BoxError, Request, Response};
+
+// inserted into the request so extractors read up to the configured limit
+// instead of their own defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BodyLimit(pub u64);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RequestBodyLimit<S> {
+    inner: S,
+    limit: u64,
+}
+
+pub fn body_limit(limit: u64) -> BodyLimitWarp {
+    BodyLimitWarp::new(limit)
+}
+
+impl<S, B> Service<Request<B>> for RequestBodyLimit<S>
+where
+    S: Service<Request<BoxBody>>,
+    S::Response: IntoResponse,
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BodyLimitFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let content_length = request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if content_length.is_some_and(|length| length > self.limit) {
+            return BodyLimitFuture::Rejected {
+                response: Some(LengthLimitError::new(self.limit).into_response()),
+            };
+        }
+        let limit = self.limit;
+        let mut request = request.map(|body| body.limit(limit).boxed());
+        request.extensions_mut().insert_cloneable(BodyLimit(limit));
+        BodyLimitFuture::Inner {
+            fut: self.inner.call(request),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for RequestBodyLimit<S>
+where
+    S: ReadyService<Request<BoxBody>>,
+    S::Response: IntoResponse,
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    #[project = BodyLimitFutureProj]
+    pub enum BodyLimitFuture<F> {
+        Inner {
+            #[pin]
+            fut: F,
+        },
+        Rejected {
+            response: Option<Response>,
+        },
+    }
+}
+
+impl<F, T, E> Future for BodyLimitFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            BodyLimitFutureProj::Inner { fut } => {
+                Poll::Ready(Ok(ready!(fut.poll(cx))?.into_response()))
+            }
+            BodyLimitFutureProj::Rejected { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for BodyLimitFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyLimitFuture::Inner { .. } => f.debug_struct("BodyLimitFuture::Inner").finish(),
+            BodyLimitFuture::Rejected { .. } => {
+                f.debug_struct("BodyLimitFuture::Rejected").finish()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimitWarp {
+    limit: u64,
+}
+
+impl BodyLimitWarp {
+    pub fn new(limit: u64) -> Self {
+        Self { limit }
+    }
+}
+
+impl<S> Wrap<S> for BodyLimitWarp {
+    type Service = RequestBodyLimit<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        RequestBodyLimit {
+            inner,
+            limit: self.limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use mtiny_core::body::Bytes;
+    use mtiny_core::http::StatusCode;
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    // reads the whole body and answers with it, or with the limit error it hit
+    fn call(request: Request<Bytes>) -> Response {
+        let service = service_fn(|mut request: Request<BoxBody>| async move {
+            let limit = request.extensions().get::<BodyLimit>().unwrap().0;
+            let mut buf = Vec::new();
+            while let Some(chunk) = request.body_mut().next().await {
+                match chunk {
+                    Ok(chunk) => buf.extend_from_slice(&chunk),
+                    Err(err) => {
+                        let err = err.downcast::<LengthLimitError>().unwrap();
+                        assert_eq!(err.limit(), limit);
+                        let mut response = err.into_response();
+                        response
+                            .headers_mut()
+                            .insert("x-streamed", "true".parse().unwrap());
+                        return Ok::<_, Infallible>(response);
+                    }
+                }
+            }
+            Ok(buf.into_response())
+        })
+        .with(body_limit(16));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("service did not complete"),
+        }
+    }
+
+    #[test]
+    fn passes_bodies_within_the_limit() {
+        let response = call(Request::new(Bytes::from_static(b"within the limit")));
+        assert_eq!(*response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("x-streamed"));
+    }
+
+    #[test]
+    fn rejects_declared_lengths_before_the_handler() {
+        let request = Request::builder()
+            .header(header::CONTENT_LENGTH, "17")
+            .body(Bytes::from_static(b"beyond the limit!"))
+            .unwrap();
+        let response = call(request);
+        assert_eq!(*response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!response.headers().contains_key("x-streamed"));
+    }
+
+    #[test]
+    fn fails_streamed_bodies_in_the_handler() {
+        let response = call(Request::new(Bytes::from_static(b"beyond the limit!")));
+        assert_eq!(*response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.headers()["x-streamed"], "true");
+    }
+}
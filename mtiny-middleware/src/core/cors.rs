@@ -0,0 +1,617 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{Request, Response};
+
+#[derive(Clone)]
+pub struct AllowOrigin {
+    kind: OriginKind,
+}
+
+#[derive(Clone)]
+enum OriginKind {
+    Any,
+    List(Vec<HeaderValue>),
+    Predicate(Arc<dyn Fn(&HeaderValue) -> bool + Send + Sync>),
+}
+
+impl AllowOrigin {
+    pub fn any() -> Self {
+        Self {
+            kind: OriginKind::Any,
+        }
+    }
+
+    pub fn exact(origin: HeaderValue) -> Self {
+        Self::list([origin])
+    }
+
+    pub fn list<I>(origins: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderValue>,
+    {
+        Self {
+            kind: OriginKind::List(origins.into_iter().collect()),
+        }
+    }
+
+    pub fn predicate<F>(f: F) -> Self
+    where
+        F: Fn(&HeaderValue) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            kind: OriginKind::Predicate(Arc::new(f)),
+        }
+    }
+
+    fn is_any(&self) -> bool {
+        matches!(self.kind, OriginKind::Any)
+    }
+
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        match &self.kind {
+            OriginKind::Any => true,
+            OriginKind::List(list) => list.contains(origin),
+            OriginKind::Predicate(f) => f(origin),
+        }
+    }
+}
+
+impl Default for AllowOrigin {
+    fn default() -> Self {
+        Self::list([])
+    }
+}
+
+impl From<HeaderValue> for AllowOrigin {
+    fn from(origin: HeaderValue) -> Self {
+        Self::exact(origin)
+    }
+}
+
+impl From<&'static str> for AllowOrigin {
+    fn from(origin: &'static str) -> Self {
+        Self::exact(HeaderValue::from_static(origin))
+    }
+}
+
+impl<const N: usize> From<[HeaderValue; N]> for AllowOrigin {
+    fn from(origins: [HeaderValue; N]) -> Self {
+        Self::list(origins)
+    }
+}
+
+impl From<Vec<HeaderValue>> for AllowOrigin {
+    fn from(origins: Vec<HeaderValue>) -> Self {
+        Self::list(origins)
+    }
+}
+
+impl fmt::Debug for AllowOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            OriginKind::Any => f.write_str("Any"),
+            OriginKind::List(list) => f.debug_tuple("List").field(list).finish(),
+            OriginKind::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
+
+// `None` means any value, sent as `*`
+fn join<T: AsRef<str>>(items: Option<&[T]>) -> Option<HeaderValue> {
+    let Some(items) = items else {
+        return Some(HeaderValue::from_static("*"));
+    };
+    if items.is_empty() {
+        return None;
+    }
+    let joined = items
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join(", ");
+    HeaderValue::from_str(&joined).ok()
+}
+
+#[derive(Debug, Clone, Default)]
+struct Config {
+    origin: AllowOrigin,
+    methods: Option<Vec<Method>>,
+    headers: Option<Vec<HeaderName>>,
+    expose: Option<Vec<HeaderName>>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Config {
+    fn validate(&self) {
+        if !self.credentials {
+            return;
+        }
+        let wildcard = [
+            (self.origin.is_any(), "access-control-allow-origin"),
+            (self.methods.is_none(), "access-control-allow-methods"),
+            (self.headers.is_none(), "access-control-allow-headers"),
+            (self.expose.is_none(), "access-control-expose-headers"),
+        ];
+        for (any, name) in wildcard {
+            if any {
+                panic!(
+                    "invalid cors configuration: `access-control-allow-credentials: true` \
+                     can not be combined with `{name}: *`"
+                );
+            }
+        }
+    }
+
+    fn allowed_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        if self.origin.is_any() {
+            return Some(HeaderValue::from_static("*"));
+        }
+        origin.filter(|origin| self.origin.allows(origin)).cloned()
+    }
+
+    fn vary(&self, headers: &mut HeaderMap, preflight: bool) {
+        let mut names = vec![];
+        if !self.origin.is_any() {
+            names.push("origin");
+        }
+        if preflight {
+            names.push("access-control-request-method");
+            names.push("access-control-request-headers");
+        }
+        for name in names {
+            let present = headers
+                .get_all(header::VARY)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|value| {
+                    let value = value.trim();
+                    value == "*" || value.eq_ignore_ascii_case(name)
+                });
+            if !present {
+                headers.append(header::VARY, HeaderValue::from_static(name));
+            }
+        }
+    }
+
+    fn preflight(&self, origin: Option<&HeaderValue>) -> Response {
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let headers = response.headers_mut();
+        self.vary(headers, true);
+        let Some(origin) = self.allowed_origin(origin) else {
+            return response;
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        if let Some(methods) = join(self.methods.as_deref()) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if let Some(allowed) = join(self.headers.as_deref()) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed);
+        }
+        if let Some(max_age) = self.max_age {
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+        response
+    }
+
+    fn actual(&self, origin: Option<&HeaderValue>, headers: &mut HeaderMap) {
+        self.vary(headers, false);
+        let Some(origin) = self.allowed_origin(origin) else {
+            return;
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        if let Some(expose) = join(self.expose.as_deref()) {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cors<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+pub fn cors() -> CorsWarp {
+    CorsWarp::new()
+}
+
+impl<S, B> Service<Request<B>> for Cors<S>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = CorsFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let origin = request.headers().get(header::ORIGIN).cloned();
+        if *request.method() == Method::OPTIONS
+            && origin.is_some()
+            && request
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            return CorsFuture::Preflight {
+                response: Some(self.config.preflight(origin.as_ref())),
+            };
+        }
+        CorsFuture::Actual {
+            fut: self.inner.call(request),
+            origin,
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for Cors<S>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    #[project = CorsFutureProj]
+    pub enum CorsFuture<F> {
+        Actual {
+            #[pin]
+            fut: F,
+            origin: Option<HeaderValue>,
+            config: Arc<Config>,
+        },
+        Preflight {
+            response: Option<Response>,
+        },
+    }
+}
+
+impl<F, T, E> Future for CorsFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            CorsFutureProj::Actual {
+                fut,
+                origin,
+                config,
+            } => {
+                let mut response = ready!(fut.poll(cx))?.into_response();
+                config.actual(origin.as_ref(), response.headers_mut());
+                Poll::Ready(Ok(response))
+            }
+            CorsFutureProj::Preflight { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for CorsFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorsFuture::Actual { origin, .. } => f
+                .debug_struct("CorsFuture::Actual")
+                .field("origin", origin)
+                .finish(),
+            CorsFuture::Preflight { .. } => f.debug_struct("CorsFuture::Preflight").finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CorsWarp {
+    config: Config,
+}
+
+impl CorsWarp {
+    pub fn new() -> Self {
+        Self {
+            config: Config {
+                methods: Some(vec![Method::GET, Method::HEAD, Method::POST]),
+                headers: Some(vec![]),
+                expose: Some(vec![]),
+                ..Config::default()
+            },
+        }
+    }
+
+    pub fn allow_origin<O>(mut self, origin: O) -> Self
+    where
+        O: Into<AllowOrigin>,
+    {
+        self.config.origin = origin.into();
+        self
+    }
+
+    pub fn allow_any_origin(self) -> Self {
+        self.allow_origin(AllowOrigin::any())
+    }
+
+    pub fn allow_methods<I>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        self.config.methods = Some(methods.into_iter().collect());
+        self
+    }
+
+    pub fn allow_any_method(mut self) -> Self {
+        self.config.methods = None;
+        self
+    }
+
+    pub fn allow_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+    {
+        self.config.headers = Some(headers.into_iter().collect());
+        self
+    }
+
+    pub fn allow_any_header(mut self) -> Self {
+        self.config.headers = None;
+        self
+    }
+
+    pub fn expose_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+    {
+        self.config.expose = Some(headers.into_iter().collect());
+        self
+    }
+
+    pub fn expose_any_header(mut self) -> Self {
+        self.config.expose = None;
+        self
+    }
+
+    pub fn allow_credentials(mut self, allow: bool) -> Self {
+        self.config.credentials = allow;
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.config.max_age = Some(max_age);
+        self
+    }
+}
+
+impl Default for CorsWarp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Wrap<S> for CorsWarp {
+    type Service = Cors<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        self.config.validate();
+        Cors {
+            inner,
+            config: Arc::new(self.config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    fn config(warp: CorsWarp) -> Config {
+        warp.config.validate();
+        warp.config
+    }
+
+    fn vary(headers: &HeaderMap) -> Vec<&str> {
+        headers
+            .get_all(header::VARY)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn join_values() {
+        assert_eq!(join::<&str>(None).unwrap(), "*");
+        assert_eq!(join::<&str>(Some(&[])), None);
+        assert_eq!(join(Some(&["a", "b"])).unwrap(), "a, b");
+    }
+
+    #[test]
+    fn preflight_allowed_origin() {
+        let config = config(
+            cors()
+                .allow_origin("https://a.example")
+                .allow_headers([header::CONTENT_TYPE])
+                .max_age(Duration::from_secs(600)),
+        );
+        let origin = HeaderValue::from_static("https://a.example");
+        let response = config.preflight(Some(&origin));
+        let headers = response.headers();
+
+        assert_eq!(*response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://a.example"
+        );
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "GET, HEAD, POST"
+        );
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        assert_eq!(
+            vary(headers),
+            [
+                "origin",
+                "access-control-request-method",
+                "access-control-request-headers"
+            ]
+        );
+    }
+
+    #[test]
+    fn preflight_rejected_origin() {
+        let config = config(cors().allow_origin("https://a.example"));
+        let origin = HeaderValue::from_static("https://b.example");
+        let response = config.preflight(Some(&origin));
+
+        assert_eq!(*response.status(), StatusCode::NO_CONTENT);
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(vary(response.headers()).len(), 3);
+    }
+
+    #[test]
+    fn actual_keeps_existing_vary() {
+        let config = config(
+            cors()
+                .allow_origin(AllowOrigin::predicate(|origin| {
+                    origin.as_bytes().ends_with(b".example")
+                }))
+                .expose_headers([header::ETAG])
+                .allow_credentials(true),
+        );
+        let origin = HeaderValue::from_static("https://a.example");
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+        config.actual(Some(&origin), &mut headers);
+
+        assert_eq!(vary(&headers), ["Origin"]);
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://a.example"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[header::ACCESS_CONTROL_EXPOSE_HEADERS], "etag");
+    }
+
+    #[test]
+    fn preflight_with_credentials_lists_methods() {
+        let config = config(
+            cors()
+                .allow_origin("https://a.example")
+                .allow_methods([Method::GET, Method::PUT])
+                .allow_credentials(true),
+        );
+        let origin = HeaderValue::from_static("https://a.example");
+        let response = config.preflight(Some(&origin));
+        let headers = response.headers();
+
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, PUT");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
+    #[test]
+    fn actual_requests_reach_inner_service() {
+        let service = service_fn(|_: Request<()>| async { Ok::<_, Infallible>("inner") })
+            .with(cors().allow_origin("https://a.example"));
+        let call = |origin: &'static str| {
+            let request = Request::builder()
+                .header(header::ORIGIN, origin)
+                .body(())
+                .unwrap();
+            let mut cx = Context::from_waker(Waker::noop());
+            match std::pin::pin!(service.call(request)).poll(&mut cx) {
+                Poll::Ready(Ok(response)) => response,
+                _ => panic!("service did not complete"),
+            }
+        };
+
+        let response = call("https://a.example");
+        assert_eq!(*response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://a.example"
+        );
+        assert_eq!(vary(response.headers()), ["origin"]);
+
+        let response = call("https://evil.example");
+        assert_eq!(*response.status(), StatusCode::OK);
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(vary(response.headers()), ["origin"]);
+    }
+
+    #[test]
+    fn any_origin_is_a_wildcard() {
+        let config = config(cors().allow_any_origin());
+        let mut headers = HeaderMap::new();
+        config.actual(None, &mut headers);
+
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(header::VARY));
+    }
+
+    #[test]
+    #[should_panic(expected = "can not be combined with `access-control-allow-origin: *`")]
+    fn credentials_reject_wildcards() {
+        let _ = cors().allow_any_origin().allow_credentials(true).wrap(());
+    }
+
+    #[test]
+    fn preflight_skips_inner_service() {
+        let service = service_fn(|_: Request<()>| async { Err::<(), _>("inner service called") })
+            .with(cors().allow_any_origin());
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://a.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .body(())
+            .unwrap();
+
+        let mut cx = Context::from_waker(Waker::noop());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => {
+                assert_eq!(*response.status(), StatusCode::NO_CONTENT);
+                assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+            }
+            _ => panic!("preflight did not complete"),
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{BoxError, Request, Response};
+
+pub fn from_fn<F>(f: F) -> FromFnWarp<F> {
+    FromFnWarp::new(f)
+}
+
+pub fn from_fn_with_state<F, T>(state: T, f: F) -> FromFnWithStateWarp<F, T> {
+    FromFnWithStateWarp::new(state, f)
+}
+
+pub fn from_fn_with<X, F>(extract: X, f: F) -> FromFnWithWarp<X, F> {
+    FromFnWithWarp::new(extract, f)
+}
+
+// the inner service is shared with every handle, `run` hands back its future
+// as is instead of boxing it
+pub struct Next<S> {
+    inner: Arc<S>,
+}
+
+impl<S> Next<S> {
+    pub fn run<B>(self, request: Request<B>) -> FromFnFuture<S::Future>
+    where
+        S: Service<Request<B>>,
+        S::Response: IntoResponse,
+        S::Error: Into<BoxError>,
+    {
+        FromFnFuture {
+            fut: self.inner.call(request),
+        }
+    }
+}
+
+impl<S> Clone for Next<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S> core::fmt::Debug for Next<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next")
+            .field("inner", &core::any::type_name::<S>())
+            .finish()
+    }
+}
+
+pub struct FromFn<S, F> {
+    inner: Arc<S>,
+    f: F,
+}
+
+impl<S, F> FromFn<S, F> {
+    pub fn new(inner: S, f: F) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            f,
+        }
+    }
+
+    fn next(&self) -> Next<S> {
+        Next {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, F> Clone for FromFn<S, F>
+where
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<S, F, B, Fut, T, E> Service<Request<B>> for FromFn<S, F>
+where
+    S: Service<Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    F: Fn(Request<B>, Next<S>) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+    E: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = BoxError;
+    type Future = FromFnFuture<Fut>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        FromFnFuture {
+            fut: (self.f)(request, self.next()),
+        }
+    }
+}
+
+impl<S, F, B, Fut, T, E> ReadyService<Request<B>> for FromFn<S, F>
+where
+    S: ReadyService<Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    F: Fn(Request<B>, Next<S>) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+    E: Into<BoxError>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+}
+
+impl<S, F> core::fmt::Debug for FromFn<S, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFn")
+            .field("inner", &core::any::type_name::<S>())
+            .field("f", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+pub struct FromFnWithState<S, F, T> {
+    inner: FromFn<S, F>,
+    state: T,
+}
+
+impl<S, F, T> FromFnWithState<S, F, T> {
+    pub fn new(inner: S, state: T, f: F) -> Self {
+        Self {
+            inner: FromFn::new(inner, f),
+            state,
+        }
+    }
+}
+
+impl<S, F, T> Clone for FromFnWithState<S, F, T>
+where
+    F: Clone,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S, F, T, B, Fut, R, E> Service<Request<B>> for FromFnWithState<S, F, T>
+where
+    S: Service<Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    F: Fn(T, Request<B>, Next<S>) -> Fut,
+    T: Clone,
+    Fut: Future<Output = Result<R, E>>,
+    R: IntoResponse,
+    E: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = BoxError;
+    type Future = FromFnFuture<Fut>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        FromFnFuture {
+            fut: (self.inner.f)(self.state.clone(), request, self.inner.next()),
+        }
+    }
+}
+
+impl<S, F, T, B, Fut, R, E> ReadyService<Request<B>> for FromFnWithState<S, F, T>
+where
+    S: ReadyService<Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    F: Fn(T, Request<B>, Next<S>) -> Fut,
+    T: Clone,
+    Fut: Future<Output = Result<R, E>>,
+    R: IntoResponse,
+    E: Into<BoxError>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.inner.poll_ready(cx).map_err(Into::into)
+    }
+}
+
+impl<S, F, T> core::fmt::Debug for FromFnWithState<S, F, T>
+where
+    T: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFnWithState")
+            .field("inner", &core::any::type_name::<S>())
+            .field("f", &core::any::type_name::<F>())
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+// `extract` runs on the request before it is handed over, the same way
+// `handle_error_with` takes its extractor
+pub struct FromFnWith<S, X, F> {
+    inner: FromFn<S, F>,
+    extract: X,
+}
+
+impl<S, X, F> FromFnWith<S, X, F> {
+    pub fn new(inner: S, extract: X, f: F) -> Self {
+        Self {
+            inner: FromFn::new(inner, f),
+            extract,
+        }
+    }
+}
+
+impl<S, X, F> Clone for FromFnWith<S, X, F>
+where
+    X: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            extract: self.extract.clone(),
+        }
+    }
+}
+
+impl<S, X, F, B, T, Fut, R, E> Service<Request<B>> for FromFnWith<S, X, F>
+where
+    S: Service<Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    X: Fn(&Request<B>) -> T,
+    F: Fn(T, Request<B>, Next<S>) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+    R: IntoResponse,
+    E: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = BoxError;
+    type Future = FromFnFuture<Fut>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let value = (self.extract)(&request);
+        FromFnFuture {
+            fut: (self.inner.f)(value, request, self.inner.next()),
+        }
+    }
+}
+
+impl<S, X, F, B, T, Fut, R, E> ReadyService<Request<B>> for FromFnWith<S, X, F>
+where
+    S: ReadyService<Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+    X: Fn(&Request<B>) -> T,
+    F: Fn(T, Request<B>, Next<S>) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+    R: IntoResponse,
+    E: Into<BoxError>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.inner.poll_ready(cx).map_err(Into::into)
+    }
+}
+
+impl<S, X, F> core::fmt::Debug for FromFnWith<S, X, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFnWith")
+            .field("inner", &core::any::type_name::<S>())
+            .field("extract", &core::any::type_name::<X>())
+            .field("f", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+pin_project! {
+    pub struct FromFnFuture<Fut> {
+        #[pin]
+        fut: Fut,
+    }
+}
+
+impl<Fut, T, E> Future for FromFnFuture<Fut>
+where
+    Fut: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+    E: Into<BoxError>,
+{
+    type Output = Result<Response, BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let output = ready!(self.project().fut.poll(cx));
+        Poll::Ready(output.map(IntoResponse::into_response).map_err(Into::into))
+    }
+}
+
+impl<Fut> core::fmt::Debug for FromFnFuture<Fut> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFnFuture")
+            .field("fut", &core::any::type_name::<Fut>())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FromFnWarp<F> {
+    f: F,
+}
+
+impl<F> FromFnWarp<F> {
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F, S> Wrap<S> for FromFnWarp<F> {
+    type Service = FromFn<S, F>;
+    fn wrap(self, service: S) -> Self::Service {
+        FromFn::new(service, self.f)
+    }
+}
+
+impl<F> core::fmt::Debug for FromFnWarp<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFnWarp")
+            .field("f", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FromFnWithStateWarp<F, T> {
+    f: F,
+    state: T,
+}
+
+impl<F, T> FromFnWithStateWarp<F, T> {
+    pub fn new(state: T, f: F) -> Self {
+        Self { f, state }
+    }
+}
+
+impl<F, T, S> Wrap<S> for FromFnWithStateWarp<F, T> {
+    type Service = FromFnWithState<S, F, T>;
+    fn wrap(self, service: S) -> Self::Service {
+        FromFnWithState::new(service, self.state, self.f)
+    }
+}
+
+impl<F, T> core::fmt::Debug for FromFnWithStateWarp<F, T>
+where
+    T: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFnWithStateWarp")
+            .field("f", &core::any::type_name::<F>())
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FromFnWithWarp<X, F> {
+    extract: X,
+    f: F,
+}
+
+impl<X, F> FromFnWithWarp<X, F> {
+    pub fn new(extract: X, f: F) -> Self {
+        Self { extract, f }
+    }
+}
+
+impl<X, F, S> Wrap<S> for FromFnWithWarp<X, F> {
+    type Service = FromFnWith<S, X, F>;
+    fn wrap(self, service: S) -> Self::Service {
+        FromFnWith::new(service, self.extract, self.f)
+    }
+}
+
+impl<X, F> core::fmt::Debug for FromFnWithWarp<X, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FromFnWithWarp")
+            .field("extract", &core::any::type_name::<X>())
+            .field("f", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use mtiny_core::http::StatusCode;
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    fn call<S>(service: &S, request: Request<()>) -> Response
+    where
+        S: Service<Request<()>, Response = Response, Error = BoxError>,
+    {
+        let mut cx = Context::from_waker(Waker::noop());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("service did not complete"),
+        }
+    }
+
+    fn inner() -> impl Service<Request<()>, Response = StatusCode, Error = Infallible> + Send + Sync
+    {
+        service_fn(|request: Request<()>| async move {
+            match request.headers().contains_key("x-seen") {
+                true => Ok(StatusCode::ACCEPTED),
+                false => Ok(StatusCode::OK),
+            }
+        })
+    }
+
+    #[test]
+    fn runs_inner_service() {
+        let service = inner().with(from_fn(|mut request: Request<()>, next: Next<_>| {
+            request.headers_mut().insert("x-seen", "1".parse().unwrap());
+            next.run(request)
+        }));
+        assert_eq!(
+            *call(&service, Request::new(())).status(),
+            StatusCode::ACCEPTED
+        );
+    }
+
+    #[test]
+    fn state_and_extractors() {
+        let service = inner()
+            .with(from_fn_with(
+                |request: &Request<()>| request.headers().contains_key("x-deny"),
+                |deny: bool, request: Request<()>, next: Next<_>| async move {
+                    if deny {
+                        return Ok::<_, BoxError>(StatusCode::FORBIDDEN.into_response());
+                    }
+                    next.run(request).await
+                },
+            ))
+            .with(from_fn_with_state(
+                StatusCode::IM_A_TEAPOT,
+                |status: StatusCode, request: Request<()>, next: Next<_>| async move {
+                    if request.headers().contains_key("x-short") {
+                        return Ok::<_, BoxError>(status.into_response());
+                    }
+                    next.run(request).await
+                },
+            ));
+        let request = |name: &'static str| Request::builder().header(name, "1").body(()).unwrap();
+
+        assert_eq!(*call(&service, Request::new(())).status(), StatusCode::OK);
+        assert_eq!(
+            *call(&service, request("x-deny")).status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            *call(&service, request("x-short")).status(),
+            StatusCode::IM_A_TEAPOT
+        );
+    }
+
+    #[test]
+    fn edits_the_inner_response() {
+        let service = inner().with(from_fn_with_state(
+            "guard",
+            |state: &'static str, request: Request<()>, next: Next<_>| async move {
+                let mut response = next.run(request).await?;
+                response
+                    .headers_mut()
+                    .insert("x-state", state.parse().unwrap());
+                Ok::<_, BoxError>(response)
+            },
+        ));
+        let response = call(&service, Request::new(()));
+        assert_eq!(*response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-state"], "guard");
+    }
+
+    #[test]
+    fn next_is_send_for_shared_services() {
+        fn assert_send<T: Send>(_: &T) {}
+        let service = inner().with(from_fn(|request: Request<()>, next: Next<_>| {
+            assert_send(&next);
+            next.run(request)
+        }));
+        call(&service, Request::new(()));
+    }
+}
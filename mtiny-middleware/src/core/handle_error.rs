@@ -1,17 +1,23 @@
 use std::convert::Infallible;
 
 use futures_core::ready;
-use futures_core::task::Poll;
+use futures_core::task::{Context, Poll};
 use futures_core::Future;
 
 use pin_project_lite::pin_project;
 
+use mtiny_core::service::ReadyService;
 use mtiny_core::service::Service;
 use mtiny_core::service::Wrap;
+use mtiny_core::Request;
 
 pub fn handle_error<F>(f: F) -> HandleErrorWap<F> {
     HandleErrorWap::new(f)
 }
+
+pub fn handle_error_with<X, F>(extract: X, f: F) -> HandleErrorWithWarp<X, F> {
+    HandleErrorWithWarp::new(extract, f)
+}
 #[derive(Clone, Copy)]
 pub struct HandleError<S, F> {
     inner: S,
@@ -40,6 +46,20 @@ where
     }
 }
 
+impl<S, F, Req> ReadyService<Req> for HandleError<S, F>
+where
+    S: ReadyService<Req>,
+    F: FnOnce(S::Error) -> S::Response + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // a readiness error has no response to turn into, so the call reports it instead
+        match self.inner.poll_ready(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
 impl<S, F> core::fmt::Debug for HandleError<S, F>
 where
     S: core::fmt::Debug,
@@ -118,3 +138,133 @@ impl<F> core::fmt::Debug for HandleErrorWap<F> {
             .finish()
     }
 }
+
+#[derive(Clone, Copy)]
+pub struct HandleErrorWith<S, X, F> {
+    inner: S,
+    extract: X,
+    f: F,
+}
+
+impl<S, X, F> HandleErrorWith<S, X, F> {
+    pub fn new(inner: S, extract: X, f: F) -> Self {
+        Self { inner, extract, f }
+    }
+}
+
+impl<S, X, F, B, T> Service<Request<B>> for HandleErrorWith<S, X, F>
+where
+    S: Service<Request<B>>,
+    X: Fn(&Request<B>) -> T,
+    F: FnOnce(T, S::Error) -> S::Response + Clone,
+{
+    type Response = S::Response;
+    type Error = Infallible;
+    type Future = HandleErrorWithFuture<S::Future, F, T>;
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let value = (self.extract)(&request);
+        HandleErrorWithFuture::Incomplete {
+            fut: self.inner.call(request),
+            f: self.f.clone(),
+            value,
+        }
+    }
+}
+
+impl<S, X, F, B, T> ReadyService<Request<B>> for HandleErrorWith<S, X, F>
+where
+    S: ReadyService<Request<B>>,
+    X: Fn(&Request<B>) -> T,
+    F: FnOnce(T, S::Error) -> S::Response + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // a readiness error has no response to turn into, so the call reports it instead
+        match self.inner.poll_ready(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl<S, X, F> core::fmt::Debug for HandleErrorWith<S, X, F>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandleErrorWith")
+            .field("inner", &self.inner)
+            .field("extract", &core::any::type_name::<X>())
+            .field("f", &core::any::type_name::<F>())
+            .finish()
+    }
+}
+
+pin_project! {
+    #[project = HandleErrorWithFutureProj]
+    #[project_replace = HandleErrorWithFutureProjReplace]
+    pub enum HandleErrorWithFuture<Fut, F, T> {
+        Incomplete {
+            #[pin]
+            fut: Fut,
+            f: F,
+            value: T,
+        },
+        Complete,
+    }
+}
+
+impl<Fut, F, T, Res, Err> Future for HandleErrorWithFuture<Fut, F, T>
+where
+    Fut: Future<Output = Result<Res, Err>>,
+    F: FnOnce(T, Err) -> Res,
+{
+    type Output = Result<Res, Infallible>;
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        match self.as_mut().project() {
+            HandleErrorWithFutureProj::Incomplete { fut, .. } => {
+                let output = ready!(fut.poll(cx));
+                match self.project_replace(HandleErrorWithFuture::Complete) {
+                    HandleErrorWithFutureProjReplace::Incomplete { f, value, .. } => match output {
+                        Ok(res) => Poll::Ready(Ok(res)),
+                        Err(err) => Poll::Ready(Ok(f(value, err))),
+                    },
+                    HandleErrorWithFutureProjReplace::Complete => unreachable!(),
+                }
+            }
+            HandleErrorWithFutureProj::Complete => {
+                panic!("polled after completion")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct HandleErrorWithWarp<X, F> {
+    extract: X,
+    f: F,
+}
+
+impl<X, F> HandleErrorWithWarp<X, F> {
+    pub fn new(extract: X, f: F) -> Self {
+        Self { extract, f }
+    }
+}
+
+impl<X, F, S> Wrap<S> for HandleErrorWithWarp<X, F> {
+    type Service = HandleErrorWith<S, X, F>;
+    fn wrap(self, service: S) -> Self::Service {
+        HandleErrorWith::new(service, self.extract, self.f)
+    }
+}
+
+impl<X, F> core::fmt::Debug for HandleErrorWithWarp<X, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandleErrorWithWarp")
+            .field("extract", &core::any::type_name::<X>())
+            .field("f", &core::any::type_name::<F>())
+            .finish()
+    }
+}
@@ -1,5 +1,25 @@
+pub mod access_log;
 pub mod add_extension;
+pub mod body_limit;
+pub mod cors;
+pub mod from_fn;
 pub mod handle_error;
+pub mod rate_limit;
+pub mod request_id;
+#[cfg(feature = "tracing")]
+pub mod trace;
+pub mod trace_context;
+pub mod trusted_proxy;
 
+pub use access_log::access_log;
 pub use add_extension::add_extension;
-pub use handle_error::handle_error;
\ No newline at end of file
+pub use body_limit::body_limit;
+pub use cors::cors;
+pub use from_fn::{from_fn, from_fn_with, from_fn_with_state, Next};
+pub use handle_error::{handle_error, handle_error_with};
+pub use rate_limit::rate_limit;
+pub use request_id::{request_id, RequestId};
+#[cfg(feature = "tracing")]
+pub use trace::trace;
+pub use trace_context::{trace_context, TraceContext};
+pub use trusted_proxy::trusted_proxy;
\ No newline at end of file
@@ -0,0 +1,699 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::http::{header, Extensions, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{ConnectionInfo, PeerAddr, Request, Response};
+
+use super::trusted_proxy::RealIp;
+
+const RATELIMIT_LIMIT: &str = "ratelimit-limit";
+const RATELIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATELIMIT_RESET: &str = "ratelimit-reset";
+const RATELIMIT_POLICY: &str = "ratelimit-policy";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidQuota;
+
+impl fmt::Display for InvalidQuota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a rate limit quota needs a non zero period")
+    }
+}
+
+impl std::error::Error for InvalidQuota {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quota {
+    limit: NonZeroU32,
+    period: Duration,
+}
+
+impl Quota {
+    pub fn new(limit: NonZeroU32, period: Duration) -> Result<Self, InvalidQuota> {
+        if period.is_zero() {
+            return Err(InvalidQuota);
+        }
+        Ok(Self { limit, period })
+    }
+
+    pub const fn per_second(limit: NonZeroU32) -> Self {
+        Self {
+            limit,
+            period: Duration::from_secs(1),
+        }
+    }
+
+    pub const fn per_minute(limit: NonZeroU32) -> Self {
+        Self {
+            limit,
+            period: Duration::from_secs(60),
+        }
+    }
+
+    pub const fn per_hour(limit: NonZeroU32) -> Self {
+        Self {
+            limit,
+            period: Duration::from_secs(60 * 60),
+        }
+    }
+
+    pub fn limit(&self) -> NonZeroU32 {
+        self.limit
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    fn emission_interval(&self) -> Duration {
+        (self.period / self.limit.get()).max(Duration::from_nanos(1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub remaining: u32,
+    pub reset: Duration,
+    pub retry_after: Duration,
+}
+
+// generic cell rate algorithm, `tat` is the theoretical arrival time of the
+// next request, a key is over its quota once that lies more than the whole
+// burst ahead of now
+pub fn gcra(tat: Option<Instant>, quota: &Quota, now: Instant) -> (Decision, Option<Instant>) {
+    let interval = quota.emission_interval();
+    let tolerance = quota.period;
+    let tat = tat.filter(|tat| *tat > now).unwrap_or(now);
+    let next = tat + interval;
+    let ahead = next - now;
+    if ahead > tolerance {
+        let decision = Decision {
+            allowed: false,
+            remaining: 0,
+            reset: tat - now,
+            retry_after: ahead - tolerance,
+        };
+        return (decision, None);
+    }
+    let remaining = ((tolerance - ahead).as_nanos() / interval.as_nanos().max(1)) as u32;
+    let decision = Decision {
+        allowed: true,
+        remaining: remaining.min(quota.limit.get() - 1),
+        reset: ahead,
+        retry_after: Duration::ZERO,
+    };
+    (decision, Some(next))
+}
+
+pub trait RateLimitStore: Send + Sync {
+    fn acquire(&self, key: &str, quota: &Quota, now: Instant) -> Decision;
+}
+
+impl<S> RateLimitStore for Arc<S>
+where
+    S: RateLimitStore + ?Sized,
+{
+    fn acquire(&self, key: &str, quota: &Quota, now: Instant) -> Decision {
+        (**self).acquire(key, quota, now)
+    }
+}
+
+struct Shard {
+    cells: HashMap<String, Instant>,
+    ops: u32,
+}
+
+// prunes a shard every so many acquisitions, keys whose arrival time has
+// passed hold no state worth keeping
+const PRUNE_EVERY: u32 = 1024;
+
+pub struct MemoryStore {
+    shards: Box<[Mutex<Shard>]>,
+    hasher: RandomState,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let shards = std::thread::available_parallelism()
+            .map(|n| n.get() * 4)
+            .unwrap_or(16);
+        Self::with_shards(shards)
+    }
+
+    pub fn with_shards(shards: usize) -> Self {
+        let shards = (0..shards.max(1))
+            .map(|_| {
+                Mutex::new(Shard {
+                    cells: HashMap::new(),
+                    ops: 0,
+                })
+            })
+            .collect();
+        Self {
+            shards,
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, key: &str) -> &Mutex<Shard> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        &self.shards[index]
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimitStore for MemoryStore {
+    fn acquire(&self, key: &str, quota: &Quota, now: Instant) -> Decision {
+        let mut shard = self
+            .shard(key)
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        shard.ops += 1;
+        if shard.ops >= PRUNE_EVERY {
+            shard.ops = 0;
+            shard.cells.retain(|_, tat| *tat > now);
+        }
+        let (decision, tat) = gcra(shard.cells.get(key).copied(), quota, now);
+        if let Some(tat) = tat {
+            match shard.cells.get_mut(key) {
+                Some(cell) => *cell = tat,
+                None => {
+                    shard.cells.insert(key.to_owned(), tat);
+                }
+            }
+        }
+        decision
+    }
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("shards", &self.shards.len())
+            .finish()
+    }
+}
+
+type ExtractKey = dyn Fn(&Extensions) -> Option<String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct RateLimitKey {
+    kind: KeyKind,
+}
+
+#[derive(Clone)]
+enum KeyKind {
+    Global,
+    PeerAddr,
+    RealIp,
+    Header(HeaderName),
+    Extension(Arc<ExtractKey>),
+}
+
+fn peer_ip(extensions: &Extensions) -> Option<IpAddr> {
+    extensions
+        .get::<PeerAddr>()
+        .map(|peer| peer.0.ip())
+        .or_else(|| {
+            extensions
+                .get::<ConnectionInfo>()
+                .map(|info| info.peer_addr().ip())
+        })
+}
+
+impl RateLimitKey {
+    pub fn global() -> Self {
+        Self {
+            kind: KeyKind::Global,
+        }
+    }
+
+    pub fn peer_addr() -> Self {
+        Self {
+            kind: KeyKind::PeerAddr,
+        }
+    }
+
+    pub fn real_ip() -> Self {
+        Self {
+            kind: KeyKind::RealIp,
+        }
+    }
+
+    pub fn header(name: HeaderName) -> Self {
+        Self {
+            kind: KeyKind::Header(name),
+        }
+    }
+
+    pub fn extension<T>() -> Self
+    where
+        T: fmt::Display + Send + Sync + 'static,
+    {
+        Self {
+            kind: KeyKind::Extension(Arc::new(|extensions| {
+                extensions.get::<T>().map(ToString::to_string)
+            })),
+        }
+    }
+
+    // `None` leaves the request unlimited
+    fn extract<B>(&self, request: &Request<B>) -> Option<String> {
+        let extensions = request.extensions();
+        match &self.kind {
+            KeyKind::Global => Some(String::new()),
+            KeyKind::PeerAddr => peer_ip(extensions).map(|ip| ip.to_string()),
+            KeyKind::RealIp => extensions
+                .get::<RealIp>()
+                .map(|ip| ip.0)
+                .or_else(|| peer_ip(extensions))
+                .map(|ip| ip.to_string()),
+            KeyKind::Header(name) => request
+                .headers()
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned()),
+            KeyKind::Extension(f) => f(extensions),
+        }
+    }
+}
+
+impl Default for RateLimitKey {
+    fn default() -> Self {
+        Self::real_ip()
+    }
+}
+
+impl fmt::Debug for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            KeyKind::Global => f.write_str("Global"),
+            KeyKind::PeerAddr => f.write_str("PeerAddr"),
+            KeyKind::RealIp => f.write_str("RealIp"),
+            KeyKind::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            KeyKind::Extension(_) => f.write_str("Extension"),
+        }
+    }
+}
+
+// whole seconds, rounded up so clients never retry too early
+fn seconds(duration: Duration) -> HeaderValue {
+    let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    secs.into()
+}
+
+fn headers(quota: &Quota, decision: &Decision, headers: &mut HeaderMap) {
+    headers.insert(
+        HeaderName::from_static(RATELIMIT_LIMIT),
+        quota.limit.get().into(),
+    );
+    headers.insert(
+        HeaderName::from_static(RATELIMIT_REMAINING),
+        decision.remaining.into(),
+    );
+    headers.insert(
+        HeaderName::from_static(RATELIMIT_RESET),
+        seconds(decision.reset),
+    );
+    if let Ok(policy) = HeaderValue::from_str(&format!(
+        "{};w={}",
+        quota.limit,
+        quota.period.as_secs().max(1)
+    )) {
+        headers.insert(HeaderName::from_static(RATELIMIT_POLICY), policy);
+    }
+    if !decision.allowed {
+        headers.insert(header::RETRY_AFTER, seconds(decision.retry_after));
+    }
+}
+
+#[derive(Clone)]
+struct Config {
+    quota: Quota,
+    key: RateLimitKey,
+    scope: String,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl Config {
+    // a store can be shared between routes, so keys are namespaced by scope
+    // and quota and one route never drains another route's budget
+    fn store_key(&self, key: &str) -> String {
+        format!(
+            "{}|{}/{:?}|{key}",
+            self.scope, self.quota.limit, self.quota.period
+        )
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("quota", &self.quota)
+            .field("key", &self.key)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    config: Config,
+}
+
+pub fn rate_limit(quota: Quota) -> RateLimitWarp {
+    RateLimitWarp::new(quota)
+}
+
+impl<S, B> Service<Request<B>> for RateLimit<S>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = RateLimitFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let Some(key) = self.config.key.extract(&request) else {
+            return RateLimitFuture::Allowed {
+                fut: self.inner.call(request),
+                quota: None,
+            };
+        };
+        let quota = self.config.quota;
+        let key = self.config.store_key(&key);
+        let decision = self.config.store.acquire(&key, &quota, Instant::now());
+        if !decision.allowed {
+            let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
+            headers(&quota, &decision, response.headers_mut());
+            return RateLimitFuture::Limited {
+                response: Some(response),
+            };
+        }
+        RateLimitFuture::Allowed {
+            fut: self.inner.call(request),
+            quota: Some((quota, decision)),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for RateLimit<S>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    #[project = RateLimitFutureProj]
+    pub enum RateLimitFuture<F> {
+        Allowed {
+            #[pin]
+            fut: F,
+            quota: Option<(Quota, Decision)>,
+        },
+        Limited {
+            response: Option<Response>,
+        },
+    }
+}
+
+impl<F, T, E> Future for RateLimitFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            RateLimitFutureProj::Allowed { fut, quota } => {
+                let mut response = ready!(fut.poll(cx))?.into_response();
+                if let Some((quota, decision)) = quota.take() {
+                    headers(&quota, &decision, response.headers_mut());
+                }
+                Poll::Ready(Ok(response))
+            }
+            RateLimitFutureProj::Limited { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for RateLimitFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitFuture::Allowed { quota, .. } => f
+                .debug_struct("RateLimitFuture::Allowed")
+                .field("quota", quota)
+                .finish(),
+            RateLimitFuture::Limited { .. } => f.debug_struct("RateLimitFuture::Limited").finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitWarp {
+    config: Config,
+}
+
+impl RateLimitWarp {
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            config: Config {
+                quota,
+                key: RateLimitKey::default(),
+                scope: String::new(),
+                store: Arc::new(MemoryStore::new()),
+            },
+        }
+    }
+
+    pub fn key(mut self, key: RateLimitKey) -> Self {
+        self.config.key = key;
+        self
+    }
+
+    pub fn scope<T>(mut self, scope: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.config.scope = scope.into();
+        self
+    }
+
+    pub fn store<T>(mut self, store: T) -> Self
+    where
+        T: RateLimitStore + 'static,
+    {
+        self.config.store = Arc::new(store);
+        self
+    }
+}
+
+impl<S> Wrap<S> for RateLimitWarp {
+    type Service = RateLimit<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            config: self.config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::task::Waker;
+
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    fn request(headers: &[(&'static str, &'static str)]) -> Request<()> {
+        let mut request = Request::new(());
+        for (name, value) in headers {
+            request.headers_mut().append(*name, value.parse().unwrap());
+        }
+        request
+    }
+
+    fn limit(limit: u32) -> NonZeroU32 {
+        NonZeroU32::new(limit).unwrap()
+    }
+
+    fn call<S>(service: &S, request: Request<()>) -> Response
+    where
+        S: Service<Request<()>, Response = Response, Error = Infallible>,
+    {
+        let mut cx = Context::from_waker(Waker::noop());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("service did not complete"),
+        }
+    }
+
+    #[test]
+    fn quota_needs_a_period() {
+        assert_eq!(Quota::new(limit(1), Duration::ZERO), Err(InvalidQuota));
+        let quota = Quota::new(limit(5), Duration::from_secs(10)).unwrap();
+        assert_eq!(quota.limit().get(), 5);
+        assert_eq!(quota.emission_interval(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn gcra_allows_a_burst_then_spaces_requests() {
+        let quota = Quota::per_minute(limit(3));
+        let now = Instant::now();
+        let mut tat = None;
+        for remaining in [2, 1, 0] {
+            let (decision, next) = gcra(tat, &quota, now);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+            tat = next;
+        }
+
+        let (decision, next) = gcra(tat, &quota, now);
+        assert!(!decision.allowed);
+        assert!(next.is_none());
+        assert_eq!(decision.retry_after, Duration::from_secs(20));
+        assert_eq!(decision.reset, Duration::from_secs(60));
+
+        let (decision, _) = gcra(tat, &quota, now + Duration::from_secs(20));
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+    }
+
+    #[test]
+    fn memory_store_keys_are_independent() {
+        let store = MemoryStore::with_shards(1);
+        let quota = Quota::per_hour(limit(1));
+        let now = Instant::now();
+
+        assert!(store.acquire("a", &quota, now).allowed);
+        assert!(!store.acquire("a", &quota, now).allowed);
+        assert!(store.acquire("b", &quota, now).allowed);
+    }
+
+    #[test]
+    fn seconds_round_up() {
+        assert_eq!(seconds(Duration::ZERO), "0");
+        assert_eq!(seconds(Duration::from_millis(1)), "1");
+        assert_eq!(seconds(Duration::from_millis(20_001)), "21");
+    }
+
+    #[test]
+    fn key_extraction() {
+        let peer: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let mut req = request(&[("x-api-key", "secret")]);
+        req.extensions_mut().insert(PeerAddr(peer));
+
+        assert_eq!(RateLimitKey::global().extract(&req).unwrap(), "");
+        assert_eq!(RateLimitKey::peer_addr().extract(&req).unwrap(), "10.0.0.1");
+        assert_eq!(RateLimitKey::real_ip().extract(&req).unwrap(), "10.0.0.1");
+        assert_eq!(
+            RateLimitKey::header(HeaderName::from_static("x-api-key"))
+                .extract(&req)
+                .unwrap(),
+            "secret"
+        );
+        assert_eq!(RateLimitKey::extension::<u32>().extract(&req), None);
+
+        req.extensions_mut()
+            .insert(RealIp("192.0.2.7".parse().unwrap()));
+        req.extensions_mut().insert(7u32);
+        assert_eq!(RateLimitKey::real_ip().extract(&req).unwrap(), "192.0.2.7");
+        assert_eq!(RateLimitKey::extension::<u32>().extract(&req).unwrap(), "7");
+    }
+
+    #[test]
+    fn limits_by_key_and_skips_requests_without_one() {
+        let service = service_fn(|_: Request<()>| async { Ok::<_, Infallible>("allowed") }).with(
+            rate_limit(Quota::per_minute(limit(1)))
+                .key(RateLimitKey::header(HeaderName::from_static("x-api-key"))),
+        );
+
+        let response = call(&service, request(&[("x-api-key", "a")]));
+        assert_eq!(*response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATELIMIT_POLICY], "1;w=60");
+
+        let response = call(&service, request(&[("x-api-key", "a")]));
+        assert_eq!(*response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "60");
+
+        let response = call(&service, request(&[]));
+        assert_eq!(*response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(RATELIMIT_LIMIT));
+    }
+
+    #[test]
+    fn reports_the_remaining_budget() {
+        let service = service_fn(|_: Request<()>| async { Ok::<_, Infallible>("allowed") })
+            .with(rate_limit(Quota::per_minute(limit(3))).key(RateLimitKey::global()));
+
+        for remaining in ["2", "1", "0"] {
+            let response = call(&service, request(&[]));
+            let headers = response.headers();
+            assert_eq!(*response.status(), StatusCode::OK);
+            assert_eq!(headers[RATELIMIT_LIMIT], "3");
+            assert_eq!(headers[RATELIMIT_REMAINING], remaining);
+            assert_eq!(headers[RATELIMIT_POLICY], "3;w=60");
+            assert!(!headers.contains_key(header::RETRY_AFTER));
+        }
+
+        let response = call(&service, request(&[]));
+        let headers = response.headers();
+        assert_eq!(*response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(headers[RATELIMIT_REMAINING], "0");
+        assert_eq!(headers[RATELIMIT_RESET], "60");
+        assert_eq!(headers[header::RETRY_AFTER], "20");
+    }
+
+    #[test]
+    fn shared_stores_are_namespaced() {
+        let store = Arc::new(MemoryStore::with_shards(1));
+        let limited = |warp: RateLimitWarp| {
+            service_fn(|_: Request<()>| async { Ok::<_, Infallible>("allowed") })
+                .with(warp.key(RateLimitKey::global()).store(store.clone()))
+        };
+        let strict = limited(rate_limit(Quota::per_minute(limit(1))));
+        let loose = limited(rate_limit(Quota::per_hour(limit(1))));
+        let scoped = limited(rate_limit(Quota::per_minute(limit(1))).scope("upload"));
+        let twin = limited(rate_limit(Quota::per_minute(limit(1))));
+
+        for service in [&strict, &loose, &scoped] {
+            assert_eq!(*call(service, request(&[])).status(), StatusCode::OK);
+        }
+        // same scope and quota, so the budget is shared
+        assert_eq!(
+            *call(&twin, request(&[])).status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
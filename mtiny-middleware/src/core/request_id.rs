@@ -0,0 +1,367 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{ready, Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::http::{HeaderName, HeaderValue};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{Request, Response};
+
+const X_REQUEST_ID: &str = "x-request-id";
+const MAX_LEN: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(HeaderValue);
+
+impl RequestId {
+    pub fn as_str(&self) -> &str {
+        // only constructed from visible ascii
+        self.0.to_str().unwrap_or_default()
+    }
+
+    pub fn header_value(&self) -> &HeaderValue {
+        &self.0
+    }
+
+    pub fn into_header_value(self) -> HeaderValue {
+        self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdFormat {
+    #[default]
+    UuidV4,
+    Ulid,
+}
+
+impl IdFormat {
+    fn generate(self) -> RequestId {
+        let id = match self {
+            IdFormat::UuidV4 => uuid_v4(random()),
+            IdFormat::Ulid => ulid(random()),
+        };
+        RequestId(HeaderValue::from_str(&id).expect("generated ids are valid header values"))
+    }
+}
+
+// request ids need to be unique rather than unpredictable, the randomly
+// keyed std hasher over a process wide counter and the clock is enough
+pub(crate) fn random() -> u128 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let state = RandomState::new();
+    let mut halves = [0u64; 2];
+    for (i, half) in halves.iter_mut().enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_u64(count);
+        hasher.write_u128(nanos);
+        hasher.write_usize(i);
+        *half = hasher.finish();
+    }
+    (halves[0] as u128) << 64 | halves[1] as u128
+}
+
+fn uuid_v4(random: u128) -> String {
+    let mut bytes = random.to_be_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// 48 bit millisecond timestamp followed by 80 random bits, in crockford base32
+fn ulid(random: u128) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    let value = (millis & 0xffff_ffff_ffff) << 80 | random & ((1 << 80) - 1);
+    (0..26)
+        .map(|i| ALPHABET[(value >> (125 - 5 * i)) as usize & 0x1f] as char)
+        .collect()
+}
+
+fn is_valid(value: &HeaderValue) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_LEN
+        && value.as_bytes().iter().all(|byte| byte.is_ascii_graphic())
+}
+
+#[derive(Debug, Clone)]
+pub struct SetRequestId<S> {
+    inner: S,
+    header: HeaderName,
+    format: IdFormat,
+}
+
+pub fn request_id() -> RequestIdWarp {
+    RequestIdWarp::new()
+}
+
+impl<S, B> Service<Request<B>> for SetRequestId<S>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = RequestIdFuture<S::Future>;
+
+    fn call(&self, mut request: Request<B>) -> Self::Future {
+        let id = match request.headers().get(&self.header) {
+            Some(value) if is_valid(value) => RequestId(value.clone()),
+            _ => {
+                let id = self.format.generate();
+                request
+                    .headers_mut()
+                    .insert(self.header.clone(), id.0.clone());
+                id
+            }
+        };
+        request.extensions_mut().insert_cloneable(id.clone());
+        RequestIdFuture {
+            fut: self.inner.call(request),
+            header: Some((self.header.clone(), id)),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for SetRequestId<S>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    pub struct RequestIdFuture<F> {
+        #[pin]
+        fut: F,
+        header: Option<(HeaderName, RequestId)>,
+    }
+}
+
+impl<F, T, E> Future for RequestIdFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.fut.poll(cx))?.into_response();
+        let (name, id) = this.header.take().expect("polled after completion");
+        if !response.headers().contains_key(&name) {
+            response.headers_mut().insert(name, id.0);
+        }
+        Poll::Ready(Ok(response))
+    }
+}
+
+impl<F> fmt::Debug for RequestIdFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestIdFuture")
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdWarp {
+    header: HeaderName,
+    format: IdFormat,
+}
+
+impl RequestIdWarp {
+    pub fn new() -> Self {
+        Self {
+            header: HeaderName::from_static(X_REQUEST_ID),
+            format: IdFormat::default(),
+        }
+    }
+
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn format(mut self, format: IdFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl Default for RequestIdWarp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Wrap<S> for RequestIdWarp {
+    type Service = SetRequestId<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        SetRequestId {
+            inner,
+            header: self.header,
+            format: self.format,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+    use crate::core::handle_error_with;
+
+    fn call<S>(service: &S, request: Request<()>) -> Response
+    where
+        S: Service<Request<()>, Response = Response, Error = Infallible>,
+    {
+        let mut cx = Context::from_waker(Waker::noop());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("service did not complete"),
+        }
+    }
+
+    // checks that the handler sees the same id in the header and the extension
+    fn echo() -> impl Service<Request<()>, Response = String, Error = Infallible> {
+        service_fn(|request: Request<()>| async move {
+            let header = request.headers()[X_REQUEST_ID].to_str().unwrap().to_owned();
+            let extension = request.extensions().get::<RequestId>().unwrap().to_string();
+            assert_eq!(header, extension);
+            Ok(header)
+        })
+    }
+
+    #[test]
+    fn uuid_v4_layout() {
+        assert_eq!(uuid_v4(0), "00000000-0000-4000-8000-000000000000");
+        assert_eq!(uuid_v4(u128::MAX), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+    }
+
+    #[test]
+    fn ulid_layout() {
+        let id = ulid(0);
+        assert_eq!(id.len(), 26);
+        assert!(id.ends_with("0000000000000000"));
+        assert!(ulid(u128::MAX)
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || byte.is_ascii_uppercase()));
+    }
+
+    #[test]
+    fn validation() {
+        assert!(is_valid(&HeaderValue::from_static("abc-123")));
+        assert!(!is_valid(&HeaderValue::from_static("")));
+        assert!(!is_valid(&HeaderValue::from_static("a b")));
+        assert!(!is_valid(
+            &HeaderValue::from_str(&"a".repeat(MAX_LEN + 1)).unwrap()
+        ));
+    }
+
+    #[test]
+    fn generates_missing_id() {
+        let service = echo().with(request_id());
+        let response = call(&service, Request::new(()));
+        let id = response.headers()[X_REQUEST_ID]
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+    }
+
+    #[test]
+    fn keeps_valid_id_and_replaces_invalid() {
+        let service = echo().with(request_id().format(IdFormat::Ulid));
+        let request = |id: &'static str| {
+            Request::builder()
+                .header(X_REQUEST_ID, id)
+                .body(())
+                .unwrap()
+        };
+
+        let response = call(&service, request("abc-123"));
+        assert_eq!(response.headers()[X_REQUEST_ID], "abc-123");
+
+        let response = call(&service, request("not valid"));
+        assert_eq!(response.headers()[X_REQUEST_ID].len(), 26);
+    }
+
+    #[test]
+    fn handler_header_wins() {
+        let service = service_fn(|_: Request<()>| async {
+            let mut response = ().into_response();
+            response
+                .headers_mut()
+                .insert(X_REQUEST_ID, HeaderValue::from_static("handler"));
+            Ok::<_, Infallible>(response)
+        })
+        .with(request_id());
+
+        let response = call(&service, Request::new(()));
+        assert_eq!(response.headers()[X_REQUEST_ID], "handler");
+    }
+
+    #[test]
+    fn custom_header_reaches_error_responses() {
+        let header = HeaderName::from_static("x-correlation-id");
+        let service = service_fn(|_: Request<()>| async { Err::<Response, _>("failed") })
+            .with(handle_error_with(
+                |request: &Request<()>| request.extensions().get::<RequestId>().cloned(),
+                |id: Option<RequestId>, err: &'static str| {
+                    format!("{err}:{}", id.unwrap()).into_response()
+                },
+            ))
+            .with(request_id().header(header.clone()));
+        let request = Request::builder()
+            .header(&header, "abc-123")
+            .body(())
+            .unwrap();
+
+        let response = call(&service, request);
+        assert_eq!(response.headers()[&header], "abc-123");
+        assert!(!response.headers().contains_key(X_REQUEST_ID));
+    }
+}
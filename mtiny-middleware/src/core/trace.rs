@@ -0,0 +1,508 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+use pin_project_lite::pin_project;
+use tracing::field::Empty;
+use tracing::Span;
+
+use mtiny_core::body::{Body, BodyExt, BoxBody, Bytes, SizeHint};
+use mtiny_core::http::HeaderMap;
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{BoxError, Request, Response};
+
+pub trait MakeSpan<B> {
+    fn make_span(&self, request: &Request<B>) -> Span;
+}
+
+impl<B, F> MakeSpan<B> for F
+where
+    F: Fn(&Request<B>) -> Span,
+{
+    fn make_span(&self, request: &Request<B>) -> Span {
+        self(request)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultMakeSpan;
+
+impl<B> MakeSpan<B> for DefaultMakeSpan {
+    fn make_span(&self, request: &Request<B>) -> Span {
+        tracing::info_span!(
+            "request",
+            method = %request.method(),
+            uri = %request.uri(),
+            version = ?request.version(),
+            status = Empty,
+            latency_ms = Empty,
+        )
+    }
+}
+
+type OnResponse = dyn Fn(&Response, Duration, &Span) + Send + Sync;
+type OnEos = dyn Fn(Duration, &Span) + Send + Sync;
+type OnDrop = dyn Fn(Duration, &Span) + Send + Sync;
+type OnBodyError = dyn Fn(&BoxError, Duration, &Span) + Send + Sync;
+
+fn latency_ms(latency: Duration) -> f64 {
+    latency.as_secs_f64() * 1000.0
+}
+
+struct Callbacks {
+    on_response: Box<OnResponse>,
+    on_eos: Box<OnEos>,
+    on_drop: Box<OnDrop>,
+    on_body_error: Box<OnBodyError>,
+}
+
+impl Default for Callbacks {
+    fn default() -> Self {
+        Self {
+            on_response: Box::new(|response, latency, span| {
+                span.record("status", response.status().as_u16());
+                tracing::debug!(
+                    status = response.status().as_u16(),
+                    latency_ms = latency_ms(latency),
+                    "response started"
+                );
+            }),
+            on_eos: Box::new(|latency, span| {
+                span.record("latency_ms", latency_ms(latency));
+                tracing::debug!(latency_ms = latency_ms(latency), "response finished");
+            }),
+            on_drop: Box::new(|latency, span| {
+                span.record("latency_ms", latency_ms(latency));
+                tracing::debug!(latency_ms = latency_ms(latency), "response body dropped");
+            }),
+            on_body_error: Box::new(|error, latency, span| {
+                span.record("latency_ms", latency_ms(latency));
+                tracing::error!(
+                    error = %error,
+                    latency_ms = latency_ms(latency),
+                    "response body failed"
+                );
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Callbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Callbacks").finish()
+    }
+}
+
+pub struct TraceBody {
+    inner: BoxBody,
+    span: Span,
+    start: Instant,
+    callbacks: Option<Arc<Callbacks>>,
+}
+
+impl Body for TraceBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = &mut *self;
+        let _guard = this.span.enter();
+        let chunk = ready!(Pin::new(&mut this.inner).poll_next(cx));
+        if let Some(callbacks) = this.callbacks.take_if(|_| !matches!(chunk, Some(Ok(_)))) {
+            match &chunk {
+                Some(Err(err)) => (callbacks.on_body_error)(err, this.start.elapsed(), &this.span),
+                _ => (callbacks.on_eos)(this.start.elapsed(), &this.span),
+            }
+        }
+        Poll::Ready(chunk)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = &mut *self;
+        let _guard = this.span.enter();
+        Pin::new(&mut this.inner).poll_trailers(cx)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// empty bodies the server never polls still finish, anything else dropped
+// before the end, e.g. an aborted download, is reported through `on_drop`
+impl Drop for TraceBody {
+    fn drop(&mut self) {
+        if let Some(callbacks) = self.callbacks.take() {
+            let _guard = self.span.enter();
+            if self.inner.size_hint().exact() == Some(0) {
+                (callbacks.on_eos)(self.start.elapsed(), &self.span);
+            } else {
+                (callbacks.on_drop)(self.start.elapsed(), &self.span);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TraceBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceBody")
+            .field("inner", &self.inner)
+            .field("span", &self.span)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trace<S, M = DefaultMakeSpan> {
+    inner: S,
+    make_span: M,
+    callbacks: Arc<Callbacks>,
+}
+
+pub fn trace() -> TraceWarp {
+    TraceWarp::new()
+}
+
+impl<S, M, B> Service<Request<B>> for Trace<S, M>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+    S::Error: fmt::Display,
+    M: MakeSpan<B>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = TraceFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let span = self.make_span.make_span(&request);
+        let fut = {
+            let _guard = span.enter();
+            self.inner.call(request)
+        };
+        TraceFuture {
+            fut,
+            span,
+            start: Instant::now(),
+            callbacks: Some(self.callbacks.clone()),
+        }
+    }
+}
+
+impl<S, M, B> ReadyService<Request<B>> for Trace<S, M>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+    S::Error: fmt::Display,
+    M: MakeSpan<B>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    pub struct TraceFuture<F> {
+        #[pin]
+        fut: F,
+        span: Span,
+        start: Instant,
+        callbacks: Option<Arc<Callbacks>>,
+    }
+}
+
+impl<F, T, E> Future for TraceFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+    E: fmt::Display,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _guard = this.span.enter();
+        let output = ready!(this.fut.poll(cx));
+        let callbacks = this.callbacks.take().expect("polled after completion");
+        let latency = this.start.elapsed();
+        let response = match output {
+            Ok(response) => response.into_response(),
+            Err(err) => {
+                this.span.record("latency_ms", latency_ms(latency));
+                tracing::error!(error = %err, latency_ms = latency_ms(latency), "request failed");
+                return Poll::Ready(Err(err));
+            }
+        };
+        (callbacks.on_response)(&response, latency, this.span);
+        let (head, body) = response.into_head();
+        let body = TraceBody {
+            inner: body,
+            span: this.span.clone(),
+            start: *this.start,
+            callbacks: Some(callbacks),
+        };
+        Poll::Ready(Ok(Response::from_head(head, body.boxed())))
+    }
+}
+
+impl<F> fmt::Debug for TraceFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceFuture")
+            .field("fut", &core::any::type_name::<F>())
+            .field("span", &self.span)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct TraceWarp<M = DefaultMakeSpan> {
+    make_span: M,
+    callbacks: Callbacks,
+}
+
+impl TraceWarp {
+    pub fn new() -> Self {
+        Self {
+            make_span: DefaultMakeSpan,
+            callbacks: Callbacks::default(),
+        }
+    }
+}
+
+impl Default for TraceWarp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> TraceWarp<M> {
+    pub fn make_span<N>(self, make_span: N) -> TraceWarp<N> {
+        TraceWarp {
+            make_span,
+            callbacks: self.callbacks,
+        }
+    }
+
+    pub fn on_response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Response, Duration, &Span) + Send + Sync + 'static,
+    {
+        self.callbacks.on_response = Box::new(f);
+        self
+    }
+
+    pub fn on_eos<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Duration, &Span) + Send + Sync + 'static,
+    {
+        self.callbacks.on_eos = Box::new(f);
+        self
+    }
+
+    pub fn on_drop<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Duration, &Span) + Send + Sync + 'static,
+    {
+        self.callbacks.on_drop = Box::new(f);
+        self
+    }
+
+    pub fn on_body_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&BoxError, Duration, &Span) + Send + Sync + 'static,
+    {
+        self.callbacks.on_body_error = Box::new(f);
+        self
+    }
+}
+
+impl<S, M> Wrap<S> for TraceWarp<M> {
+    type Service = Trace<S, M>;
+    fn wrap(self, inner: S) -> Self::Service {
+        Trace {
+            inner,
+            make_span: self.make_span,
+            callbacks: Arc::new(self.callbacks),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+    use std::task::Waker;
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    // records the parent of every span and the span each event fired in
+    #[derive(Default)]
+    struct Captured {
+        next: AtomicU64,
+        names: Mutex<Vec<&'static str>>,
+        stack: Mutex<Vec<u64>>,
+        spans: Mutex<Vec<(&'static str, Option<&'static str>)>>,
+        events: Mutex<Vec<(String, Option<&'static str>)>>,
+    }
+
+    impl Captured {
+        fn current(&self) -> Option<&'static str> {
+            let id = *self.stack.lock().unwrap().last()?;
+            Some(self.names.lock().unwrap()[id as usize - 1])
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Captured>);
+
+    struct Message(String);
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let id = self.0.next.fetch_add(1, Ordering::Relaxed) + 1;
+            let name = attrs.metadata().name();
+            let parent = self.0.current();
+            self.0.names.lock().unwrap().push(name);
+            self.0.spans.lock().unwrap().push((name, parent));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            let current = self.0.current();
+            self.0.events.lock().unwrap().push((message.0, current));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.0.stack.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.0.stack.lock().unwrap().pop();
+        }
+    }
+
+    // a body of `remaining` chunks that logs from inside poll_next
+    struct Chunks {
+        remaining: u8,
+    }
+
+    impl Body for Chunks {
+        type Error = Infallible;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            if self.remaining == 0 {
+                return Poll::Ready(None);
+            }
+            self.remaining -= 1;
+            tracing::info!("body chunk");
+            Poll::Ready(Some(Ok(Bytes::from_static(b"chunk"))))
+        }
+
+        fn size_hint(&self) -> SizeHint {
+            SizeHint::with_exact(u64::from(self.remaining) * 5)
+        }
+    }
+
+    fn respond(chunks: u8, read: usize, warp: TraceWarp) -> Capture {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let service = service_fn(move |_: Request<()>| async move {
+                let _span = tracing::info_span!("handler").entered();
+                tracing::info!("handling");
+                Ok::<_, Infallible>(Chunks { remaining: chunks }.boxed())
+            })
+            .with(warp);
+
+            let mut cx = Context::from_waker(Waker::noop());
+            let response = match std::pin::pin!(service.call(Request::new(()))).poll(&mut cx) {
+                Poll::Ready(Ok(response)) => response,
+                _ => panic!("service did not complete"),
+            };
+            let (_, mut body) = response.into_head();
+            for _ in 0..read {
+                let _ = Pin::new(&mut body).poll_next(&mut cx);
+            }
+        });
+        capture
+    }
+
+    fn messages(capture: &Capture) -> Vec<(String, Option<&'static str>)> {
+        capture.0.events.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn nests_handler_and_body_under_request_span() {
+        let capture = respond(2, 3, trace());
+        assert_eq!(
+            *capture.0.spans.lock().unwrap(),
+            [("request", None), ("handler", Some("request"))]
+        );
+        let events = messages(&capture);
+        assert_eq!(events[0], ("handling".to_owned(), Some("handler")));
+        assert_eq!(events[2], ("body chunk".to_owned(), Some("request")));
+        assert_eq!(events[3], ("body chunk".to_owned(), Some("request")));
+        assert_eq!(events[4], ("response finished".to_owned(), Some("request")));
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn reports_early_drops_separately() {
+        let capture = respond(2, 1, trace());
+        let events = messages(&capture);
+        assert_eq!(
+            events.last().unwrap(),
+            &("response body dropped".to_owned(), Some("request"))
+        );
+        assert!(!events
+            .iter()
+            .any(|(message, _)| message == "response finished"));
+    }
+
+    #[test]
+    fn empty_bodies_finish_on_drop() {
+        let capture = respond(0, 0, trace().on_drop(|_, _| panic!("not an early drop")));
+        let events = messages(&capture);
+        assert_eq!(
+            events.last().unwrap(),
+            &("response finished".to_owned(), Some("request"))
+        );
+    }
+}
@@ -0,0 +1,623 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::http::{HeaderMap, HeaderName, HeaderValue};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{Request, Response};
+
+use super::request_id::random;
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+const MAX_TRACESTATE_LEN: usize = 512;
+const SAMPLED: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    flags: u8,
+    trace_state: Option<HeaderValue>,
+}
+
+impl TraceContext {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut traceparent = headers.get_all(TRACEPARENT).iter();
+        let (trace_id, span_id, flags) = match (traceparent.next(), traceparent.next()) {
+            (Some(value), None) => parse_traceparent(value.as_bytes())?,
+            _ => return None,
+        };
+        Some(Self {
+            trace_id,
+            span_id,
+            parent_id: None,
+            flags,
+            trace_state: parse_tracestate(headers),
+        })
+    }
+
+    fn root(sampled: bool) -> Self {
+        Self {
+            trace_id: random().max(1),
+            span_id: span_id(),
+            parent_id: None,
+            flags: if sampled { SAMPLED } else { 0 },
+            trace_state: None,
+        }
+    }
+
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: span_id(),
+            parent_id: Some(self.span_id),
+            flags: self.flags,
+            trace_state: self.trace_state.clone(),
+        }
+    }
+
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    pub fn parent_span_id(&self) -> Option<u64> {
+        self.parent_id
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED != 0
+    }
+
+    pub fn trace_state(&self) -> Option<&HeaderValue> {
+        self.trace_state.as_ref()
+    }
+
+    pub fn traceparent(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.to_string()).expect("traceparent is a valid header value")
+    }
+
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        headers.insert(HeaderName::from_static(TRACEPARENT), self.traceparent());
+        match &self.trace_state {
+            Some(state) => {
+                headers.insert(HeaderName::from_static(TRACESTATE), state.clone());
+            }
+            None => {
+                headers.remove(TRACESTATE);
+            }
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+fn span_id() -> u64 {
+    (random() as u64).max(1)
+}
+
+fn hex(digits: &[u8]) -> Option<u128> {
+    digits.iter().try_fold(0u128, |acc, &digit| {
+        let value = match digit {
+            b'0'..=b'9' => digit - b'0',
+            b'a'..=b'f' => digit - b'a' + 10,
+            _ => return None,
+        };
+        Some(acc << 4 | value as u128)
+    })
+}
+
+// version-traceid-parentid-flags, later versions may append more fields
+fn parse_traceparent(value: &[u8]) -> Option<(u128, u64, u8)> {
+    if value.len() < 55 {
+        return None;
+    }
+    let (value, rest) = value.split_at(55);
+    let version = hex(&value[..2])?;
+    match (version, rest.first()) {
+        (0xff, _) | (0, Some(_)) => return None,
+        (_, Some(b'-') | None) => {}
+        _ => return None,
+    }
+    if value[2] != b'-' || value[35] != b'-' || value[52] != b'-' {
+        return None;
+    }
+    let trace_id = hex(&value[3..35])?;
+    let span_id = hex(&value[36..52])? as u64;
+    let flags = hex(&value[53..])? as u8;
+    (trace_id != 0 && span_id != 0).then_some((trace_id, span_id, flags))
+}
+
+fn parse_tracestate(headers: &HeaderMap) -> Option<HeaderValue> {
+    let state = headers
+        .get_all(TRACESTATE)
+        .iter()
+        .map(|value| value.to_str().ok().map(str::trim))
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+    if state.is_empty() || state.len() > MAX_TRACESTATE_LEN {
+        return None;
+    }
+    HeaderValue::from_str(&state).ok()
+}
+
+#[cfg(feature = "opentelemetry")]
+mod otel {
+    use opentelemetry::trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState};
+
+    use super::*;
+
+    impl TraceContext {
+        pub fn span_context(&self) -> SpanContext {
+            let state = self
+                .trace_state
+                .as_ref()
+                .and_then(|state| state.to_str().ok())
+                .and_then(|state| state.parse().ok())
+                .unwrap_or_default();
+            SpanContext::new(
+                TraceId::from(self.trace_id),
+                SpanId::from(self.span_id),
+                TraceFlags::new(self.flags),
+                true,
+                state,
+            )
+        }
+
+        pub(super) fn from_span_context(context: &SpanContext, parent_id: Option<u64>) -> Self {
+            Self {
+                trace_id: u128::from_be_bytes(context.trace_id().to_bytes()),
+                span_id: u64::from_be_bytes(context.span_id().to_bytes()),
+                parent_id,
+                flags: context.trace_flags().to_u8(),
+                trace_state: trace_state(context.trace_state()),
+            }
+        }
+    }
+
+    fn trace_state(state: &TraceState) -> Option<HeaderValue> {
+        let state = state.header();
+        if state.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&state).ok()
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+type Span = opentelemetry::global::BoxedSpan;
+
+#[derive(Debug, Clone)]
+struct Config {
+    sampled: bool,
+    #[cfg(feature = "opentelemetry")]
+    tracer: Option<Arc<opentelemetry::global::BoxedTracer>>,
+}
+
+impl Config {
+    fn start<B>(&self, request: &mut Request<B>) -> (TraceContext, Option<Span>) {
+        let parent = TraceContext::from_headers(request.headers());
+        #[cfg(feature = "opentelemetry")]
+        if let Some(tracer) = &self.tracer {
+            use opentelemetry::trace::{Span as _, SpanBuilder, SpanKind, TraceContextExt, Tracer};
+            use opentelemetry::KeyValue;
+
+            let parent_cx = match &parent {
+                Some(parent) => {
+                    opentelemetry::Context::new().with_remote_span_context(parent.span_context())
+                }
+                None => opentelemetry::Context::new(),
+            };
+            let builder = SpanBuilder::from_name(request.method().to_string())
+                .with_kind(SpanKind::Server)
+                .with_attributes([
+                    KeyValue::new("http.request.method", request.method().to_string()),
+                    KeyValue::new("url.path", request.uri().path().to_owned()),
+                ]);
+            let span = tracer.build_with_context(builder, &parent_cx);
+            let context = TraceContext::from_span_context(
+                span.span_context(),
+                parent.map(|parent| parent.span_id),
+            );
+            request.extensions_mut().insert_cloneable(
+                opentelemetry::Context::new().with_remote_span_context(span.span_context().clone()),
+            );
+            return (context, Some(span));
+        }
+        let context = match parent {
+            Some(parent) => parent.child(),
+            None => TraceContext::root(self.sampled),
+        };
+        (context, None)
+    }
+}
+
+#[cfg(not(feature = "opentelemetry"))]
+enum Span {}
+
+#[cfg(not(feature = "opentelemetry"))]
+fn end(span: Option<Span>, _: Option<&Response>) {
+    if let Some(span) = span {
+        match span {}
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+fn end(span: Option<Span>, response: Option<&Response>) {
+    use opentelemetry::trace::{Span as _, Status};
+    use opentelemetry::KeyValue;
+
+    let Some(mut span) = span else {
+        return;
+    };
+    match response {
+        Some(response) => {
+            let status = response.status().as_u16();
+            span.set_attribute(KeyValue::new("http.response.status_code", status as i64));
+            if status >= 500 {
+                span.set_status(Status::error(""));
+            }
+        }
+        None => span.set_status(Status::error("service error")),
+    }
+    span.end();
+}
+
+#[derive(Debug, Clone)]
+pub struct PropagateTraceContext<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+pub fn trace_context() -> TraceContextWarp {
+    TraceContextWarp::new()
+}
+
+impl<S, B> Service<Request<B>> for PropagateTraceContext<S>
+where
+    S: Service<Request<B>>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = TraceContextFuture<S::Future>;
+
+    fn call(&self, mut request: Request<B>) -> Self::Future {
+        let (context, span) = self.config.start(&mut request);
+        request.extensions_mut().insert_cloneable(context.clone());
+        TraceContextFuture {
+            fut: self.inner.call(request),
+            context: Some(context),
+            span,
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for PropagateTraceContext<S>
+where
+    S: ReadyService<Request<B>>,
+    S::Response: IntoResponse,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    pub struct TraceContextFuture<F> {
+        #[pin]
+        fut: F,
+        context: Option<TraceContext>,
+        span: Option<Span>,
+    }
+}
+
+impl<F, T, E> Future for TraceContextFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = ready!(this.fut.poll(cx));
+        let context = this.context.take().expect("polled after completion");
+        let mut response = match output {
+            Ok(response) => response.into_response(),
+            Err(err) => {
+                end(this.span.take(), None);
+                return Poll::Ready(Err(err));
+            }
+        };
+        end(this.span.take(), Some(&response));
+        context.inject(response.headers_mut());
+        Poll::Ready(Ok(response))
+    }
+}
+
+impl<F> fmt::Debug for TraceContextFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceContextFuture")
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceContextWarp {
+    config: Config,
+}
+
+impl TraceContextWarp {
+    pub fn new() -> Self {
+        Self {
+            config: Config {
+                sampled: false,
+                #[cfg(feature = "opentelemetry")]
+                tracer: None,
+            },
+        }
+    }
+
+    pub fn sampled(mut self, sampled: bool) -> Self {
+        self.config.sampled = sampled;
+        self
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    pub fn tracer<T>(mut self, tracer: T) -> Self
+    where
+        T: opentelemetry::trace::Tracer + Send + Sync + 'static,
+        T::Span: Send + Sync + 'static,
+    {
+        self.config.tracer = Some(Arc::new(opentelemetry::global::BoxedTracer::new(Box::new(
+            tracer,
+        ))));
+        self
+    }
+}
+
+impl Default for TraceContextWarp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Wrap<S> for TraceContextWarp {
+    type Service = PropagateTraceContext<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        PropagateTraceContext {
+            inner,
+            config: Arc::new(self.config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::Waker;
+
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT: &str = "00f067aa0ba902b7";
+
+    fn traceparent(version: &str, trace_id: &str, span_id: &str, flags: &str) -> String {
+        format!("{version}-{trace_id}-{span_id}-{flags}")
+    }
+
+    fn headers(values: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn call<S>(service: &S, headers: HeaderMap) -> Response
+    where
+        S: Service<Request<()>, Response = Response, Error = Infallible>,
+    {
+        let mut request = Request::new(());
+        *request.headers_mut() = headers;
+        let mut cx = Context::from_waker(Waker::noop());
+        match std::pin::pin!(service.call(request)).poll(&mut cx) {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("service did not complete"),
+        }
+    }
+
+    // answers with the context the handler saw in an `x-seen` header
+    fn echo() -> impl Service<Request<()>, Response = Response, Error = Infallible> {
+        service_fn(|request: Request<()>| async move {
+            let context = request.extensions().get::<TraceContext>().unwrap();
+            let mut response = ().into_response();
+            response
+                .headers_mut()
+                .insert("x-seen", context.traceparent());
+            Ok(response)
+        })
+    }
+
+    #[test]
+    fn parses_traceparent() {
+        let value = traceparent("00", TRACE_ID, PARENT, "01");
+        assert_eq!(
+            parse_traceparent(value.as_bytes()),
+            Some((0x4bf92f3577b34da6a3ce929d0e0e4736, 0x00f067aa0ba902b7, 1))
+        );
+        let value = traceparent("00", TRACE_ID, PARENT, "00");
+        assert_eq!(parse_traceparent(value.as_bytes()).unwrap().2, 0);
+    }
+
+    #[test]
+    fn rejects_invalid_traceparent() {
+        let zeros = "0".repeat(32);
+        for value in [
+            traceparent("00", &TRACE_ID.to_uppercase(), PARENT, "01"),
+            traceparent("00", &zeros, PARENT, "01"),
+            traceparent("00", TRACE_ID, &zeros[..16], "01"),
+            traceparent("ff", TRACE_ID, PARENT, "01"),
+            traceparent("0g", TRACE_ID, PARENT, "01"),
+            traceparent("00", TRACE_ID, PARENT, "0x"),
+            traceparent("00", TRACE_ID, PARENT, "01") + "-extra",
+            traceparent("00", TRACE_ID, PARENT, "1"),
+            traceparent("00", TRACE_ID, PARENT, "01").replace('-', "_"),
+        ] {
+            assert_eq!(parse_traceparent(value.as_bytes()), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn later_versions_may_append_fields() {
+        let value = traceparent("01", TRACE_ID, PARENT, "01");
+        assert!(parse_traceparent(value.as_bytes()).is_some());
+        assert!(parse_traceparent((value.clone() + "-extra").as_bytes()).is_some());
+        assert_eq!(parse_traceparent((value + "extra").as_bytes()), None);
+    }
+
+    #[test]
+    fn duplicate_traceparent_is_ignored() {
+        let value = traceparent("00", TRACE_ID, PARENT, "01");
+        let headers = headers(&[("traceparent", &value), ("traceparent", &value)]);
+        assert_eq!(TraceContext::from_headers(&headers), None);
+    }
+
+    #[test]
+    fn tracestate_is_joined_and_bounded() {
+        let state = parse_tracestate(&headers(&[
+            ("tracestate", "congo=t61rcWkgMzE"),
+            ("tracestate", " "),
+            ("tracestate", " rojo=00f067aa0ba902b7 "),
+        ]));
+        assert_eq!(state.unwrap(), "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7");
+
+        let long = format!("key={}", "v".repeat(MAX_TRACESTATE_LEN));
+        assert_eq!(parse_tracestate(&headers(&[("tracestate", &long)])), None);
+        assert_eq!(parse_tracestate(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn child_keeps_trace_flags_and_state() {
+        let value = traceparent("00", TRACE_ID, PARENT, "03");
+        let parent = TraceContext::from_headers(&headers(&[
+            ("traceparent", &value),
+            ("tracestate", "congo=t61rcWkgMzE"),
+        ]))
+        .unwrap();
+        assert!(parent.is_sampled());
+        assert_eq!(parent.to_string(), value);
+
+        let child = parent.child();
+        assert_eq!(child.trace_id(), parent.trace_id());
+        assert_eq!(child.parent_span_id(), Some(parent.span_id()));
+        assert_ne!(child.span_id(), parent.span_id());
+        assert!(child.to_string().ends_with("-03"));
+        assert_eq!(child.trace_state().unwrap(), "congo=t61rcWkgMzE");
+
+        let mut headers = headers(&[("tracestate", "stale=1")]);
+        TraceContext::root(false).inject(&mut headers);
+        assert!(!headers.contains_key(TRACESTATE));
+    }
+
+    #[test]
+    fn continues_incoming_trace() {
+        let service = echo().with(trace_context());
+        let value = traceparent("00", TRACE_ID, PARENT, "01");
+        let response = call(
+            &service,
+            headers(&[("traceparent", &value), ("tracestate", "congo=t61rcWkgMzE")]),
+        );
+        let headers = response.headers();
+        let returned = headers[TRACEPARENT].to_str().unwrap();
+
+        assert_eq!(&returned[3..35], TRACE_ID);
+        assert_ne!(&returned[36..52], PARENT);
+        assert!(returned.ends_with("-01"));
+        assert_eq!(headers[TRACESTATE], "congo=t61rcWkgMzE");
+        assert_eq!(headers["x-seen"], returned);
+    }
+
+    #[test]
+    fn starts_a_root_for_invalid_parents() {
+        let service = echo().with(trace_context());
+        let invalid = traceparent("ff", TRACE_ID, PARENT, "01");
+        let response = call(
+            &service,
+            headers(&[
+                ("traceparent", &invalid),
+                ("tracestate", "congo=t61rcWkgMzE"),
+            ]),
+        );
+        let returned = response.headers()[TRACEPARENT].to_str().unwrap();
+
+        assert_eq!(returned.len(), 55);
+        assert_ne!(&returned[3..35], TRACE_ID);
+        assert!(returned.ends_with("-00"));
+        assert!(!response.headers().contains_key(TRACESTATE));
+
+        let service = echo().with(trace_context().sampled(true));
+        let response = call(&service, HeaderMap::new());
+        assert!(response.headers()[TRACEPARENT]
+            .to_str()
+            .unwrap()
+            .ends_with("-01"));
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    #[test]
+    fn records_server_spans() {
+        use opentelemetry::trace::{SpanKind, TracerProvider as _};
+        use opentelemetry::KeyValue;
+        use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let service = echo().with(trace_context().tracer(provider.tracer("test")));
+        let value = traceparent("00", TRACE_ID, PARENT, "01");
+        let response = call(&service, headers(&[("traceparent", &value)]));
+        let returned = response.headers()[TRACEPARENT].to_str().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let [span] = &spans[..] else {
+            panic!("expected one span, got {}", spans.len());
+        };
+        assert_eq!(span.span_context.trace_id().to_string(), TRACE_ID);
+        assert_eq!(span.span_context.span_id().to_string(), &returned[36..52]);
+        assert_eq!(span.parent_span_id.to_string(), PARENT);
+        assert!(span.parent_span_is_remote);
+        assert_eq!(span.span_kind, SpanKind::Server);
+        assert_eq!(span.name, "GET");
+        assert!(span
+            .attributes
+            .contains(&KeyValue::new("http.response.status_code", 200i64)));
+    }
+}
@@ -0,0 +1,551 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+use mtiny_core::http::uri::{Authority, Scheme};
+use mtiny_core::http::{header, HeaderMap};
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{ConnectionInfo, PeerAddr, Request};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RealIp(pub IpAddr);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EffectiveScheme(pub Scheme);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EffectiveHost(pub Authority);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, InvalidCidr> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix > max {
+            return Err(InvalidCidr(()));
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => prefix_eq(
+                u32::from(net).into(),
+                u32::from(addr).into(),
+                32,
+                self.prefix,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                prefix_eq(u128::from(net), u128::from(addr), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_eq(net: u128, addr: u128, bits: u8, prefix: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    net >> shift == addr >> shift
+}
+
+impl FromStr for Cidr {
+    type Err = InvalidCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((addr, prefix)) => Cidr::new(
+                addr.parse().map_err(|_| InvalidCidr(()))?,
+                prefix.parse().map_err(|_| InvalidCidr(()))?,
+            ),
+            None => {
+                let addr = s.parse::<IpAddr>().map_err(|_| InvalidCidr(()))?;
+                let prefix = if addr.is_ipv4() { 32 } else { 128 };
+                Cidr::new(addr, prefix)
+            }
+        }
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        let prefix = if addr.is_ipv4() { 32 } else { 128 };
+        Self { addr, prefix }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidCidr(());
+
+impl fmt::Display for InvalidCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid CIDR")
+    }
+}
+
+impl std::error::Error for InvalidCidr {}
+
+// which headers the trusted proxies write; the other one is never read, so a
+// client can not slip in a header the proxies pass through untouched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ForwardedHeader {
+    Forwarded,
+    #[default]
+    XForwarded,
+}
+
+#[derive(Clone)]
+pub struct TrustedProxy<S> {
+    inner: S,
+    trusted: Vec<Cidr>,
+    header: ForwardedHeader,
+}
+
+pub fn trusted_proxy<I>(trusted: I) -> TrustedProxyWarp
+where
+    I: IntoIterator<Item = Cidr>,
+{
+    TrustedProxyWarp::new(trusted)
+}
+
+impl<S> TrustedProxy<S> {
+    fn new(inner: S, trusted: Vec<Cidr>, header: ForwardedHeader) -> Self {
+        Self {
+            inner,
+            trusted,
+            header,
+        }
+    }
+
+    fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.trusted.iter().any(|cidr| cidr.contains(addr))
+    }
+
+    fn resolve<B>(&self, request: &Request<B>) -> Resolved {
+        let peer = request
+            .extensions()
+            .get::<PeerAddr>()
+            .map(|addr| addr.0)
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<ConnectionInfo>()
+                    .map(ConnectionInfo::peer_addr)
+            });
+
+        let mut resolved = Resolved {
+            ip: peer.map(|addr| addr.ip()),
+            scheme: request
+                .extensions()
+                .get::<ConnectionInfo>()
+                .map(|info| info.scheme().clone())
+                .or_else(|| request.uri().scheme().cloned())
+                .unwrap_or(Scheme::HTTP),
+            host: request
+                .headers()
+                .get(header::HOST)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .or_else(|| request.uri().authority().cloned()),
+        };
+
+        match resolved.ip {
+            Some(ip) if self.is_trusted(ip) => {}
+            _ => return resolved,
+        }
+
+        let headers = request.headers();
+        let hops = match self.header {
+            ForwardedHeader::Forwarded => forwarded(headers),
+            ForwardedHeader::XForwarded => x_forwarded(headers),
+        };
+
+        for hop in hops.into_iter().rev() {
+            if let Some(scheme) = hop.scheme {
+                resolved.scheme = scheme;
+            }
+            if let Some(host) = hop.host {
+                resolved.host = Some(host);
+            }
+            let Some(ip) = hop.ip else {
+                break;
+            };
+            resolved.ip = Some(ip);
+            if !self.is_trusted(ip) {
+                break;
+            }
+        }
+
+        resolved
+    }
+}
+
+impl<S, B> Service<Request<B>> for TrustedProxy<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+
+    type Error = S::Error;
+
+    type Future = S::Future;
+
+    fn call(&self, mut request: Request<B>) -> Self::Future {
+        let resolved = self.resolve(&request);
+
+        let extensions = request.extensions_mut();
+        if let Some(ip) = resolved.ip {
+            extensions.insert_cloneable(RealIp(ip));
+        }
+        extensions.insert_cloneable(EffectiveScheme(resolved.scheme));
+        if let Some(host) = resolved.host {
+            extensions.insert_cloneable(EffectiveHost(host));
+        }
+
+        self.inner.call(request)
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for TrustedProxy<S>
+where
+    S: ReadyService<Request<B>>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+impl<S> fmt::Debug for TrustedProxy<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustedProxy")
+            .field("inner", &self.inner)
+            .field("trusted", &self.trusted)
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrustedProxyWarp {
+    trusted: Vec<Cidr>,
+    header: ForwardedHeader,
+}
+
+impl TrustedProxyWarp {
+    pub fn new<I>(trusted: I) -> Self
+    where
+        I: IntoIterator<Item = Cidr>,
+    {
+        Self {
+            trusted: trusted.into_iter().collect(),
+            header: ForwardedHeader::default(),
+        }
+    }
+
+    pub fn header(mut self, header: ForwardedHeader) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+impl<S> Wrap<S> for TrustedProxyWarp {
+    type Service = TrustedProxy<S>;
+    fn wrap(self, service: S) -> Self::Service {
+        TrustedProxy::new(service, self.trusted, self.header)
+    }
+}
+
+struct Resolved {
+    ip: Option<IpAddr>,
+    scheme: Scheme,
+    host: Option<Authority>,
+}
+
+#[derive(Default)]
+struct Hop {
+    ip: Option<IpAddr>,
+    scheme: Option<Scheme>,
+    host: Option<Authority>,
+}
+
+fn forwarded(headers: &HeaderMap) -> Vec<Hop> {
+    let mut hops = Vec::new();
+    for value in headers.get_all(header::FORWARDED) {
+        let Ok(value) = value.to_str() else {
+            return Vec::new();
+        };
+        for element in value.split(',') {
+            let mut hop = Hop::default();
+            for pair in element.split(';') {
+                let Some((name, value)) = pair.split_once('=') else {
+                    continue;
+                };
+                let value = value.trim().trim_matches('"');
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "for" => hop.ip = parse_node(value),
+                    "proto" => hop.scheme = parse_scheme(value),
+                    "host" => hop.host = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            hops.push(hop);
+        }
+    }
+    hops
+}
+
+fn x_forwarded(headers: &HeaderMap) -> Vec<Hop> {
+    let list = |name: &str| -> Vec<String> {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|value| value.trim().to_owned())
+            .collect()
+    };
+
+    let ips = list(X_FORWARDED_FOR);
+    let schemes = list(X_FORWARDED_PROTO);
+    let hosts = list(X_FORWARDED_HOST);
+
+    let mut hops = ips
+        .iter()
+        .map(|ip| Hop {
+            ip: parse_node(ip),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    if hops.is_empty() {
+        hops.push(Hop::default());
+    }
+
+    // the lists only line up when every proxy appended to all of them, any
+    // other shape could credit a client supplied entry to a trusted hop
+    if schemes.len() == hops.len() {
+        for (hop, scheme) in hops.iter_mut().zip(&schemes) {
+            hop.scheme = parse_scheme(scheme);
+        }
+    }
+    if hosts.len() == hops.len() {
+        for (hop, host) in hops.iter_mut().zip(&hosts) {
+            hop.host = host.parse().ok();
+        }
+    }
+    hops
+}
+
+fn parse_node(value: &str) -> Option<IpAddr> {
+    if let Ok(addr) = value.parse::<IpAddr>() {
+        return Some(addr);
+    }
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .and_then(|value| value.parse().ok())
+}
+
+fn parse_scheme(value: &str) -> Option<Scheme> {
+    match value.to_ascii_lowercase().as_str() {
+        "http" => Some(Scheme::HTTP),
+        "https" => Some(Scheme::HTTPS),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn proxy(header: ForwardedHeader) -> TrustedProxy<()> {
+        TrustedProxy::new((), vec![cidr("10.0.0.0/8"), cidr("::1")], header)
+    }
+
+    fn request(peer: &str, headers: &[(&'static str, &'static str)]) -> Request<()> {
+        let mut request = Request::new(());
+        request
+            .extensions_mut()
+            .insert(PeerAddr(SocketAddr::new(ip(peer), 4000)));
+        for (name, value) in headers {
+            request.headers_mut().append(*name, value.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn cidr_parse() {
+        assert_eq!(cidr("10.0.0.0/8").to_string(), "10.0.0.0/8");
+        assert_eq!(cidr("10.1.2.3").to_string(), "10.1.2.3/32");
+        assert_eq!(cidr("fd00::/8").to_string(), "fd00::/8");
+        assert_eq!(cidr("::1").to_string(), "::1/128");
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("fd00::/129".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+        assert!("localhost".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let net = cidr("192.168.0.0/16");
+        assert!(net.contains(ip("192.168.0.1")));
+        assert!(net.contains(ip("192.168.255.255")));
+        assert!(!net.contains(ip("192.169.0.1")));
+        assert!(!net.contains(ip("fd00::1")));
+
+        assert!(cidr("0.0.0.0/0").contains(ip("8.8.8.8")));
+        assert!(cidr("::/0").contains(ip("2001:db8::1")));
+        assert!(cidr("10.0.0.1").contains(ip("10.0.0.1")));
+        assert!(!cidr("10.0.0.1").contains(ip("10.0.0.2")));
+
+        let net = cidr("2001:db8::/32");
+        assert!(net.contains(ip("2001:db8:ffff::1")));
+        assert!(!net.contains(ip("2001:db9::1")));
+
+        // ipv4-mapped peers match ipv4 networks
+        assert!(cidr("10.0.0.0/8").contains(ip("::ffff:10.1.2.3")));
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_headers() {
+        let request = request(
+            "203.0.113.9",
+            &[
+                ("x-forwarded-for", "1.2.3.4"),
+                ("x-forwarded-proto", "https"),
+                ("host", "example.com"),
+            ],
+        );
+        let resolved = proxy(ForwardedHeader::XForwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("203.0.113.9")));
+        assert_eq!(resolved.scheme, Scheme::HTTP);
+        assert_eq!(resolved.host.unwrap(), "example.com");
+    }
+
+    #[test]
+    fn x_forwarded_walks_trusted_hops() {
+        let request = request(
+            "10.0.0.1",
+            &[
+                ("x-forwarded-for", "1.2.3.4, 198.51.100.7, 10.0.0.2"),
+                ("x-forwarded-proto", "http, https, https"),
+                ("x-forwarded-host", "a.example, b.example, c.example"),
+            ],
+        );
+        let resolved = proxy(ForwardedHeader::XForwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("198.51.100.7")));
+        assert_eq!(resolved.scheme, Scheme::HTTPS);
+        assert_eq!(resolved.host.unwrap(), "b.example");
+    }
+
+    #[test]
+    fn x_forwarded_ignores_misaligned_lists() {
+        // the client sent its own proto and host, the proxy only appended
+        // to x-forwarded-for
+        let request = request(
+            "10.0.0.1",
+            &[
+                ("x-forwarded-for", "198.51.100.7"),
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-proto", "http"),
+                ("x-forwarded-host", "evil.example, good.example"),
+                ("host", "good.example"),
+            ],
+        );
+        let resolved = proxy(ForwardedHeader::XForwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("198.51.100.7")));
+        assert_eq!(resolved.scheme, Scheme::HTTP);
+        assert_eq!(resolved.host.unwrap(), "good.example");
+    }
+
+    #[test]
+    fn x_forwarded_ignores_spoofed_forwarded() {
+        let request = request(
+            "10.0.0.1",
+            &[
+                ("forwarded", "for=1.2.3.4;proto=https"),
+                ("x-forwarded-for", "198.51.100.7"),
+            ],
+        );
+        let resolved = proxy(ForwardedHeader::XForwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("198.51.100.7")));
+        assert_eq!(resolved.scheme, Scheme::HTTP);
+    }
+
+    #[test]
+    fn forwarded_ignores_spoofed_x_forwarded() {
+        let request = request(
+            "::1",
+            &[
+                ("x-forwarded-for", "1.2.3.4"),
+                ("x-forwarded-proto", "https"),
+                ("forwarded", r#"for="[2001:db8::7]:1234";proto=http"#),
+            ],
+        );
+        let resolved = proxy(ForwardedHeader::Forwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("2001:db8::7")));
+        assert_eq!(resolved.scheme, Scheme::HTTP);
+    }
+
+    #[test]
+    fn forwarded_stops_at_first_untrusted_hop() {
+        let request = request(
+            "10.0.0.1",
+            &[
+                ("forwarded", "for=9.9.9.9;proto=https;host=spoofed.example"),
+                (
+                    "forwarded",
+                    "for=198.51.100.7;proto=https;host=app.example, for=10.0.0.2",
+                ),
+            ],
+        );
+        let resolved = proxy(ForwardedHeader::Forwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("198.51.100.7")));
+        assert_eq!(resolved.scheme, Scheme::HTTPS);
+        assert_eq!(resolved.host.unwrap(), "app.example");
+    }
+
+    #[test]
+    fn forwarded_without_for_stops() {
+        let request = request(
+            "10.0.0.1",
+            &[("forwarded", "for=1.2.3.4, proto=https;for=unknown")],
+        );
+        let resolved = proxy(ForwardedHeader::Forwarded).resolve(&request);
+        assert_eq!(resolved.ip, Some(ip("10.0.0.1")));
+        assert_eq!(resolved.scheme, Scheme::HTTPS);
+    }
+}
@@ -0,0 +1,241 @@
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use mtiny_core::body::{Body, BoxBody, Bytes};
+use mtiny_core::http::HeaderMap;
+use mtiny_core::BoxError;
+
+use super::DecompressionError;
+use crate::encoding::Encoding;
+
+// bounds the decoded output as it is produced, so a single small chunk can
+// not expand into an unbounded allocation before the limit is checked
+struct Sink {
+    buf: Vec<u8>,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl Write for Sink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() as u64 > self.remaining {
+            self.exceeded = true;
+            return Err(io::Error::other("decompressed body too large"));
+        }
+        self.remaining -= data.len() as u64;
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Decoder {
+    #[cfg(feature = "decompression-br")]
+    Brotli(Box<brotli::DecompressorWriter<Sink>>),
+    // the raw writer, since it is the one that reports a truncated frame on finish
+    #[cfg(feature = "decompression-zstd")]
+    Zstd(zstd::stream::zio::Writer<Sink, zstd::stream::raw::Decoder<'static>>),
+    #[cfg(feature = "decompression-gzip")]
+    Gzip(flate2::write::GzDecoder<Sink>),
+    #[cfg(feature = "decompression-deflate")]
+    Deflate(flate2::write::ZlibDecoder<Sink>),
+}
+
+impl Decoder {
+    fn new(encoding: Encoding, max_size: u64) -> io::Result<Option<Self>> {
+        let sink = Sink {
+            buf: Vec::new(),
+            remaining: max_size,
+            exceeded: false,
+        };
+        let decoder = match encoding {
+            #[cfg(feature = "decompression-br")]
+            Encoding::Brotli => {
+                Decoder::Brotli(Box::new(brotli::DecompressorWriter::new(sink, 4096)))
+            }
+            #[cfg(feature = "decompression-zstd")]
+            Encoding::Zstd => Decoder::Zstd(zstd::stream::zio::Writer::new(
+                sink,
+                zstd::stream::raw::Decoder::new()?,
+            )),
+            #[cfg(feature = "decompression-gzip")]
+            Encoding::Gzip => Decoder::Gzip(flate2::write::GzDecoder::new(sink)),
+            #[cfg(feature = "decompression-deflate")]
+            Encoding::Deflate => Decoder::Deflate(flate2::write::ZlibDecoder::new(sink)),
+            _ => return Ok(None),
+        };
+        Ok(Some(decoder))
+    }
+
+    fn sink(&mut self) -> &mut Sink {
+        match self {
+            #[cfg(feature = "decompression-br")]
+            Decoder::Brotli(decoder) => decoder.get_mut(),
+            #[cfg(feature = "decompression-zstd")]
+            Decoder::Zstd(decoder) => decoder.writer_mut(),
+            #[cfg(feature = "decompression-gzip")]
+            Decoder::Gzip(decoder) => decoder.get_mut(),
+            #[cfg(feature = "decompression-deflate")]
+            Decoder::Deflate(decoder) => decoder.get_mut(),
+        }
+    }
+
+    fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "decompression-br")]
+            Decoder::Brotli(decoder) => decoder.write_all(chunk).and_then(|_| decoder.flush()),
+            #[cfg(feature = "decompression-zstd")]
+            Decoder::Zstd(decoder) => decoder.write_all(chunk).and_then(|_| decoder.flush()),
+            #[cfg(feature = "decompression-gzip")]
+            Decoder::Gzip(decoder) => decoder.write_all(chunk).and_then(|_| decoder.flush()),
+            #[cfg(feature = "decompression-deflate")]
+            Decoder::Deflate(decoder) => decoder.write_all(chunk).and_then(|_| decoder.flush()),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "decompression-br")]
+            Decoder::Brotli(decoder) => decoder.close(),
+            #[cfg(feature = "decompression-zstd")]
+            Decoder::Zstd(decoder) => decoder.finish(),
+            #[cfg(feature = "decompression-gzip")]
+            Decoder::Gzip(decoder) => decoder.try_finish(),
+            #[cfg(feature = "decompression-deflate")]
+            Decoder::Deflate(decoder) => decoder.try_finish(),
+        }
+    }
+
+    fn decode(&mut self, chunk: Option<&[u8]>) -> Result<Bytes, DecompressionError> {
+        let result = match chunk {
+            Some(chunk) => self.write(chunk),
+            None => self.finish(),
+        };
+        let sink = self.sink();
+        if sink.exceeded {
+            return Err(DecompressionError::LimitExceeded);
+        }
+        result.map_err(DecompressionError::Decode)?;
+        Ok(Bytes::from(std::mem::take(&mut sink.buf)))
+    }
+}
+
+pub struct DecompressionBody {
+    inner: BoxBody,
+    decoder: Option<Decoder>,
+}
+
+impl DecompressionBody {
+    pub(crate) fn new(inner: BoxBody, encoding: Encoding, max_size: u64) -> io::Result<Self> {
+        Ok(Self {
+            inner,
+            decoder: Decoder::new(encoding, max_size)?,
+        })
+    }
+}
+
+impl Body for DecompressionBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        loop {
+            if self.decoder.is_none() {
+                return Poll::Ready(None);
+            }
+            let chunk = ready!(Pin::new(&mut self.inner).poll_next(cx));
+            let decoder = self.decoder.as_mut().unwrap();
+            let decoded = match chunk {
+                Some(Ok(chunk)) => decoder.decode(Some(&chunk)),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    let decoded = decoder.decode(None);
+                    self.decoder = None;
+                    decoded
+                }
+            };
+            match decoded {
+                Ok(decoded) if decoded.is_empty() => {}
+                Ok(decoded) => return Poll::Ready(Some(Ok(decoded))),
+                Err(err) => {
+                    self.decoder = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+        }
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+}
+
+impl std::fmt::Debug for DecompressionBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecompressionBody")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "decompression-zstd", feature = "decompression-gzip")
+))]
+mod tests {
+    use super::*;
+
+    fn decode(
+        encoding: Encoding,
+        data: &[u8],
+        max_size: u64,
+    ) -> Result<Vec<u8>, DecompressionError> {
+        let mut decoder = Decoder::new(encoding, max_size).unwrap().unwrap();
+        let mut out = decoder.decode(Some(data))?.to_vec();
+        out.extend_from_slice(&decoder.decode(None)?);
+        Ok(out)
+    }
+
+    #[cfg(feature = "decompression-zstd")]
+    #[test]
+    fn zstd_rejects_truncated_frame() {
+        let encoded = zstd::encode_all(&b"hello zstd ".repeat(32)[..], 0).unwrap();
+        assert_eq!(
+            decode(Encoding::Zstd, &encoded, 1024).unwrap(),
+            b"hello zstd ".repeat(32)
+        );
+        let truncated = &encoded[..encoded.len() - 4];
+        assert!(matches!(
+            decode(Encoding::Zstd, truncated, 1024),
+            Err(DecompressionError::Decode(_))
+        ));
+    }
+
+    #[cfg(feature = "decompression-gzip")]
+    #[test]
+    fn gzip_limits_output() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&[0; 4096]).unwrap();
+        let encoded = encoder.finish().unwrap();
+        assert_eq!(decode(Encoding::Gzip, &encoded, 4096).unwrap().len(), 4096);
+        assert!(matches!(
+            decode(Encoding::Gzip, &encoded, 4095),
+            Err(DecompressionError::LimitExceeded)
+        ));
+        assert!(matches!(
+            decode(Encoding::Gzip, &encoded[..encoded.len() / 2], 4096),
+            Err(DecompressionError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,403 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::body::{Body, BodyExt, BoxBody};
+use mtiny_core::http::{header, HeaderValue, StatusCode};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{BoxError, Request, Response};
+
+pub use crate::encoding::Encoding;
+
+mod body;
+pub use body::DecompressionBody;
+
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum DecompressionError {
+    Decode(std::io::Error),
+    LimitExceeded,
+}
+
+impl std::fmt::Display for DecompressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecompressionError::Decode(e) => write!(f, "failed to decompress body ({})", e),
+            DecompressionError::LimitExceeded => f.write_str("decompressed body too large"),
+        }
+    }
+}
+
+impl std::error::Error for DecompressionError {}
+
+impl IntoResponse for DecompressionError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            DecompressionError::Decode(_) => StatusCode::BAD_REQUEST,
+            DecompressionError::LimitExceeded => StatusCode::PAYLOAD_TOO_LARGE,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    max_size: u64,
+    #[cfg(feature = "decompression-br")]
+    br: bool,
+    #[cfg(feature = "decompression-zstd")]
+    zstd: bool,
+    #[cfg(feature = "decompression-gzip")]
+    gzip: bool,
+    #[cfg(feature = "decompression-deflate")]
+    deflate: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            #[cfg(feature = "decompression-br")]
+            br: true,
+            #[cfg(feature = "decompression-zstd")]
+            zstd: true,
+            #[cfg(feature = "decompression-gzip")]
+            gzip: true,
+            #[cfg(feature = "decompression-deflate")]
+            deflate: true,
+        }
+    }
+}
+
+impl Config {
+    fn enabled(&self, encoding: Encoding) -> bool {
+        match encoding {
+            #[cfg(feature = "decompression-br")]
+            Encoding::Brotli => self.br,
+            #[cfg(feature = "decompression-zstd")]
+            Encoding::Zstd => self.zstd,
+            #[cfg(feature = "decompression-gzip")]
+            Encoding::Gzip => self.gzip,
+            #[cfg(feature = "decompression-deflate")]
+            Encoding::Deflate => self.deflate,
+            Encoding::Identity => true,
+            // variants that only exist for the other direction
+            #[cfg(all(feature = "compression-br", not(feature = "decompression-br")))]
+            Encoding::Brotli => false,
+            #[cfg(all(feature = "compression-zstd", not(feature = "decompression-zstd")))]
+            Encoding::Zstd => false,
+            #[cfg(all(feature = "compression-gzip", not(feature = "decompression-gzip")))]
+            Encoding::Gzip => false,
+            #[cfg(all(
+                feature = "compression-deflate",
+                not(feature = "decompression-deflate")
+            ))]
+            Encoding::Deflate => false,
+        }
+    }
+
+    // the codings listed in Content-Encoding, in the order they were applied
+    fn encodings(&self, request: &Request<impl Sized>) -> Option<Vec<Encoding>> {
+        let mut encodings = Vec::new();
+        for value in request.headers().get_all(header::CONTENT_ENCODING) {
+            for token in value.to_str().ok()?.split(',').map(str::trim) {
+                if token.is_empty() {
+                    continue;
+                }
+                let encoding = Encoding::from_token(token).filter(|e| self.enabled(*e))?;
+                if encoding != Encoding::Identity {
+                    encodings.push(encoding);
+                }
+            }
+        }
+        Some(encodings)
+    }
+
+    fn unsupported(&self) -> Response {
+        let accepted = Encoding::PREFERRED
+            .iter()
+            .copied()
+            .filter(|encoding| self.enabled(*encoding))
+            .chain([Encoding::Identity])
+            .map(|encoding| encoding.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut response = StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+        if let Ok(value) = HeaderValue::from_str(&accepted) {
+            response
+                .headers_mut()
+                .insert(header::ACCEPT_ENCODING, value);
+        }
+        response
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Decompression<S> {
+    inner: S,
+    config: Config,
+}
+
+pub fn decompression() -> DecompressionWarp {
+    DecompressionWarp::new()
+}
+
+impl<S, B> Service<Request<B>> for Decompression<S>
+where
+    S: Service<Request<BoxBody>>,
+    S::Response: IntoResponse,
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = DecompressionFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let Some(encodings) = self.config.encodings(&request) else {
+            return DecompressionFuture::Rejected {
+                response: Some(self.config.unsupported()),
+            };
+        };
+        let mut request = request.map(BodyExt::boxed);
+        if request.headers().contains_key(header::CONTENT_ENCODING) {
+            let headers = request.headers_mut();
+            headers.remove(header::CONTENT_ENCODING);
+            headers.remove(header::CONTENT_LENGTH);
+        }
+        for encoding in encodings.into_iter().rev() {
+            let body = std::mem::take(request.body_mut());
+            match DecompressionBody::new(body, encoding, self.config.max_size) {
+                Ok(body) => *request.body_mut() = body.boxed(),
+                Err(_) => {
+                    return DecompressionFuture::Rejected {
+                        response: Some(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+                    }
+                }
+            }
+        }
+        DecompressionFuture::Inner {
+            fut: self.inner.call(request),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for Decompression<S>
+where
+    S: ReadyService<Request<BoxBody>>,
+    S::Response: IntoResponse,
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    #[project = DecompressionFutureProj]
+    pub enum DecompressionFuture<F> {
+        Inner {
+            #[pin]
+            fut: F,
+        },
+        Rejected {
+            response: Option<Response>,
+        },
+    }
+}
+
+impl<F, T, E> Future for DecompressionFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            DecompressionFutureProj::Inner { fut } => {
+                Poll::Ready(Ok(ready!(fut.poll(cx))?.into_response()))
+            }
+            DecompressionFutureProj::Rejected { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for DecompressionFuture<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecompressionFuture::Inner { .. } => f.debug_struct("DecompressionFuture::Inner"),
+            DecompressionFuture::Rejected { .. } => f.debug_struct("DecompressionFuture::Rejected"),
+        }
+        .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DecompressionWarp {
+    config: Config,
+}
+
+impl DecompressionWarp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.config.max_size = max_size;
+        self
+    }
+
+    #[cfg(feature = "decompression-br")]
+    pub fn br(mut self, enable: bool) -> Self {
+        self.config.br = enable;
+        self
+    }
+
+    #[cfg(feature = "decompression-zstd")]
+    pub fn zstd(mut self, enable: bool) -> Self {
+        self.config.zstd = enable;
+        self
+    }
+
+    #[cfg(feature = "decompression-gzip")]
+    pub fn gzip(mut self, enable: bool) -> Self {
+        self.config.gzip = enable;
+        self
+    }
+
+    #[cfg(feature = "decompression-deflate")]
+    pub fn deflate(mut self, enable: bool) -> Self {
+        self.config.deflate = enable;
+        self
+    }
+}
+
+impl<S> Wrap<S> for DecompressionWarp {
+    type Service = Decompression<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        Decompression {
+            inner,
+            config: self.config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_map_to_status() {
+        let response = DecompressionError::LimitExceeded.into_response();
+        assert_eq!(*response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let response =
+            DecompressionError::Decode(std::io::ErrorKind::InvalidData.into()).into_response();
+        assert_eq!(*response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "decompression-gzip")]
+    mod service {
+        use std::convert::Infallible;
+        use std::io::Write;
+        use std::task::Waker;
+
+        use mtiny_core::body::Bytes;
+        use mtiny_core::service::ServiceExt;
+        use mtiny_core::service_fn;
+
+        use super::*;
+
+        fn gzip(data: &[u8]) -> Vec<u8> {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+
+        // the handler answers with what it read, or with the error it hit
+        fn call(encoding: &'static str, body: Vec<u8>) -> Response {
+            let handler = service_fn(|mut request: Request<BoxBody>| async move {
+                let encoded = request.headers().contains_key(header::CONTENT_ENCODING);
+                let mut buf = Vec::new();
+                while let Some(chunk) = request.body_mut().next().await {
+                    match chunk {
+                        Ok(chunk) => buf.extend_from_slice(&chunk),
+                        Err(err) => match err.downcast::<DecompressionError>() {
+                            Ok(err) => return Ok::<_, Infallible>(err.into_response()),
+                            Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+                        },
+                    }
+                }
+                Ok(format!("{encoded}:{}", String::from_utf8_lossy(&buf)).into_response())
+            });
+            let service = handler.with(decompression().max_size(1024));
+            let request = Request::builder()
+                .header(header::CONTENT_ENCODING, encoding)
+                .body(Bytes::from(body))
+                .unwrap();
+
+            let mut cx = Context::from_waker(Waker::noop());
+            match std::pin::pin!(service.call(request)).poll(&mut cx) {
+                Poll::Ready(Ok(response)) => response,
+                _ => panic!("service did not complete"),
+            }
+        }
+
+        fn text(response: Response) -> String {
+            let mut body = response.into_body();
+            let mut cx = Context::from_waker(Waker::noop());
+            match std::pin::pin!(body.next()).poll(&mut cx) {
+                Poll::Ready(Some(Ok(chunk))) => String::from_utf8(chunk.to_vec()).unwrap(),
+                _ => panic!("body was not ready"),
+            }
+        }
+
+        #[test]
+        fn decodes_and_strips_the_encoding() {
+            let response = call("gzip", gzip(br#"{"hello":"world"}"#));
+            assert_eq!(*response.status(), StatusCode::OK);
+            assert_eq!(text(response), r#"false:{"hello":"world"}"#);
+
+            let response = call("identity", b"plain".to_vec());
+            assert_eq!(text(response), "false:plain");
+        }
+
+        #[cfg(feature = "decompression-deflate")]
+        #[test]
+        fn decodes_stacked_encodings_in_reverse() {
+            let mut deflate =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            deflate.write_all(&gzip(b"twice")).unwrap();
+            let response = call("gzip, deflate", deflate.finish().unwrap());
+            assert_eq!(text(response), "false:twice");
+        }
+
+        #[test]
+        fn rejects_unsupported_encodings() {
+            let response = call("compress", b"abc".to_vec());
+            assert_eq!(*response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            let accepted = response.headers()[header::ACCEPT_ENCODING]
+                .to_str()
+                .unwrap();
+            assert!(accepted.contains("gzip"), "{accepted}");
+            assert!(accepted.ends_with("identity"), "{accepted}");
+        }
+
+        #[test]
+        fn limits_the_decoded_size() {
+            let response = call("gzip", gzip(&[0; 64 * 1024]));
+            assert_eq!(*response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            let response = call("gzip", b"not gzip".to_vec());
+            assert_eq!(*response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
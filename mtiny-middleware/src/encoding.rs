@@ -0,0 +1,194 @@
+#[cfg(feature = "compression-any")]
+use mtiny_core::http::{header, HeaderMap, HeaderValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[cfg(any(feature = "compression-br", feature = "decompression-br"))]
+    Brotli,
+    #[cfg(any(feature = "compression-zstd", feature = "decompression-zstd"))]
+    Zstd,
+    #[cfg(any(feature = "compression-gzip", feature = "decompression-gzip"))]
+    Gzip,
+    #[cfg(any(feature = "compression-deflate", feature = "decompression-deflate"))]
+    Deflate,
+    Identity,
+}
+
+impl Encoding {
+    pub(crate) const PREFERRED: &'static [Encoding] = &[
+        #[cfg(any(feature = "compression-br", feature = "decompression-br"))]
+        Encoding::Brotli,
+        #[cfg(any(feature = "compression-zstd", feature = "decompression-zstd"))]
+        Encoding::Zstd,
+        #[cfg(any(feature = "compression-gzip", feature = "decompression-gzip"))]
+        Encoding::Gzip,
+        #[cfg(any(feature = "compression-deflate", feature = "decompression-deflate"))]
+        Encoding::Deflate,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            #[cfg(any(feature = "compression-br", feature = "decompression-br"))]
+            Encoding::Brotli => "br",
+            #[cfg(any(feature = "compression-zstd", feature = "decompression-zstd"))]
+            Encoding::Zstd => "zstd",
+            #[cfg(any(feature = "compression-gzip", feature = "decompression-gzip"))]
+            Encoding::Gzip => "gzip",
+            #[cfg(any(feature = "compression-deflate", feature = "decompression-deflate"))]
+            Encoding::Deflate => "deflate",
+            Encoding::Identity => "identity",
+        }
+    }
+
+    pub(crate) fn from_token(token: &str) -> Option<Self> {
+        #[cfg(any(feature = "compression-gzip", feature = "decompression-gzip"))]
+        if token.eq_ignore_ascii_case("x-gzip") {
+            return Some(Encoding::Gzip);
+        }
+        Self::PREFERRED
+            .iter()
+            .chain([&Encoding::Identity])
+            .copied()
+            .find(|encoding| token.eq_ignore_ascii_case(encoding.as_str()))
+    }
+}
+
+#[cfg(feature = "compression-any")]
+impl Encoding {
+    pub(crate) fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_static(self.as_str())
+    }
+
+    pub(crate) fn negotiate<F>(headers: &HeaderMap, enabled: F) -> Encoding
+    where
+        F: Fn(Encoding) -> bool,
+    {
+        let mut wildcard = None;
+        let mut explicit = Vec::new();
+        for (token, q) in accept_encoding(headers) {
+            if token == "*" {
+                wildcard = Some(q);
+            } else if let Some(encoding) = Encoding::from_token(token) {
+                explicit.push((encoding, q));
+            }
+        }
+
+        let mut best = (Encoding::Identity, 0);
+        for &encoding in Self::PREFERRED {
+            if !enabled(encoding) {
+                continue;
+            }
+            let q = explicit
+                .iter()
+                .find(|(candidate, _)| *candidate == encoding)
+                .map(|(_, q)| *q)
+                .or(wildcard)
+                .unwrap_or(0);
+            if q > best.1 {
+                best = (encoding, q);
+            }
+        }
+        best.0
+    }
+}
+
+#[cfg(feature = "compression-any")]
+fn accept_encoding(headers: &HeaderMap) -> impl Iterator<Item = (&str, u16)> {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let token = params.next()?.trim();
+            if token.is_empty() {
+                return None;
+            }
+            let mut q = 1000;
+            for param in params {
+                if let Some((name, value)) = param.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("q") {
+                        q = qvalue(value.trim())?;
+                    }
+                }
+            }
+            Some((token, q))
+        })
+}
+
+// q-values in thousandths, as defined in RFC 9110 section 12.4.2
+#[cfg(feature = "compression-any")]
+fn qvalue(value: &str) -> Option<u16> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac = format!("{frac:0<3}").parse::<u16>().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "compression-gzip", feature = "compression-br"))]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: &str) -> Encoding {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_str(accept).unwrap(),
+        );
+        Encoding::negotiate(&headers, |_| true)
+    }
+
+    #[test]
+    fn parses_qvalues() {
+        assert_eq!(qvalue("0"), Some(0));
+        assert_eq!(qvalue("0.5"), Some(500));
+        assert_eq!(qvalue("0.125"), Some(125));
+        assert_eq!(qvalue("1"), Some(1000));
+        assert_eq!(qvalue("1.000"), Some(1000));
+        assert_eq!(qvalue("1.5"), None);
+        assert_eq!(qvalue("0.1234"), None);
+        assert_eq!(qvalue("0.x"), None);
+        assert_eq!(qvalue("2"), None);
+    }
+
+    #[test]
+    fn prefers_highest_qvalue() {
+        assert_eq!(negotiate("gzip;q=0.8, br;q=0.5"), Encoding::Gzip);
+        assert_eq!(negotiate("gzip, br"), Encoding::Brotli);
+        assert_eq!(negotiate("GZIP;Q=1"), Encoding::Gzip);
+        assert_eq!(negotiate("x-gzip"), Encoding::Gzip);
+    }
+
+    #[test]
+    fn rejects_zero_and_invalid_qvalues() {
+        assert_eq!(negotiate("gzip;q=0"), Encoding::Identity);
+        assert_eq!(negotiate("br;q=1.5, gzip;q=0.1"), Encoding::Gzip);
+        assert_eq!(negotiate("unknown, identity"), Encoding::Identity);
+    }
+
+    #[test]
+    fn wildcard_covers_unlisted_encodings() {
+        assert_eq!(negotiate("*"), Encoding::Brotli);
+        assert_eq!(negotiate("br;q=0, *;q=0.5"), Encoding::PREFERRED[1]);
+        assert_eq!(negotiate("*;q=0"), Encoding::Identity);
+    }
+
+    #[test]
+    fn skips_disabled_encodings() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("br, gzip;q=0.5"),
+        );
+        let encoding = Encoding::negotiate(&headers, |encoding| encoding != Encoding::Brotli);
+        assert_eq!(encoding, Encoding::Gzip);
+        assert_eq!(Encoding::negotiate(&headers, |_| false), Encoding::Identity);
+    }
+}
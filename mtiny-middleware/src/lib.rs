@@ -1,5 +1,20 @@
-
 #![forbid(unsafe_code)]
 
 //#[cfg(feature = "core")]
 pub mod core;
+
+#[cfg(feature = "compression-any")]
+pub mod compression;
+#[cfg(feature = "decompression-any")]
+pub mod decompression;
+#[cfg(feature = "timeout")]
+pub mod timeout;
+#[cfg(any(feature = "compression-any", feature = "decompression-any"))]
+mod encoding;
+
+#[cfg(feature = "compression-any")]
+pub use compression::compression;
+#[cfg(feature = "decompression-any")]
+pub use decompression::decompression;
+#[cfg(feature = "timeout")]
+pub use timeout::timeout;
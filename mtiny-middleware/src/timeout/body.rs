@@ -0,0 +1,182 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use tokio::time::{Instant, Sleep};
+
+use mtiny_core::body::{Body, BoxBody, Bytes, SizeHint};
+use mtiny_core::http::HeaderMap;
+use mtiny_core::BoxError;
+
+use super::TimeoutError;
+
+pub struct TimeoutBody {
+    inner: BoxBody,
+    idle: Duration,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl TimeoutBody {
+    pub(super) fn new(inner: BoxBody, idle: Duration) -> Self {
+        Self {
+            inner,
+            idle,
+            sleep: Some(Box::pin(tokio::time::sleep(idle))),
+        }
+    }
+
+    fn reset(&mut self) {
+        if let (Some(sleep), Some(deadline)) =
+            (&mut self.sleep, Instant::now().checked_add(self.idle))
+        {
+            sleep.as_mut().reset(deadline);
+        }
+    }
+
+    fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<BoxError> {
+        if let Some(sleep) = &mut self.sleep {
+            ready!(sleep.as_mut().poll(cx));
+        }
+        self.sleep = None;
+        Poll::Ready(TimeoutError.into())
+    }
+}
+
+impl Body for TimeoutBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        if self.sleep.is_none() {
+            return Poll::Ready(None);
+        }
+        if let Poll::Ready(chunk) = Pin::new(&mut self.inner).poll_next(cx) {
+            self.reset();
+            return Poll::Ready(chunk);
+        }
+        self.poll_expired(cx).map(|err| Some(Err(err)))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        if self.sleep.is_none() {
+            return Poll::Ready(Ok(None));
+        }
+        if let Poll::Ready(trailers) = Pin::new(&mut self.inner).poll_trailers(cx) {
+            return Poll::Ready(trailers);
+        }
+        self.poll_expired(cx).map(Err)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl std::fmt::Debug for TimeoutBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeoutBody")
+            .field("inner", &self.inner)
+            .field("idle", &self.idle)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use tokio::time::timeout;
+
+    use mtiny_core::body::BodyExt;
+
+    use super::*;
+
+    // yields whatever the test sends, ends once the sender is dropped
+    struct Channel(mpsc::UnboundedReceiver<&'static str>);
+
+    impl Body for Channel {
+        type Error = BoxError;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            self.0
+                .poll_recv(cx)
+                .map(|chunk| chunk.map(|chunk| Ok(Bytes::from(chunk))))
+        }
+    }
+
+    // ends its data at once and never delivers trailers
+    struct NoTrailers;
+
+    impl Body for NoTrailers {
+        type Error = BoxError;
+
+        fn poll_next(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            Poll::Ready(None)
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Pending
+        }
+    }
+
+    fn body(idle: Duration) -> (mpsc::UnboundedSender<&'static str>, TimeoutBody) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (tx, TimeoutBody::new(Channel(rx).boxed(), idle))
+    }
+
+    const IDLE: Duration = Duration::from_millis(50);
+    const LESS: Duration = Duration::from_millis(40);
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_timeout_restarts_on_every_chunk() {
+        let (tx, mut body) = body(IDLE);
+
+        for chunk in ["a", "b", "c"] {
+            tokio::time::sleep(LESS).await;
+            tx.send(chunk).unwrap();
+            assert_eq!(body.next().await.unwrap().unwrap(), chunk);
+        }
+        assert!(timeout(LESS, body.next()).await.is_err());
+
+        drop(tx);
+        assert!(body.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_body_fails_once() {
+        let (tx, mut body) = body(IDLE);
+        tx.send("a").unwrap();
+        assert!(body.next().await.unwrap().is_ok());
+
+        let start = Instant::now();
+        let err = body.next().await.unwrap().unwrap_err();
+        assert!(err.is::<TimeoutError>());
+        assert_eq!(start.elapsed(), IDLE);
+
+        // the body is over once it timed out, even if more data arrives
+        tx.send("b").unwrap();
+        assert!(body.next().await.is_none());
+        assert_eq!(body.trailers().await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_for_trailers_times_out() {
+        let mut body = TimeoutBody::new(NoTrailers.boxed(), IDLE);
+        assert!(body.next().await.is_none());
+        assert!(body.trailers().await.unwrap_err().is::<TimeoutError>());
+    }
+}
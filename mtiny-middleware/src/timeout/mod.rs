@@ -0,0 +1,435 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use pin_project_lite::pin_project;
+use tokio::time::{Instant, Sleep};
+
+use mtiny_core::body::{Body, BodyExt, BoxBody};
+use mtiny_core::http::StatusCode;
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{ReadyService, Service, Wrap};
+use mtiny_core::{BoxError, Request, Response};
+
+mod body;
+pub use body::TimeoutBody;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutError;
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request timed out")
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+// shared between the middleware, the bodies it wraps and any route that
+// overrides the duration, so a later override still moves the deadline
+#[derive(Debug, Clone)]
+pub struct RequestTimeout {
+    nanos: Arc<AtomicU64>,
+}
+
+impl RequestTimeout {
+    fn new(duration: Duration) -> Self {
+        let timeout = Self {
+            nanos: Arc::new(AtomicU64::new(0)),
+        };
+        timeout.set(duration);
+        timeout
+    }
+
+    pub fn get(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, duration: Duration) {
+        let nanos = duration.as_nanos().min(u64::MAX as u128) as u64;
+        self.nanos.store(nanos, Ordering::Relaxed);
+    }
+}
+
+struct Timer {
+    start: Instant,
+    duration: Duration,
+    timeout: RequestTimeout,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl Timer {
+    fn new(start: Instant, timeout: RequestTimeout) -> Self {
+        let duration = timeout.get();
+        Self {
+            start,
+            duration,
+            sleep: Box::pin(tokio::time::sleep_until(
+                start.checked_add(duration).unwrap_or(start),
+            )),
+            timeout,
+        }
+    }
+
+    fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let duration = self.timeout.get();
+        if duration != self.duration {
+            self.duration = duration;
+            if let Some(deadline) = self.start.checked_add(duration) {
+                self.sleep.as_mut().reset(deadline);
+            }
+        }
+        self.sleep.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("start", &self.start)
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
+
+type OnTimeout = dyn Fn() -> Response + Send + Sync;
+
+#[derive(Clone)]
+struct Config {
+    duration: Duration,
+    on_timeout: Arc<OnTimeout>,
+    body_timeout: Option<Duration>,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("duration", &self.duration)
+            .field("body_timeout", &self.body_timeout)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Timeout<S> {
+    inner: S,
+    config: Config,
+}
+
+pub fn timeout(duration: Duration) -> TimeoutWarp {
+    TimeoutWarp::new(duration)
+}
+
+impl<S, B> Service<Request<B>> for Timeout<S>
+where
+    S: Service<Request<BoxBody>>,
+    S::Response: IntoResponse,
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = TimeoutFuture<S::Future>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let start = Instant::now();
+        let timeout = RequestTimeout::new(self.config.duration);
+        let mut request = request.map(BodyExt::boxed);
+        request.extensions_mut().insert_cloneable(timeout.clone());
+        if let Some(idle) = self.config.body_timeout {
+            let body = std::mem::take(request.body_mut());
+            *request.body_mut() = TimeoutBody::new(body, idle).boxed();
+        }
+        TimeoutFuture {
+            fut: self.inner.call(request),
+            timer: Timer::new(start, timeout),
+            config: Some(self.config.clone()),
+        }
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for Timeout<S>
+where
+    S: ReadyService<Request<BoxBody>>,
+    S::Response: IntoResponse,
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+pin_project! {
+    pub struct TimeoutFuture<F> {
+        #[pin]
+        fut: F,
+        timer: Timer,
+        config: Option<Config>,
+    }
+}
+
+impl<F, T, E> Future for TimeoutFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: IntoResponse,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        // checked first so a handler that gives up on a timed out body does
+        // not race the timeout response
+        if this.timer.poll_expired(cx).is_ready() {
+            let config = this.config.take().expect("polled after completion");
+            return Poll::Ready(Ok((config.on_timeout)()));
+        }
+        let output = ready!(this.fut.poll(cx));
+        let config = this.config.take().expect("polled after completion");
+        let response = output?.into_response();
+        match config.body_timeout {
+            Some(idle) => {
+                Poll::Ready(Ok(response.map(|body| TimeoutBody::new(body, idle).boxed())))
+            }
+            None => Poll::Ready(Ok(response)),
+        }
+    }
+}
+
+impl<F> fmt::Debug for TimeoutFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutFuture")
+            .field("fut", &core::any::type_name::<F>())
+            .field("timer", &self.timer)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeoutWarp {
+    config: Config,
+}
+
+impl TimeoutWarp {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            config: Config {
+                duration,
+                on_timeout: Arc::new(|| StatusCode::REQUEST_TIMEOUT.into_response()),
+                body_timeout: None,
+            },
+        }
+    }
+
+    pub fn status(self, status: StatusCode) -> Self {
+        self.on_timeout(move || status.into_response())
+    }
+
+    pub fn on_timeout<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> Response + Send + Sync + 'static,
+    {
+        self.config.on_timeout = Arc::new(f);
+        self
+    }
+
+    // the duration set here, or by a route through `RequestTimeout`, only
+    // bounds the time until the response head is ready. bodies are covered by
+    // this idle timeout instead, which restarts with every chunk, so a stalled
+    // upload or stream is cut off while a long download that keeps moving is
+    // not. the request body fails with `TimeoutError`, the response body
+    // ends with it and the connection is closed
+    pub fn body_timeout(mut self, idle: Duration) -> Self {
+        self.config.body_timeout = Some(idle);
+        self
+    }
+}
+
+impl<S> Wrap<S> for TimeoutWarp {
+    type Service = Timeout<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        Timeout {
+            inner,
+            config: self.config,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeoutOverride<S> {
+    inner: S,
+    duration: Duration,
+}
+
+pub fn timeout_override(duration: Duration) -> TimeoutOverrideWarp {
+    TimeoutOverrideWarp { duration }
+}
+
+impl<S, B> Service<Request<B>> for TimeoutOverride<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        if let Some(timeout) = request.extensions().get::<RequestTimeout>() {
+            timeout.set(self.duration);
+        }
+        self.inner.call(request)
+    }
+}
+
+impl<S, B> ReadyService<Request<B>> for TimeoutOverride<S>
+where
+    S: ReadyService<Request<B>>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutOverrideWarp {
+    duration: Duration,
+}
+
+impl<S> Wrap<S> for TimeoutOverrideWarp {
+    type Service = TimeoutOverride<S>;
+    fn wrap(self, inner: S) -> Self::Service {
+        TimeoutOverride {
+            inner,
+            duration: self.duration,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use mtiny_core::body::Bytes;
+    use mtiny_core::service::ServiceExt;
+    use mtiny_core::service_fn;
+
+    use super::*;
+
+    const LIMIT: Duration = Duration::from_millis(50);
+
+    // yields its chunk and then never makes progress again
+    struct Stalled(Option<&'static str>);
+
+    impl Body for Stalled {
+        type Error = Infallible;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            match self.0.take() {
+                Some(chunk) => Poll::Ready(Some(Ok(Bytes::from(chunk)))),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    // sleeps for the duration in the request body before answering
+    fn slow() -> impl Service<Request<BoxBody>, Response = &'static str, Error = Infallible> + Clone
+    {
+        service_fn(|mut request: Request<BoxBody>| async move {
+            let millis = request.body_mut().next().await.unwrap().unwrap();
+            let millis = std::str::from_utf8(&millis).unwrap().parse().unwrap();
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            Ok("finished")
+        })
+    }
+
+    fn request(millis: u64) -> Request<BoxBody> {
+        Request::new(Bytes::from(millis.to_string()).boxed())
+    }
+
+    async fn call<S>(service: S, request: Request<BoxBody>) -> Response
+    where
+        S: Service<Request<BoxBody>, Response = Response, Error = Infallible>,
+    {
+        service.call(request).await.unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn answers_before_the_deadline() {
+        let response = call(slow().with(timeout(LIMIT)), request(40)).await;
+        assert_eq!(*response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_after_the_deadline() {
+        let start = Instant::now();
+        let response = call(slow().with(timeout(LIMIT)), request(60)).await;
+        assert_eq!(*response.status(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(start.elapsed(), LIMIT);
+
+        let warp = timeout(LIMIT).status(StatusCode::SERVICE_UNAVAILABLE);
+        let response = call(slow().with(warp), request(60)).await;
+        assert_eq!(*response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let warp = timeout(LIMIT).on_timeout(|| StatusCode::GATEWAY_TIMEOUT.into_response());
+        let response = call(slow().with(warp), request(60)).await;
+        assert_eq!(*response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn routes_can_override_the_deadline() {
+        let service = slow()
+            .with(timeout_override(Duration::from_millis(100)))
+            .with(timeout(LIMIT));
+        let response = call(service.clone(), request(80)).await;
+        assert_eq!(*response.status(), StatusCode::OK);
+
+        let response = call(service, request(120)).await;
+        assert_eq!(*response.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_uploads_fail_in_the_handler() {
+        let handler = service_fn(|mut request: Request<BoxBody>| async move {
+            let mut received = 0;
+            while let Some(chunk) = request.body_mut().next().await {
+                match chunk {
+                    Ok(chunk) => received += chunk.len(),
+                    Err(err) => {
+                        assert!(err.is::<TimeoutError>());
+                        return Ok::<_, Infallible>(format!("stalled after {received}"));
+                    }
+                }
+            }
+            Ok(format!("received {received}"))
+        });
+        let service = handler.with(timeout(Duration::from_secs(5)).body_timeout(LIMIT));
+
+        let response = call(&service, request(1234)).await;
+        assert_eq!(response.into_body().next().await.unwrap().unwrap(), "received 4");
+
+        let response = call(&service, Request::new(Stalled(Some("12")).boxed())).await;
+        assert_eq!(response.into_body().next().await.unwrap().unwrap(), "stalled after 2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_responses_are_cut_off() {
+        let handler = service_fn(|_: Request<BoxBody>| async {
+            Ok::<_, Infallible>(Response::new(Stalled(Some("partial")).boxed()))
+        });
+        let service = handler.with(timeout(Duration::from_secs(5)).body_timeout(LIMIT));
+
+        let mut body = call(service, request(0)).await.into_body();
+        assert_eq!(body.next().await.unwrap().unwrap(), "partial");
+        let start = Instant::now();
+        assert!(body.next().await.unwrap().unwrap_err().is::<TimeoutError>());
+        assert_eq!(start.elapsed(), LIMIT);
+        assert!(body.next().await.is_none());
+    }
+}
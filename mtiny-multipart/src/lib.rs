@@ -1,16 +1,22 @@
 use actix_http::error::PayloadError;
-use actix_http::header::HeaderMap as ActixHeaderMap;
+use actix_http::header::{
+    HeaderMap as ActixHeaderMap, HeaderValue as ActixHeaderValue, CONTENT_TYPE as ACTIX_CONTENT_TYPE,
+};
 use bytes::Bytes;
+use core::cell::Cell;
 use core::task::Poll;
 use futures_util::Stream;
 use futures_util::TryStreamExt;
 
-use http::{header, HeaderMap};
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+use std::rc::Rc;
 
 pin_project_lite::pin_project! {
     pub struct Multipart{
         #[pin]
         inner: actix_multipart::Multipart,
+        // the error the body stream failed with, actix only keeps an io error kind
+        error: Rc<Cell<Option<Box<dyn std::error::Error>>>>,
     }
 }
 
@@ -21,15 +27,23 @@ impl Multipart {
     {
         Self::boundary(headers)?;
 
-        let content_type = headers.get(&header::CONTENT_TYPE).unwrap().to_owned();
+        let content_type = headers.get(&header::CONTENT_TYPE).unwrap();
+        let content_type = ActixHeaderValue::from_bytes(content_type.as_bytes())
+            .map_err(|_| MultipartError::UnsupportedContentType)?;
 
         let mut headers = ActixHeaderMap::with_capacity(1);
-        headers.append(header::CONTENT_TYPE, content_type);
+        headers.append(ACTIX_CONTENT_TYPE, content_type);
 
-        let stream = stream.map_err(|_| PayloadError::Io(std::io::ErrorKind::Other.into()));
+        let error = Rc::new(Cell::new(None));
+        let slot = error.clone();
+        let stream = stream.map_err(move |err| {
+            slot.set(Some(err));
+            PayloadError::Io(std::io::ErrorKind::Other.into())
+        });
 
         Ok(Self {
             inner: actix_multipart::Multipart::new(&headers, stream),
+            error,
         })
     }
 
@@ -63,7 +77,12 @@ impl Field {
             headers: field
                 .headers()
                 .into_iter()
-                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .filter_map(|(k, v)| {
+                    Some((
+                        HeaderName::from_bytes(k.as_str().as_bytes()).ok()?,
+                        HeaderValue::from_bytes(v.as_bytes()).ok()?,
+                    ))
+                })
                 .collect(),
             inner: field,
         }
@@ -91,12 +110,14 @@ impl Stream for Multipart {
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        match self.project().inner.poll_next(cx) {
+        let this = self.project();
+        match this.inner.poll_next(cx) {
             Poll::Pending => Poll::Pending,
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Ready(Some(Ok(field))) => Poll::Ready(Some(Ok(Field::from_actix(field)))),
             Poll::Ready(Some(Err(err))) => {
-                Poll::Ready(Some(Err(MultipartError::Other(err.into()))))
+                let err = this.error.take().unwrap_or_else(|| err.into());
+                Poll::Ready(Some(Err(MultipartError::Other(err))))
             }
         }
     }
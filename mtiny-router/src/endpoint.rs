@@ -0,0 +1,38 @@
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::util::{BoxService, UnsyncBoxService};
+use mtiny_core::service::{Service, ServiceExt};
+use mtiny_core::{BoxError, Request, Response};
+
+pub trait IntoEndpoint<E> {
+    fn into_endpoint(self) -> E;
+}
+
+impl<S, B> IntoEndpoint<BoxService<Request<B>, Response, BoxError>> for S
+where
+    B: 'static,
+    S: Service<Request<B>> + Send + Sync + 'static,
+    S::Future: Send + 'static,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+{
+    fn into_endpoint(self) -> BoxService<Request<B>, Response, BoxError> {
+        self.map_response(IntoResponse::into_response)
+            .map_err(Into::into)
+            .boxed()
+    }
+}
+
+impl<S, B> IntoEndpoint<UnsyncBoxService<Request<B>, Response, BoxError>> for S
+where
+    B: 'static,
+    S: Service<Request<B>> + 'static,
+    S::Future: 'static,
+    S::Response: IntoResponse,
+    S::Error: Into<BoxError>,
+{
+    fn into_endpoint(self) -> UnsyncBoxService<Request<B>, Response, BoxError> {
+        self.map_response(IntoResponse::into_response)
+            .map_err(Into::into)
+            .boxed_unsync()
+    }
+}
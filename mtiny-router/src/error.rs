@@ -1,65 +1,67 @@
+use mtiny_core::body::BoxBody;
 use mtiny_core::Request;
+use sync_wrapper::SyncWrapper;
 
+// the request is only reachable through `&mut self`, which keeps the error
+// `Sync` without requiring it from the request
 #[derive(Debug)]
-pub struct NotFound {
-    request: Request,
+pub struct NotFound<B = BoxBody> {
+    request: SyncWrapper<Request<B>>,
 }
 
-impl NotFound {
-    pub fn new(request: Request) -> Self {
-        Self { request }
+impl<B> NotFound<B> {
+    pub fn new(request: Request<B>) -> Self {
+        Self {
+            request: SyncWrapper::new(request),
+        }
     }
 
-    pub fn request_ref(&self) -> &Request {
-        &self.request
+    pub fn request_mut(&mut self) -> &mut Request<B> {
+        self.request.get_mut()
     }
 
-    pub fn request_mut(&mut self) -> &mut Request {
-        &mut self.request
-    }
-
-    pub fn into_request(self) -> Request {
-        self.request
+    pub fn into_request(self) -> Request<B> {
+        self.request.into_inner()
     }
 }
 
-impl std::error::Error for NotFound {
+impl<B: std::fmt::Debug> std::error::Error for NotFound<B> {
     
 }
-impl std::fmt::Display for NotFound {
+impl<B> std::fmt::Display for NotFound<B> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("router not found")
     }
 }
 
 
+// the request is only reachable through `&mut self`, which keeps the error
+// `Sync` without requiring it from the request
 #[derive(Debug)]
-pub struct MethodNotAllowed {
-    request: Request,
+pub struct MethodNotAllowed<B = BoxBody> {
+    request: SyncWrapper<Request<B>>,
 }
 
-impl MethodNotAllowed {
-    pub fn new(request: Request) -> Self {
-        Self { request }
-    }
-
-    pub fn request_ref(&self) -> &Request {
-        &self.request
+impl<B> MethodNotAllowed<B> {
+    pub fn new(request: Request<B>) -> Self {
+        Self {
+            request: SyncWrapper::new(request),
+        }
     }
 
-    pub fn request_mut(&mut self) -> &mut Request {
-        &mut self.request
+    pub fn request_mut(&mut self) -> &mut Request<B> {
+        self.request.get_mut()
     }
 
-    pub fn into_request(self) -> Request {
-        self.request
+    pub fn into_request(self) -> Request<B> {
+        self.request.into_inner()
     }
 }
 
-impl std::error::Error for MethodNotAllowed {
+impl<B: std::fmt::Debug> std::error::Error for MethodNotAllowed<B> {
     
 }
-impl std::fmt::Display for MethodNotAllowed {
+impl<B> std::fmt::Display for MethodNotAllowed<B> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("router not found")
     }
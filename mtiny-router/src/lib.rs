@@ -1,7 +1,9 @@
+mod endpoint;
 mod method;
 mod router;
 
 pub mod error;
 
+pub use endpoint::*;
 pub use method::*;
 pub use router::*;
@@ -1,39 +1,64 @@
+use core::marker::PhantomData;
+
 use mtiny_core::{
+    body::BoxBody,
     http::Method,
-    response::IntoResponse,
-    service::{util::BoxService, Service, ServiceExt},
+    service::{
+        util::{BoxService, UnsyncBoxService},
+        Service,
+    },
     BoxError, Request, Response,
 };
 
-use crate::{error::MethodNotAllowed, RouteFuture};
+use crate::{error::MethodNotAllowed, IntoEndpoint, RouteFuture};
+
+pub type LocalMethodRouter<B = BoxBody> =
+    MethodRouter<B, UnsyncBoxService<Request<B>, Response, BoxError>>;
 
-pub struct MethodRouter {
-    get: Option<BoxService<Request, Response, BoxError>>,
-    post: Option<BoxService<Request, Response, BoxError>>,
-    put: Option<BoxService<Request, Response, BoxError>>,
-    delete: Option<BoxService<Request, Response, BoxError>>,
-    head: Option<BoxService<Request, Response, BoxError>>,
-    patch: Option<BoxService<Request, Response, BoxError>>,
-    trace: Option<BoxService<Request, Response, BoxError>>,
-    options: Option<BoxService<Request, Response, BoxError>>,
+pub struct MethodRouter<B = BoxBody, E = BoxService<Request<B>, Response, BoxError>> {
+    get: Option<E>,
+    post: Option<E>,
+    put: Option<E>,
+    delete: Option<E>,
+    head: Option<E>,
+    patch: Option<E>,
+    trace: Option<E>,
+    options: Option<E>,
+    _body: PhantomData<fn(Request<B>)>,
 }
 
 macro_rules! method_router_impl_fn {
     ($method:ident) => {
         pub fn $method<S>(mut self, service: S) -> Self
         where
-            S: Service<Request> + 'static,
-            S::Response: IntoResponse,
-            S::Error: Into<BoxError>,
+            S: IntoEndpoint<E>,
         {
-            self.$method = Some(Self::into_box_service(service));
+            self.$method = Some(service.into_endpoint());
             self
         }
     };
 }
 
-impl MethodRouter {
-    fn new() -> Self {
+impl<B> MethodRouter<B> {
+    pub fn new() -> Self {
+        Self::empty()
+    }
+}
+
+impl<B> LocalMethodRouter<B> {
+    pub fn local() -> Self {
+        Self::empty()
+    }
+}
+
+impl<B> Default for MethodRouter<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E> MethodRouter<B, E> {
+    fn empty() -> Self {
         Self {
             get: None,
             post: None,
@@ -43,6 +68,7 @@ impl MethodRouter {
             patch: None,
             trace: None,
             options: None,
+            _body: PhantomData,
         }
     }
     method_router_impl_fn!(get);
@@ -53,25 +79,17 @@ impl MethodRouter {
     method_router_impl_fn!(patch);
     method_router_impl_fn!(trace);
     method_router_impl_fn!(options);
-
-    fn into_box_service<S>(service: S) -> BoxService<Request, Response, BoxError>
-    where
-        S: Service<Request> + 'static,
-        S::Response: IntoResponse,
-        S::Error: Into<BoxError>,
-    {
-        service
-            .map_response(IntoResponse::into_response)
-            .map_err(Into::into)
-            .boxed()
-    }
 }
 
-impl Service<Request> for MethodRouter {
+impl<B, E> Service<Request<B>> for MethodRouter<B, E>
+where
+    B: core::fmt::Debug + Send + 'static,
+    E: Service<Request<B>, Response = Response, Error = BoxError>,
+{
     type Response = Response;
     type Error = BoxError;
-    type Future = RouteFuture;
-    fn call(&self, request: Request) -> Self::Future {
+    type Future = RouteFuture<E::Future>;
+    fn call(&self, request: Request<B>) -> Self::Future {
         macro_rules! method_call {
             ($req:expr, $method:expr, $svc:expr) => {
                 if $method == $req.method() {
@@ -101,11 +119,9 @@ impl Service<Request> for MethodRouter {
 
 macro_rules! route_method_impl_fn {
     ($method:ident) => {
-        pub fn $method<S>(service: S) -> MethodRouter
+        pub fn $method<S, B>(service: S) -> MethodRouter<B>
         where
-            S: Service<Request> + 'static,
-            S::Response: IntoResponse,
-            S::Error: Into<BoxError>,
+            S: IntoEndpoint<BoxService<Request<B>, Response, BoxError>>,
         {
             MethodRouter::new().$method(service)
         }
@@ -1,4 +1,5 @@
 use core::future::Future;
+use core::marker::PhantomData;
 use core::panic;
 use core::task::Poll;
 use std::collections::HashMap;
@@ -7,30 +8,48 @@ use matchit::Match;
 use pin_project_lite::pin_project;
 
 use mtiny_core::http::uri::{Parts, PathAndQuery, Uri};
-use mtiny_core::response::IntoResponse;
-use mtiny_core::service::{util::BoxFuture, util::BoxService, Service, ServiceExt};
+use mtiny_core::body::BoxBody;
+use mtiny_core::service::util::{BoxFuture, BoxService, UnsyncBoxService};
+use mtiny_core::service::Service;
 use mtiny_core::{BoxError, Request, Response};
 
+use crate::endpoint::IntoEndpoint;
 use crate::error::NotFound;
 
-const PRIVATE_TAIL_PARAM: &'static str = "_private_xycy_tail_param";
-enum Endpoint {
-    Full(BoxService<Request, Response, BoxError>),
-    Nest(BoxService<Request, Response, BoxError>),
+const PRIVATE_TAIL_PARAM: &str = "_private_xycy_tail_param";
+enum Endpoint<E> {
+    Full(E),
+    Nest(E),
 }
 
-pub struct Router {
-    inner: matchit::Router<Endpoint>,
+pub type LocalRouter<B = BoxBody> = Router<B, UnsyncBoxService<Request<B>, Response, BoxError>>;
+
+pub struct Router<B = BoxBody, E = BoxService<Request<B>, Response, BoxError>> {
+    inner: matchit::Router<Endpoint<E>>,
+    _body: PhantomData<fn(Request<B>)>,
 }
 
-impl Router {
+impl<B> Router<B> {
     pub fn new() -> Self {
+        Self::empty()
+    }
+}
+
+impl<B> LocalRouter<B> {
+    pub fn local() -> Self {
+        Self::empty()
+    }
+}
+
+impl<B, E> Router<B, E> {
+    fn empty() -> Self {
         Self {
             inner: matchit::Router::new(),
+            _body: PhantomData,
         }
     }
 
-    fn add_route(mut self, path: String, endpoint: Endpoint) -> Self {
+    fn add_route(mut self, path: String, endpoint: Endpoint<E>) -> Self {
         if let Err(e) = self.inner.insert(path, endpoint) {
             panic!("{e}")
         }
@@ -39,9 +58,7 @@ impl Router {
 
     pub fn route<S>(self, path: &str, service: S) -> Self
     where
-    S: Service<Request> + 'static,
-    S::Response: IntoResponse,
-    S::Error: Into<BoxError>,
+        S: IntoEndpoint<E>,
     {
         if !path.starts_with('/') {
             panic!("Path must start with a `/`");
@@ -51,15 +68,13 @@ impl Router {
         } else {
             path.into()
         };
-        self.add_route(path, Endpoint::Full(Self::into_box_service(service)))
+        self.add_route(path, Endpoint::Full(service.into_endpoint()))
     }
 
 
     pub fn nest<S>(self, path: &str, service: S) -> Self
     where
-        S: Service<Request> + 'static,
-        S::Response: IntoResponse,
-        S::Error: Into<BoxError>,
+        S: IntoEndpoint<E>,
     {
         if !path.starts_with('/') {
             panic!("Path must start with a `/`");
@@ -69,33 +84,31 @@ impl Router {
         } else {
             format!("{path}/*{PRIVATE_TAIL_PARAM}")
         };
-        self.add_route(path, Endpoint::Nest(Self::into_box_service(service)))
+        self.add_route(path, Endpoint::Nest(service.into_endpoint()))
     }
+}
 
-    fn into_box_service<S>(service: S) -> BoxService<Request, Response, BoxError>
-    where
-        S: Service<Request> + 'static,
-        S::Response: IntoResponse,
-        S::Error: Into<BoxError>,
-    {
-        service
-            .map_response(IntoResponse::into_response)
-            .map_err(Into::into)
-            .boxed()
+impl<B> Default for Router<B> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl core::fmt::Debug for Router {
+impl<B, E> core::fmt::Debug for Router<B, E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Router").finish()
     }
 }
 
-impl Service<Request> for Router {
+impl<B, E> Service<Request<B>> for Router<B, E>
+where
+    B: core::fmt::Debug + Send + 'static,
+    E: Service<Request<B>, Response = Response, Error = BoxError>,
+{
     type Response = Response;
     type Error = BoxError;
-    type Future = RouteFuture;
-    fn call(&self, mut request: Request) -> Self::Future {
+    type Future = RouteFuture<E::Future>;
+    fn call(&self, mut request: Request<B>) -> Self::Future {
         match self.inner.at(request.uri().path()) {
             Ok(Match { value, params }) => {
                 let fut = match value {
@@ -120,7 +133,7 @@ impl Service<Request> for Router {
     }
 }
 
-fn modify_path_and_query(request: &mut Request, path: &str) {
+fn modify_path_and_query<B>(request: &mut Request<B>, path: &str) {
     let uri = request.uri_mut();
 
     let path_and_query = if let Some(query) = uri.query() {
@@ -175,12 +188,12 @@ fn get_params(params: matchit::Params) -> (Vec<(String, String)>, Option<String>
     (params, path)
 }
 
-fn insert_params(request: &mut Request, captures: Vec<(String, String)>) {
+fn insert_params<B>(request: &mut Request<B>, captures: Vec<(String, String)>) {
     let extensions = request.extensions_mut();
     let params = if let Some(params) = extensions.get_mut::<Params>() {
         params
     } else {
-        extensions.insert(Params::new());
+        extensions.insert_cloneable(Params::new());
         extensions.get_mut::<Params>().unwrap()
     };
     params.0.extend(captures)
@@ -188,10 +201,10 @@ fn insert_params(request: &mut Request, captures: Vec<(String, String)>) {
 
 pin_project! {
     #[project = RouteFutureProj]
-pub enum  RouteFuture{
+pub enum  RouteFuture<F = BoxFuture<Result<Response, BoxError>>>{
     Future {
         #[pin]
-        fut: BoxFuture<Result<Response,BoxError>>,
+        fut: F,
     },
     Error {
         err: Option<BoxError>
@@ -199,7 +212,10 @@ pub enum  RouteFuture{
    }
 }
 
-impl Future for RouteFuture {
+impl<F> Future for RouteFuture<F>
+where
+    F: Future<Output = Result<Response, BoxError>>,
+{
     type Output = Result<Response, BoxError>;
     fn poll(
         self: core::pin::Pin<&mut Self>,
@@ -211,3 +227,71 @@ impl Future for RouteFuture {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::future::{ready, Ready};
+    use std::pin::pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Waker};
+
+    use mtiny_core::service::{ReadyService, ServiceExt};
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[derive(Clone, Default)]
+    struct Gate(Arc<AtomicBool>);
+
+    impl Service<Request> for Gate {
+        type Response = ();
+        type Error = Infallible;
+        type Future = Ready<Result<(), Infallible>>;
+
+        fn call(&self, _request: Request) -> Self::Future {
+            ready(Ok(()))
+        }
+    }
+
+    impl ReadyService<Request> for Gate {
+        fn poll_ready(&self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.load(Ordering::Acquire) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    fn request(path: &str) -> Request {
+        Request::builder()
+            .uri(path)
+            .body(BoxBody::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn router_is_send_and_sync() {
+        assert_send_sync::<Router>();
+        assert_send_sync::<Arc<Router>>();
+    }
+
+    #[test]
+    fn dispatch_waits_for_opted_in_endpoints() {
+        let gate = Gate::default();
+        let router = Router::new()
+            .route("/gated", gate.clone().wait_ready())
+            .route("/plain", Gate::default());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        assert!(pin!(router.call(request("/plain"))).poll(&mut cx).is_ready());
+
+        let mut gated = pin!(router.call(request("/gated")));
+        assert!(gated.as_mut().poll(&mut cx).is_pending());
+        gate.0.store(true, Ordering::Release);
+        assert!(matches!(gated.as_mut().poll(&mut cx), Poll::Ready(Ok(_))));
+    }
+}
@@ -1,35 +1,44 @@
+use std::cell::Cell;
 use std::convert::Infallible;
 use std::future::Future;
+use std::net::SocketAddr;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
 
 use pin_project_lite::pin_project;
 
 use actix_http::body::{BodySize, MessageBody};
-use actix_http::Payload;
-use futures_core::Stream;
+use actix_http::{ConnectionType, HttpMessage};
 use mtiny_core::body::{Body, BodyExt, BoxBody, Bytes};
+use mtiny_core::http::upgrade::OnUpgrade;
+use mtiny_core::http::uri::Scheme;
+use mtiny_core::http::{header, request, HeaderName, HeaderValue, Method, Uri, Version};
 use mtiny_core::response::IntoResponse;
 use mtiny_core::service::{Service, ServiceExt};
-use mtiny_core::{BoxError, Request};
+use mtiny_core::{ConnectionInfo, PeerAddr, Request};
 
-use crate::PeerAddr;
+use crate::connection;
+use crate::proxy::ProxyHeader;
 
-pin_project! {
-    struct IntoTinyBody {
-        #[pin]
-        body: Payload,
-    }
-}
+use super::io;
+use super::payload::PayloadBody;
+use super::upgrade::{self, Pending};
 
-impl Body for IntoTinyBody {
-    type Error = BoxError;
+struct Connection {
+    id: u64,
+    local_addr: SocketAddr,
+    proxy: Option<ProxyHeader>,
+    request_count: Cell<u64>,
+}
 
-    fn poll_next(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
-        self.project().body.poll_next(cx).map_err(Into::into)
+pub(crate) fn on_connect(stream: &io::Stream, extensions: &mut actix_http::Extensions) {
+    if let Ok(local_addr) = stream.io.local_addr() {
+        extensions.insert(Connection {
+            id: connection::next_id(),
+            local_addr,
+            proxy: stream.proxy.clone(),
+            request_count: Cell::new(0),
+        });
     }
 }
 
@@ -37,11 +46,23 @@ pin_project! {
     pub(crate) struct IntoActixBody {
         #[pin]
         body: BoxBody,
+        data_done: bool,
+        trailers_wanted: bool,
+    }
+}
+
+impl IntoActixBody {
+    fn new(body: BoxBody) -> Self {
+        Self {
+            body,
+            data_done: false,
+            trailers_wanted: false,
+        }
     }
 }
 
 impl MessageBody for IntoActixBody {
-    type Error = BoxError;
+    type Error = Box<dyn std::error::Error>;
 
     fn size(&self) -> BodySize {
         if let Some(size) = self.body.size_hint().exact() {
@@ -51,54 +72,217 @@ impl MessageBody for IntoActixBody {
         }
     }
 
+    // actix-http cannot write trailers; rather than dropping them silently
+    // the body fails when the client asked for trailers and there are some
     fn poll_next(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
-        self.project().body.poll_next(cx)
+        let mut this = self.project();
+        if !*this.data_done {
+            match ready!(this.body.as_mut().poll_next(cx)) {
+                Some(res) => return Poll::Ready(Some(res.map_err(|err| err as Self::Error))),
+                None => *this.data_done = true,
+            }
+        }
+        if !*this.trailers_wanted {
+            return Poll::Ready(None);
+        }
+        let trailers = ready!(this.body.poll_trailers(cx)).map_err(|err| err as Self::Error);
+        *this.trailers_wanted = false;
+        match trailers {
+            Ok(Some(trailers)) if !trailers.is_empty() => Poll::Ready(Some(Err(
+                "response trailers are not supported by the actix backend".into(),
+            ))),
+            Ok(_) => Poll::Ready(None),
+            Err(err) => Poll::Ready(Some(Err(err))),
+        }
+    }
+}
+
+pub(crate) struct ActixService<S> {
+    inner: S,
+}
+
+impl<S> Service<actix_http::Request> for ActixService<S>
+where
+    S: Service<
+        actix_http::Request,
+        Response = actix_http::Response<IntoActixBody>,
+        Error = Infallible,
+    >,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ActixFuture<S::Future>;
+
+    fn call(&self, mut request: actix_http::Request) -> Self::Future {
+        let pending = if is_websocket_upgrade(&request) {
+            let (pending, on_upgrade) = upgrade::pending(request.take_payload());
+            request.extensions_mut().insert(on_upgrade);
+            Some(pending)
+        } else {
+            None
+        };
+        let trailers_wanted = request.version() == actix_http::Version::HTTP_2
+            || request
+                .headers()
+                .get_all(actix_http::header::TE)
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|value| value.trim().eq_ignore_ascii_case("trailers"));
+        ActixFuture {
+            fut: self.inner.call(request),
+            pending,
+            trailers_wanted,
+        }
+    }
+}
+
+pin_project! {
+    pub(crate) struct ActixFuture<F> {
+        #[pin]
+        fut: F,
+        pending: Option<Pending>,
+        trailers_wanted: bool,
+    }
+}
+
+impl<F> Future for ActixFuture<F>
+where
+    F: Future<Output = Result<actix_http::Response<IntoActixBody>, Infallible>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.fut.poll(cx))?;
+        if response.status() == actix_http::StatusCode::SWITCHING_PROTOCOLS {
+            if let Some(pending) = this.pending.take() {
+                response
+                    .head_mut()
+                    .set_connection_type(ConnectionType::Upgrade);
+                response = response.map_body(|_, _| IntoActixBody::new(pending.upgrade().boxed()));
+            }
+        }
+        this.pending.take();
+        let trailers_wanted = *this.trailers_wanted;
+        Poll::Ready(Ok(response.map_body(|_, mut body| {
+            body.trailers_wanted = trailers_wanted;
+            body
+        })))
     }
 }
 
+fn is_websocket_upgrade(request: &actix_http::Request) -> bool {
+    request.version() == actix_http::Version::HTTP_11
+        && request.upgrade()
+        && request
+            .headers()
+            .get(actix_http::header::UPGRADE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
 pub(crate) fn into_actix_service<S>(
     service: S,
-) -> impl Service<
-    actix_http::Request,
-    Response = actix_http::Response<IntoActixBody>,
-    Error = Infallible,
-    Future = impl Future<Output = Result<actix_http::Response<IntoActixBody>, Infallible>>,
+) -> ActixService<
+    impl Service<
+        actix_http::Request,
+        Response = actix_http::Response<IntoActixBody>,
+        Error = Infallible,
+        Future = impl Future<Output = Result<actix_http::Response<IntoActixBody>, Infallible>>,
+    >,
 >
 where
     S: Service<Request, Error = Infallible>,
     S::Response: IntoResponse,
 {
-    service
+    let inner = service
         .map_request(|request: actix_http::Request| {
+            let on_upgrade = request.extensions_mut().remove::<OnUpgrade>();
+            let conn = request.conn_data::<Connection>().map(|conn| {
+                conn.request_count.set(conn.request_count.get() + 1);
+                (
+                    conn.id,
+                    conn.local_addr,
+                    conn.request_count.get(),
+                    conn.proxy.clone(),
+                )
+            });
             let (head, body) = request.into_parts();
 
-            let mut request = Request::builder()
-                .method(&head.method)
-                .uri(&head.uri)
-                .version(head.version);
+            let mut request = request::Head::default();
+            request.method =
+                Method::from_bytes(head.method.as_str().as_bytes()).unwrap_or_default();
+            request.uri = Uri::try_from(head.uri.to_string()).unwrap_or_default();
+            request.version = into_tiny_version(head.version);
+
+            request.headers.reserve(head.headers.len());
+            for (k, v) in head.headers.iter() {
+                if let (Ok(k), Ok(v)) = (
+                    HeaderName::from_bytes(k.as_str().as_bytes()),
+                    HeaderValue::from_bytes(v.as_bytes()),
+                ) {
+                    request.headers.append(k, v);
+                }
+            }
 
             if let Some(peer_addr) = head.peer_addr {
-                request = request.extension(PeerAddr(peer_addr));
+                request.extensions.insert_cloneable(PeerAddr(peer_addr));
+
+                if let Some((id, local_addr, request_count, proxy)) = conn {
+                    if let Some(proxy) = proxy {
+                        request.extensions.insert_cloneable(proxy);
+                    }
+                    request.extensions.insert_cloneable(ConnectionInfo::new(
+                        id,
+                        peer_addr,
+                        local_addr,
+                        Scheme::HTTP,
+                        request.version,
+                        request_count,
+                    ));
+                }
             }
 
-            for (k, v) in head.headers.iter() {
-                request = request.header(k, v);
+            if let Some(on_upgrade) = on_upgrade {
+                request.extensions.insert(on_upgrade);
             }
 
-            request.body(IntoTinyBody { body }.boxed()).unwrap()
+            let body = match body {
+                actix_http::Payload::None => BoxBody::default(),
+                body => {
+                    PayloadBody::new(body, request.headers.contains_key(header::TRAILER)).boxed()
+                }
+            };
+            Request::from_head(request, body)
         })
         .map_response(|response: S::Response| {
             let (head, body) = response.into_response().into_head();
 
-            let mut response = actix_http::Response::build(head.status);
+            let status = actix_http::StatusCode::from_u16(head.status.as_u16())
+                .unwrap_or(actix_http::StatusCode::INTERNAL_SERVER_ERROR);
+            let mut response = actix_http::Response::build(status);
 
             for (k, v) in head.headers.iter() {
-                response.append_header((k, v));
+                if let Ok(v) = actix_http::header::HeaderValue::from_bytes(v.as_bytes()) {
+                    response.append_header((k.as_str(), v));
+                }
             }
 
-            response.message_body(IntoActixBody { body }).unwrap()
-        })
+            response.message_body(IntoActixBody::new(body)).unwrap()
+        });
+
+    ActixService { inner }
+}
+
+fn into_tiny_version(version: actix_http::Version) -> Version {
+    match version {
+        actix_http::Version::HTTP_09 => Version::HTTP_09,
+        actix_http::Version::HTTP_10 => Version::HTTP_10,
+        actix_http::Version::HTTP_2 => Version::HTTP_2,
+        actix_http::Version::HTTP_3 => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    }
 }
@@ -1,94 +1,243 @@
 use std::convert::Infallible;
-use std::net::SocketAddr;
+use std::future::Future;
+use std::time::Duration;
 
+use tokio::io::AsyncReadExt;
 use tokio::net::TcpStream;
 
-use actix_http::HttpService;
-use actix_service::IntoService;
+use actix_http::{HttpService, Protocol};
+use actix_service::{fn_service, IntoService, ServiceFactoryExt};
 
+use mtiny_core::body::Bytes;
 use mtiny_core::response::IntoResponse;
 use mtiny_core::service::Service;
 use mtiny_core::BoxError;
 use mtiny_core::Request;
 
-mod compat;
+use crate::limit::HeaderLimit;
+use crate::options::ServerOptions;
+use crate::proxy::{self, ProxyHeader};
 
-pub struct Server<F> {
-    factory: F,
-    options: Result<ServerOptions, BoxError>,
-}
+mod compat;
+mod io;
+mod payload;
+mod upgrade;
 
-pub struct ServerOptions {
-    addr: Vec<SocketAddr>,
-    workers: Option<usize>,
-}
+const MAX_HEADERS: usize = 96;
+const MAX_HEADER_SIZE: usize = 131_072;
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+// actix's own default for reading the request head
+const CLIENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
-impl<F, S> Server<F>
+pub(crate) async fn run<F, S, G>(
+    factory: F,
+    options: ServerOptions,
+    signal: G,
+) -> Result<(), BoxError>
 where
     F: Fn() -> S + Clone + Send + 'static,
     S: Service<Request, Error = Infallible> + 'static,
     S::Response: IntoResponse,
     S::Future: 'static,
+    G: Future<Output = ()>,
 {
-    pub fn new(factory: F) -> Self {
-        Self {
-            factory,
-            options: Ok(ServerOptions {
-                addr: vec![],
-                workers: None,
-            }),
+    check_options(&options)?;
+
+    let max_headers = options.max_headers;
+    let max_header_size = options.max_header_size;
+    let factory = move || {
+        let service = HeaderLimit::new(factory(), max_headers, max_header_size);
+        let service = compat::into_actix_service(service);
+        let service = move |request: actix_http::Request| service.call(request);
+
+        async move { Ok::<_, Infallible>(service.into_service()) }
+    };
+
+    let keep_alive = options.keep_alive;
+    let client_request_timeout = options.client_request_timeout;
+    let request_timeout = options.request_timeout(CLIENT_REQUEST_TIMEOUT);
+    let client_disconnect_timeout = options.disconnect_timeout();
+    let tcp_nodelay = options.tcp_nodelay;
+    let proxy_protocol = options.proxy_protocol;
+    let factory = move || {
+        let mut builder = HttpService::<io::Stream, _, _, _, _>::build();
+        if let Some(keep_alive) = keep_alive {
+            builder = builder.keep_alive(keep_alive);
         }
-    }
+        if let Some(timeout) = client_request_timeout {
+            builder = builder.client_request_timeout(timeout);
+        }
+        if let Some(timeout) = client_disconnect_timeout {
+            builder = builder.client_disconnect_timeout(timeout);
+        }
+        let service = builder
+            .on_connect_ext(compat::on_connect)
+            .finish(factory.clone());
 
-    pub fn workers(mut self, num: usize) -> Self {
-        self.options = self.options.and_then(|mut sp| {
-            sp.workers = Some(num);
-            Ok(sp)
-        });
-        self
-    }
+        fn_service(move |mut io: TcpStream| async move {
+            if let Some(nodelay) = tcp_nodelay {
+                io.set_nodelay(nodelay)?;
+            }
+
+            // the proxy header and the preface are part of the request head,
+            // so an idle connection can not hold on to a worker slot
+            let head = async {
+                let proxy = if proxy_protocol {
+                    Some(proxy::read_header(&mut io).await?)
+                } else {
+                    None
+                };
+                let (protocol, preface) = read_preface(&mut io).await?;
+                Ok::<_, std::io::Error>((proxy, protocol, preface))
+            };
+            let (proxy, protocol, preface) = match request_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, head)
+                    .await
+                    .unwrap_or_else(|err| Err(err.into()))?,
+                None => head.await?,
+            };
 
-    pub fn bind<T>(mut self, addr: T) -> Self
-    where
-        T: Into<SocketAddr>,
-    {
-        self.options = self.options.and_then(|mut sp| {
-            sp.addr.push(addr.into());
-            Ok(sp)
-        });
-        self
+            let peer_addr = proxy
+                .as_ref()
+                .and_then(ProxyHeader::source)
+                .or_else(|| io.peer_addr().ok());
+            let io = io::Stream { io, proxy, preface };
+            Ok((io, protocol, peer_addr))
+        })
+        .and_then(service)
+    };
+
+    let mut server = actix_server::Server::build().disable_signals();
+    if let Some(workers) = options.workers {
+        server = server.workers(workers);
+    }
+    if let Some(backlog) = options.backlog {
+        server = server.backlog(backlog);
+    }
+    if let Some(max_connections) = options.max_connections {
+        server = server.max_concurrent_connections(max_connections);
+    }
+    if let Some(timeout) = options.shutdown_timeout {
+        // actix only takes whole seconds, round up so a short timeout still waits
+        let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        server = server.shutdown_timeout(secs);
+    }
+    if !options.addr.is_empty() {
+        server = server.bind("tiny", &options.addr[..], factory.clone())?;
+    }
+    for listener in options.listeners {
+        server = server.listen("tiny", listener, factory.clone())?;
     }
 
-    pub async fn run(self) -> Result<(), BoxError> {
-        let options = self.options?;
-        let factory = self.factory;
+    let server = server.run();
+    let handle = server.handle();
+    tokio::pin!(server);
 
-        let factory = move || {
-            let service = compat::into_actix_service(factory());
-            let service = move |request: actix_http::Request| service.call(request);
+    tokio::select! {
+        res = &mut server => return res.map_err(From::from),
+        _ = signal => {}
+    }
 
-            async move { Ok::<_, Infallible>(service.into_service()) }
-        };
+    drop(handle.stop(true));
+    server.await.map_err(From::from)
+}
 
-        let mut server = actix_server::Server::build();
-        if let Some(workers) = options.workers {
-            server = server.workers(workers);
+// reads no further than the preface, so whatever was read can be replayed
+// in front of the stream
+async fn read_preface(io: &mut TcpStream) -> std::io::Result<(Protocol, Bytes)> {
+    let mut buf = Vec::with_capacity(H2_PREFACE.len());
+    while buf.len() < H2_PREFACE.len() {
+        let mut chunk = [0; H2_PREFACE.len()];
+        let n = io.read(&mut chunk[..H2_PREFACE.len() - buf.len()]).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if !H2_PREFACE.starts_with(&buf) {
+            break;
         }
+    }
+    let protocol = if buf == H2_PREFACE {
+        Protocol::Http2
+    } else {
+        Protocol::Http1
+    };
+    Ok((protocol, buf.into()))
+}
 
-        server
-            .bind("tiny", &options.addr[..], move || {
-                HttpService::<TcpStream, _, _, _, _>::build()
-                    .finish(factory.clone())
-                    .tcp()
-            })?
-            .run()
-            .await
-            .map_err(From::from)
+fn check_options(options: &ServerOptions) -> Result<(), BoxError> {
+    if matches!(options.max_headers, Some(num) if num > MAX_HEADERS) {
+        return Err(
+            format!("`max_headers` must not exceed {MAX_HEADERS} with the actix backend").into(),
+        );
+    }
+    if matches!(options.max_header_size, Some(size) if size > MAX_HEADER_SIZE) {
+        return Err(format!(
+            "`max_header_size` must not exceed {MAX_HEADER_SIZE} with the actix backend"
+        )
+        .into());
+    }
+    if options.tls.is_some() {
+        return Err("`tls` is not supported by the actix backend".into());
+    }
+    if options.http2_max_concurrent_streams.is_some() {
+        return Err("`http2_max_concurrent_streams` is not supported by the actix backend".into());
+    }
+    if options.http2_initial_stream_window_size.is_some() {
+        return Err(
+            "`http2_initial_stream_window_size` is not supported by the actix backend".into(),
+        );
+    }
+    if options.http2_initial_connection_window_size.is_some() {
+        return Err(
+            "`http2_initial_connection_window_size` is not supported by the actix backend".into(),
+        );
     }
+    Ok(())
 }
 
-impl<F> std::fmt::Debug for Server<F> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Server").finish()
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn preface(chunks: &[&'static [u8]]) -> (Protocol, Bytes) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let chunks = chunks.to_vec();
+        let write = async move {
+            for chunk in chunks {
+                client.write_all(chunk).await.unwrap();
+                client.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            client
+        };
+        let (res, _client) = tokio::join!(read_preface(&mut server), write);
+        res.unwrap()
+    }
+
+    #[tokio::test]
+    async fn detects_http2_preface_across_short_reads() {
+        let (protocol, buf) = preface(&[b"PRI * HT", b"TP/2.0\r\n\r\nSM\r\n\r\n"]).await;
+        assert_eq!(protocol, Protocol::Http2);
+        assert_eq!(&buf[..], H2_PREFACE);
+    }
+
+    #[tokio::test]
+    async fn stops_reading_on_mismatch() {
+        let (protocol, buf) = preface(&[b"GET / HTTP/1.1\r\nhost: a\r\n\r\n"]).await;
+        assert_eq!(protocol, Protocol::Http1);
+        assert_eq!(&buf[..], b"GET / HTTP/1.1\r\nhost: a\r");
+
+        let (protocol, buf) = preface(&[b"PRI", b" / HTTP/1.1\r\n"]).await;
+        assert_eq!(protocol, Protocol::Http1);
+        assert_eq!(&buf[..], b"PRI / HTTP/1.1\r\n");
     }
 }
@@ -0,0 +1,69 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_http::Payload;
+use futures_core::Stream;
+use mtiny_core::body::{Body, Bytes};
+use mtiny_core::http::HeaderMap;
+use mtiny_core::BoxError;
+use send_wrapper::SendWrapper;
+
+// actix payloads are tied to the worker thread that accepted the request, the
+// body fails when polled anywhere else instead of touching them
+pub(crate) struct PayloadBody {
+    payload: SendWrapper<Payload>,
+    trailers_declared: bool,
+}
+
+impl PayloadBody {
+    pub(crate) fn new(payload: Payload, trailers_declared: bool) -> Self {
+        Self {
+            payload: SendWrapper::new(payload),
+            trailers_declared,
+        }
+    }
+}
+
+impl Body for PayloadBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        if !self.payload.valid() {
+            return Poll::Ready(Some(Err(
+                "actix request body polled outside of its worker thread".into(),
+            )));
+        }
+        Pin::new(&mut *self.payload)
+            .poll_next(cx)
+            .map(|item| item.map(|res| res.map_err(Into::into)))
+    }
+
+    // actix-http parses request trailers and throws them away; a request that
+    // announced some gets an error instead of quietly looking trailer-less
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        if self.trailers_declared {
+            Poll::Ready(Err(
+                "request trailers are not supported by the actix backend".into(),
+            ))
+        } else {
+            Poll::Ready(Ok(None))
+        }
+    }
+}
+
+impl Drop for PayloadBody {
+    fn drop(&mut self) {
+        // dropping the payload on another thread would panic, leaking it is
+        // the only safe option left
+        if !self.payload.valid() {
+            let payload = std::mem::replace(&mut self.payload, SendWrapper::new(Payload::None));
+            std::mem::forget(payload);
+        }
+    }
+}
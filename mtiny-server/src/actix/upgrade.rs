@@ -0,0 +1,229 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use actix_http::Payload;
+use mtiny_core::body::{Body, Bytes};
+use mtiny_core::http::upgrade::{Io, OnUpgrade, Upgraded};
+use mtiny_core::BoxError;
+
+use super::payload::PayloadBody;
+
+const MAX_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    #[default]
+    Pending,
+    Upgraded,
+    Failed,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: State,
+    buf: Vec<u8>,
+    closed: bool,
+    dropped: bool,
+    reader: Option<Waker>,
+    writer: Option<Waker>,
+    payload: Option<Waker>,
+}
+
+impl Shared {
+    fn wake_reader(&mut self) {
+        if let Some(waker) = self.reader.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_writer(&mut self) {
+        if let Some(waker) = self.writer.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_payload(&mut self) {
+        if let Some(waker) = self.payload.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct SharedRef(Arc<Mutex<Shared>>);
+
+impl SharedRef {
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub(crate) struct Pending {
+    shared: SharedRef,
+}
+
+pub(crate) fn pending(payload: Payload) -> (Pending, OnUpgrade) {
+    let shared = SharedRef::default();
+    let io = UpgradedIo {
+        payload: PayloadBody::new(payload, false),
+        shared: shared.clone(),
+    };
+    (
+        Pending { shared },
+        OnUpgrade::new(WaitUpgrade { io: Some(io) }),
+    )
+}
+
+impl Pending {
+    pub(crate) fn upgrade(self) -> UpgradeBody {
+        let mut shared = self.shared.lock();
+        shared.state = State::Upgraded;
+        shared.wake_writer();
+        drop(shared);
+
+        UpgradeBody {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+        if shared.state == State::Pending {
+            shared.state = State::Failed;
+            shared.wake_writer();
+        }
+    }
+}
+
+struct WaitUpgrade {
+    io: Option<UpgradedIo>,
+}
+
+impl Future for WaitUpgrade {
+    type Output = io::Result<Upgraded>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let io = self
+            .io
+            .as_ref()
+            .expect("`OnUpgrade` polled after completion");
+        let state = {
+            let mut shared = io.shared.lock();
+            if shared.state == State::Pending {
+                shared.writer = Some(cx.waker().clone());
+            }
+            shared.state
+        };
+        match state {
+            State::Pending => Poll::Pending,
+            State::Upgraded => Poll::Ready(Ok(Upgraded::new(self.io.take().unwrap()))),
+            State::Failed => Poll::Ready(Err(io::Error::other("connection was not upgraded"))),
+        }
+    }
+}
+
+struct UpgradedIo {
+    payload: PayloadBody,
+    shared: SharedRef,
+}
+
+impl Io for UpgradedIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Bytes>>> {
+        let poll = Pin::new(&mut self.payload).poll_next(cx);
+        if poll.is_pending() {
+            self.shared.lock().payload = Some(cx.waker().clone());
+        }
+        poll.map(|item| item.map(|res| res.map_err(io::Error::other)))
+    }
+
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut shared = self.shared.lock();
+        if shared.dropped || shared.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let available = MAX_BUFFER_SIZE.saturating_sub(shared.buf.len());
+        if available == 0 {
+            shared.writer = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let len = buf.len().min(available);
+        shared.buf.extend_from_slice(&buf[..len]);
+        shared.wake_reader();
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut shared = self.shared.lock();
+        if shared.dropped {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if shared.buf.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        shared.writer = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut shared = self.shared.lock();
+        shared.closed = true;
+        shared.wake_reader();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for UpgradedIo {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+        shared.closed = true;
+        shared.wake_reader();
+    }
+}
+
+pub(crate) struct UpgradeBody {
+    shared: SharedRef,
+}
+
+impl Body for UpgradeBody {
+    type Error = BoxError;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let mut shared = self.shared.lock();
+        if !shared.buf.is_empty() {
+            let data = std::mem::take(&mut shared.buf);
+            shared.wake_writer();
+            return Poll::Ready(Some(Ok(Bytes::from(data))));
+        }
+        if shared.closed {
+            return Poll::Ready(None);
+        }
+        shared.reader = Some(cx.waker().clone());
+        // the dispatcher feeds eof/errors into the payload without waking its
+        // reader, but always polls the response body afterwards
+        shared.wake_payload();
+        Poll::Pending
+    }
+}
+
+impl Drop for UpgradeBody {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+        shared.dropped = true;
+        shared.wake_writer();
+    }
+}
@@ -0,0 +1,528 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::options::{non_zero, window_size, Backend, ServerOptions, DEFAULT_WINDOW_SIZE};
+
+const ENV_PREFIX: &str = "MTINY_";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: Vec<SocketAddr>,
+    pub workers: Option<usize>,
+    pub backend: Option<String>,
+    pub tcp_nodelay: Option<bool>,
+    pub proxy_protocol: bool,
+    pub tls: Option<TlsConfig>,
+    pub timeouts: TimeoutConfig,
+    pub limits: LimitConfig,
+    pub http2: Http2Config,
+    pub shutdown: ShutdownConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    #[serde(deserialize_with = "duration")]
+    pub keep_alive: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub client_request: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub client_disconnect: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitConfig {
+    pub max_headers: Option<usize>,
+    pub max_header_size: Option<usize>,
+    pub max_connections: Option<usize>,
+    pub backlog: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Http2Config {
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    #[serde(deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(toml::de::Error),
+    Invalid { key: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => {
+                write!(f, "failed to read config `{}`: {}", path.display(), err)
+            }
+            ConfigError::Parse(err) => write!(f, "failed to parse config: {}", err),
+            ConfigError::Invalid { key, reason } => {
+                write!(f, "invalid config `{}`: {}", key, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read(_, err) => Some(err),
+            ConfigError::Parse(err) => Some(err),
+            ConfigError::Invalid { .. } => None,
+        }
+    }
+}
+
+fn invalid(key: &str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: reason.into(),
+    }
+}
+
+impl ServerConfig {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        toml::from_str(s).map_err(ConfigError::Parse)
+    }
+
+    pub fn from_file<P>(path: P) -> Result<Self, ConfigError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let s =
+            std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.to_owned(), err))?;
+        Self::from_toml(&s)?.with_env()
+    }
+
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::default().with_env()
+    }
+
+    pub fn with_env(self) -> Result<Self, ConfigError> {
+        self.with_vars(std::env::vars())
+    }
+
+    pub fn with_vars<I, K, V>(mut self, vars: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (name, value) in vars {
+            let Some(key) = name.as_ref().strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            if !self.set(&key.to_ascii_lowercase(), value.as_ref())? {
+                return Err(invalid(name.as_ref(), "unknown configuration key"));
+            }
+        }
+        Ok(self)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<bool, ConfigError> {
+        match name {
+            "bind" => {
+                self.bind = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|addr| !addr.is_empty())
+                    .map(|addr| parse("bind", addr))
+                    .collect::<Result<_, _>>()?;
+            }
+            "workers" => self.workers = Some(parse("workers", value)?),
+            "backend" => self.backend = Some(value.to_owned()),
+            "tcp_nodelay" => self.tcp_nodelay = Some(parse("tcp_nodelay", value)?),
+            "proxy_protocol" => self.proxy_protocol = parse("proxy_protocol", value)?,
+            "tls_cert" => self.tls.get_or_insert_with(Default::default).cert = value.into(),
+            "tls_key" => self.tls.get_or_insert_with(Default::default).key = value.into(),
+            "timeouts_keep_alive" => {
+                self.timeouts.keep_alive = Some(parse_duration("timeouts.keep_alive", value)?);
+            }
+            "timeouts_client_request" => {
+                self.timeouts.client_request =
+                    Some(parse_duration("timeouts.client_request", value)?);
+            }
+            "timeouts_client_disconnect" => {
+                self.timeouts.client_disconnect =
+                    Some(parse_duration("timeouts.client_disconnect", value)?);
+            }
+            "limits_max_headers" => {
+                self.limits.max_headers = Some(parse("limits.max_headers", value)?);
+            }
+            "limits_max_header_size" => {
+                self.limits.max_header_size = Some(parse("limits.max_header_size", value)?);
+            }
+            "limits_max_connections" => {
+                self.limits.max_connections = Some(parse("limits.max_connections", value)?);
+            }
+            "limits_backlog" => self.limits.backlog = Some(parse("limits.backlog", value)?),
+            "http2_max_concurrent_streams" => {
+                self.http2.max_concurrent_streams =
+                    Some(parse("http2.max_concurrent_streams", value)?);
+            }
+            "http2_initial_stream_window_size" => {
+                self.http2.initial_stream_window_size =
+                    Some(parse("http2.initial_stream_window_size", value)?);
+            }
+            "http2_initial_connection_window_size" => {
+                self.http2.initial_connection_window_size =
+                    Some(parse("http2.initial_connection_window_size", value)?);
+            }
+            "shutdown_timeout" => {
+                self.shutdown.timeout = Some(parse_duration("shutdown.timeout", value)?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.bind.is_empty() {
+            return Err(invalid("bind", "at least one address is required"));
+        }
+        if let Some(backend) = &self.backend {
+            backend_from_str(backend)?;
+        }
+        check("workers", self.workers, non_zero)?;
+        check("limits.max_headers", self.limits.max_headers, non_zero)?;
+        check(
+            "limits.max_header_size",
+            self.limits.max_header_size,
+            non_zero,
+        )?;
+        check(
+            "limits.max_connections",
+            self.limits.max_connections,
+            non_zero,
+        )?;
+        check("limits.backlog", self.limits.backlog, non_zero)?;
+        check(
+            "http2.max_concurrent_streams",
+            self.http2.max_concurrent_streams,
+            non_zero,
+        )?;
+        check(
+            "http2.initial_stream_window_size",
+            self.http2.initial_stream_window_size,
+            |size| window_size(size, 0),
+        )?;
+        check(
+            "http2.initial_connection_window_size",
+            self.http2.initial_connection_window_size,
+            |size| window_size(size, DEFAULT_WINDOW_SIZE),
+        )?;
+
+        if let Some(tls) = &self.tls {
+            if cfg!(not(feature = "tls")) {
+                return Err(invalid("tls", "requires the `tls` feature"));
+            }
+            file_exists("tls.cert", &tls.cert)?;
+            file_exists("tls.key", &tls.key)?;
+        }
+        Ok(())
+    }
+
+    pub fn into_options(self) -> Result<ServerOptions, ConfigError> {
+        self.validate()?;
+
+        let backend = match &self.backend {
+            Some(backend) => backend_from_str(backend)?,
+            None => Backend::default(),
+        };
+
+        Ok(ServerOptions {
+            addr: self.bind,
+            workers: self.workers,
+            backend,
+            keep_alive: self.timeouts.keep_alive,
+            client_request_timeout: self.timeouts.client_request,
+            client_disconnect_timeout: self.timeouts.client_disconnect,
+            max_headers: self.limits.max_headers,
+            max_header_size: self.limits.max_header_size,
+            backlog: self.limits.backlog,
+            max_connections: self.limits.max_connections,
+            tcp_nodelay: self.tcp_nodelay,
+            http2_max_concurrent_streams: self.http2.max_concurrent_streams,
+            http2_initial_stream_window_size: self.http2.initial_stream_window_size,
+            http2_initial_connection_window_size: self.http2.initial_connection_window_size,
+            shutdown_timeout: self.shutdown.timeout,
+            tls: self.tls.map(|tls| (tls.cert, tls.key)),
+            proxy_protocol: self.proxy_protocol,
+            ..Default::default()
+        })
+    }
+}
+
+fn backend_from_str(s: &str) -> Result<Backend, ConfigError> {
+    match s {
+        #[cfg(feature = "actix")]
+        "actix" => Ok(Backend::Actix),
+        #[cfg(feature = "hyper")]
+        "hyper" => Ok(Backend::Hyper),
+        _ if matches!(s, "actix" | "hyper") => {
+            Err(invalid("backend", format!("requires the `{}` feature", s)))
+        }
+        _ => Err(invalid(
+            "backend",
+            format!("unknown backend `{}`, expected `actix` or `hyper`", s),
+        )),
+    }
+}
+
+fn check<T, F>(key: &str, value: Option<T>, check: F) -> Result<(), ConfigError>
+where
+    F: FnOnce(T) -> Result<T, String>,
+{
+    match value {
+        Some(value) => check(value)
+            .map(drop)
+            .map_err(|reason| invalid(key, reason)),
+        None => Ok(()),
+    }
+}
+
+fn file_exists(key: &str, path: &Path) -> Result<(), ConfigError> {
+    if path.as_os_str().is_empty() {
+        return Err(invalid(key, "is required"));
+    }
+    if !path.is_file() {
+        return Err(invalid(key, format!("`{}` is not a file", path.display())));
+    }
+    Ok(())
+}
+
+fn parse<T>(key: &str, value: &str) -> Result<T, ConfigError>
+where
+    T: std::str::FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|err| invalid(key, format!("`{}`: {}", value, err)))
+}
+
+fn parse_duration(key: &str, value: &str) -> Result<Duration, ConfigError> {
+    duration_from_str(value).map_err(|reason| invalid(key, reason))
+}
+
+fn duration_from_str(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num = num
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration `{}`", s))?;
+
+    let secs = match unit.trim() {
+        "ms" => return Ok(Duration::from_millis(num)),
+        "" | "s" => Some(num),
+        "m" => num.checked_mul(60),
+        "h" => num.checked_mul(60 * 60),
+        unit => {
+            return Err(format!(
+                "unknown duration unit `{}` in `{}`, expected `ms`, `s`, `m` or `h`",
+                unit, s
+            ))
+        }
+    };
+    secs.map(Duration::from_secs)
+        .ok_or_else(|| format!("duration `{}` is too large", s))
+}
+
+fn duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Secs(u64),
+        Str(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Secs(secs) => Ok(Some(Duration::from_secs(secs))),
+        Raw::Str(s) => duration_from_str(&s)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&'static str, &'static str)]) -> Result<ServerConfig, ConfigError> {
+        ServerConfig::default().with_vars(vars.iter().copied())
+    }
+
+    #[test]
+    fn toml() {
+        let config = ServerConfig::from_toml(
+            r#"
+            bind = ["127.0.0.1:8080", "[::1]:8080"]
+            workers = 4
+            tcp_nodelay = true
+
+            [timeouts]
+            keep_alive = 75
+            client_request = "500ms"
+            client_disconnect = "2m"
+
+            [limits]
+            max_headers = 64
+
+            [http2]
+            initial_stream_window_size = 1048576
+            initial_connection_window_size = 65535
+
+            [shutdown]
+            timeout = "1h"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.bind.len(), 2);
+        assert_eq!(config.workers, Some(4));
+        assert_eq!(config.tcp_nodelay, Some(true));
+        assert_eq!(config.timeouts.keep_alive, Some(Duration::from_secs(75)));
+        assert_eq!(
+            config.timeouts.client_request,
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            config.timeouts.client_disconnect,
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(config.limits.max_headers, Some(64));
+        assert_eq!(config.shutdown.timeout, Some(Duration::from_secs(3600)));
+
+        let options = config.into_options().unwrap();
+        assert_eq!(options.workers(), Some(4));
+        assert_eq!(options.http2_initial_stream_window_size(), Some(1 << 20));
+    }
+
+    #[test]
+    fn toml_rejects_unknown_fields() {
+        assert!(ServerConfig::from_toml("port = 80").is_err());
+        assert!(ServerConfig::from_toml("[timeouts]\nidle = 5").is_err());
+    }
+
+    #[test]
+    fn toml_rejects_invalid_durations() {
+        assert!(ServerConfig::from_toml("[shutdown]\ntimeout = \"5d\"").is_err());
+        assert!(ServerConfig::from_toml("[shutdown]\ntimeout = \"soon\"").is_err());
+    }
+
+    #[test]
+    fn env() {
+        let config = vars(&[
+            ("PATH", "/usr/bin"),
+            ("MTINY_BIND", "127.0.0.1:80, 127.0.0.1:81"),
+            ("MTINY_WORKERS", "2"),
+            ("mtiny_backend", "actix"),
+            ("MTINY_TIMEOUTS_KEEP_ALIVE", "10s"),
+            ("MTINY_LIMITS_BACKLOG", "128"),
+            ("MTINY_TLS_CERT", "cert.pem"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.bind.len(), 2);
+        assert_eq!(config.workers, Some(2));
+        assert_eq!(config.backend, None);
+        assert_eq!(config.timeouts.keep_alive, Some(Duration::from_secs(10)));
+        assert_eq!(config.limits.backlog, Some(128));
+        assert_eq!(config.tls.unwrap().cert, PathBuf::from("cert.pem"));
+    }
+
+    #[test]
+    fn env_overrides_toml() {
+        let config = ServerConfig::from_toml("workers = 8")
+            .unwrap()
+            .with_vars([("MTINY_WORKERS", "3")])
+            .unwrap();
+        assert_eq!(config.workers, Some(3));
+    }
+
+    #[test]
+    fn env_rejects_unknown_keys() {
+        let err = vars(&[("MTINY_WROKERS", "2")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config `MTINY_WROKERS`: unknown configuration key"
+        );
+    }
+
+    #[test]
+    fn env_rejects_invalid_values() {
+        assert!(vars(&[("MTINY_WORKERS", "many")]).is_err());
+        assert!(vars(&[("MTINY_BIND", "localhost")]).is_err());
+        assert!(vars(&[("MTINY_SHUTDOWN_TIMEOUT", "99999999999999999h")]).is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration_from_str("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(duration_from_str("3"), Ok(Duration::from_secs(3)));
+        assert_eq!(duration_from_str(" 3 s "), Ok(Duration::from_secs(3)));
+        assert_eq!(duration_from_str("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(duration_from_str("2h"), Ok(Duration::from_secs(7200)));
+        assert!(duration_from_str("-1s").is_err());
+        assert!(duration_from_str("1w").is_err());
+        assert!(duration_from_str(&format!("{}h", u64::MAX / 60)).is_err());
+        assert!(duration_from_str(&format!("{}m", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let config = ServerConfig::default();
+        assert!(config.validate().is_err());
+
+        let mut config = vars(&[("MTINY_BIND", "127.0.0.1:80")]).unwrap();
+        assert!(config.validate().is_ok());
+
+        config.workers = Some(0);
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config `workers`: must be greater than 0"
+        );
+
+        config.workers = None;
+        config.http2.initial_connection_window_size = Some(1);
+        assert!(config.validate().is_err());
+
+        config.http2.initial_connection_window_size = Some(DEFAULT_WINDOW_SIZE);
+        config.http2.initial_stream_window_size = Some(1 << 20);
+        assert!(config.validate().is_ok());
+
+        config.backend = Some("tokio".to_owned());
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use http_body::Frame;
+use hyper::body::Incoming;
+use mtiny_core::body::{Body, BodyExt, BoxBody, Bytes, FromHttpBody};
+use mtiny_core::http::{header, HeaderMap, HeaderName, Version};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{Service, ServiceExt};
+use mtiny_core::{PeerAddr, Request};
+
+use super::conn::{ConnGuard, ConnState};
+use super::upgrade;
+
+#[derive(Default)]
+enum TrailerFields {
+    #[default]
+    None,
+    Any,
+    Declared(Vec<HeaderName>),
+}
+
+impl TrailerFields {
+    fn new(version: Version, te_trailers: bool, headers: &HeaderMap) -> Self {
+        if version == Version::HTTP_2 {
+            return TrailerFields::Any;
+        }
+        let declared = headers
+            .get_all(header::TRAILER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+            .collect::<Vec<_>>();
+        if te_trailers && !declared.is_empty() {
+            TrailerFields::Declared(declared)
+        } else {
+            TrailerFields::None
+        }
+    }
+
+    // hyper leaves a chunked HTTP/1.1 body unterminated when none of the
+    // trailers it is handed are declared, so filter them here instead
+    fn filter(&self, mut trailers: HeaderMap) -> Option<HeaderMap> {
+        match self {
+            TrailerFields::None => return None,
+            TrailerFields::Any => {}
+            TrailerFields::Declared(declared) => {
+                trailers = trailers
+                    .into_iter()
+                    .filter_map(|(name, value)| Some((name?, value)))
+                    .filter(|(name, _)| declared.contains(name))
+                    .fold(HeaderMap::new(), |mut map, (name, value)| {
+                        map.append(name, value);
+                        map
+                    });
+            }
+        }
+        (!trailers.is_empty()).then_some(trailers)
+    }
+}
+
+pin_project! {
+    pub(crate) struct IntoHyperBody {
+        #[pin]
+        body: BoxBody,
+        data_done: bool,
+        trailers_done: bool,
+        trailers: TrailerFields,
+        guard: Option<ConnGuard>,
+    }
+}
+
+impl http_body::Body for IntoHyperBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        if *this.trailers_done {
+            this.guard.take();
+            return Poll::Ready(None);
+        }
+        if !*this.data_done {
+            match ready!(this.body.as_mut().poll_next(cx)) {
+                Some(Ok(data)) => return Poll::Ready(Some(Ok(Frame::data(data)))),
+                Some(Err(err)) => return Poll::Ready(Some(Err(std::io::Error::other(err)))),
+                None => *this.data_done = true,
+            }
+        }
+        let frame = match ready!(this.body.poll_trailers(cx)) {
+            Ok(trailers) => trailers
+                .and_then(|trailers| this.trailers.filter(trailers))
+                .map(|trailers| Ok(Frame::trailers(trailers))),
+            Err(err) => Some(Err(std::io::Error::other(err))),
+        };
+        *this.trailers_done = true;
+        if frame.is_none() {
+            this.guard.take();
+        }
+        Poll::Ready(frame)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        // declared trailers need a chunked HTTP/1.1 body
+        if matches!(self.trailers, TrailerFields::Declared(_)) {
+            let mut size_hint = http_body::SizeHint::new();
+            size_hint.set_lower(self.body.size_hint().lower());
+            return size_hint;
+        }
+        self.body.size_hint().into()
+    }
+}
+
+pub(crate) struct HyperService<S> {
+    inner: S,
+    state: Rc<ConnState>,
+}
+
+impl<S> hyper::service::Service<hyper::Request<Incoming>> for HyperService<S>
+where
+    S: Service<
+        hyper::Request<Incoming>,
+        Response = hyper::Response<IntoHyperBody>,
+        Error = Infallible,
+    >,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = HyperFuture<S::Future>;
+
+    fn call(&self, request: hyper::Request<Incoming>) -> Self::Future {
+        let guard = self.state.guard();
+        let version = request.version();
+        let te_trailers = request
+            .headers()
+            .get_all(header::TE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case("trailers"));
+        HyperFuture {
+            fut: self.inner.call(request),
+            guard: Some(guard),
+            version,
+            te_trailers,
+        }
+    }
+}
+
+pin_project! {
+    pub(crate) struct HyperFuture<F> {
+        #[pin]
+        fut: F,
+        guard: Option<ConnGuard>,
+        version: Version,
+        te_trailers: bool,
+    }
+}
+
+impl<F> Future for HyperFuture<F>
+where
+    F: Future<Output = Result<hyper::Response<IntoHyperBody>, Infallible>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.fut.poll(cx))?;
+        let trailers = TrailerFields::new(*this.version, *this.te_trailers, response.headers());
+        let body = response.body_mut();
+        body.guard = this.guard.take();
+        body.trailers = trailers;
+        Poll::Ready(Ok(response))
+    }
+}
+
+pub(crate) fn into_hyper_service<S>(
+    service: S,
+    state: Rc<ConnState>,
+) -> HyperService<
+    impl Service<
+        hyper::Request<Incoming>,
+        Response = hyper::Response<IntoHyperBody>,
+        Error = Infallible,
+        Future = impl Future<Output = Result<hyper::Response<IntoHyperBody>, Infallible>>,
+    >,
+>
+where
+    S: Service<Request, Error = Infallible>,
+    S::Response: IntoResponse,
+{
+    let conn = state.clone();
+    let inner = service
+        .map_request(move |mut request: hyper::Request<Incoming>| {
+            let on_upgrade = upgrade::on_upgrade(&mut request);
+            let mut request = Request::from(request.map(|body| FromHttpBody::new(body).boxed()));
+
+            let info = conn.info(*request.version());
+            let extensions = request.extensions_mut();
+            extensions.insert_cloneable(PeerAddr(info.peer_addr()));
+            if let Some(proxy) = conn.proxy() {
+                extensions.insert_cloneable(proxy.clone());
+            }
+            extensions.insert_cloneable(info);
+            if let Some(on_upgrade) = on_upgrade {
+                extensions.insert(on_upgrade);
+            }
+            request
+        })
+        .map_response(|response: S::Response| {
+            let (head, body) = response.into_response().into_head();
+
+            let mut response = hyper::Response::new(IntoHyperBody {
+                body,
+                data_done: false,
+                trailers_done: false,
+                trailers: TrailerFields::None,
+                guard: None,
+            });
+            *response.status_mut() = head.status;
+            *response.headers_mut() = head.headers;
+            response
+        });
+
+    HyperService { inner, state }
+}
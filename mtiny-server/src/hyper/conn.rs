@@ -0,0 +1,89 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use mtiny_core::http::Version;
+use mtiny_core::ConnectionInfo;
+
+use crate::proxy::ProxyHeader;
+
+#[derive(Debug)]
+pub(crate) struct ConnState {
+    info: ConnectionInfo,
+    proxy: Option<ProxyHeader>,
+    request_count: Cell<u64>,
+    inflight: Cell<usize>,
+    idle_since: Cell<Instant>,
+    notify: Notify,
+}
+
+impl ConnState {
+    pub(crate) fn new(info: ConnectionInfo, proxy: Option<ProxyHeader>) -> Rc<Self> {
+        Rc::new(Self {
+            info,
+            proxy,
+            request_count: Cell::new(0),
+            inflight: Cell::new(0),
+            idle_since: Cell::new(Instant::now()),
+            notify: Notify::new(),
+        })
+    }
+
+    pub(crate) fn info(&self, version: Version) -> ConnectionInfo {
+        let info = &self.info;
+        ConnectionInfo::new(
+            info.id(),
+            info.peer_addr(),
+            info.local_addr(),
+            info.scheme().clone(),
+            version,
+            self.request_count.get(),
+        )
+    }
+
+    pub(crate) fn proxy(&self) -> Option<&ProxyHeader> {
+        self.proxy.as_ref()
+    }
+
+    pub(crate) fn guard(self: &Rc<Self>) -> ConnGuard {
+        self.request_count.set(self.request_count.get() + 1);
+        self.inflight.set(self.inflight.get() + 1);
+        ConnGuard {
+            state: self.clone(),
+        }
+    }
+
+    pub(crate) async fn idle(&self, keep_alive: Duration) {
+        loop {
+            if self.inflight.get() == 0 {
+                let deadline = self.idle_since.get() + keep_alive;
+                if deadline <= Instant::now() {
+                    return;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {}
+                    _ = self.notify.notified() => {}
+                }
+            } else {
+                self.notify.notified().await;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ConnGuard {
+    state: Rc<ConnState>,
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        let state = &self.state;
+        state.inflight.set(state.inflight.get() - 1);
+        state.idle_since.set(Instant::now());
+        state.notify.notify_waiters();
+    }
+}
@@ -0,0 +1,331 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener as StdTcpListener};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio::task::LocalSet;
+
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+
+use mtiny_core::http::Version;
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::Service;
+use mtiny_core::BoxError;
+use mtiny_core::{ConnectionInfo, Request};
+
+use crate::connection;
+use crate::limit::HeaderLimit;
+use crate::options::ServerOptions;
+use crate::proxy::ProxyHeader;
+
+use self::conn::ConnState;
+use self::io::Acceptor;
+
+mod compat;
+mod conn;
+mod io;
+#[cfg(feature = "tls")]
+mod tls;
+mod upgrade;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_BACKLOG: u32 = 1024;
+// hyper's own default for reading the request head
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(500);
+
+pub(crate) async fn run<F, S, G>(
+    factory: F,
+    mut options: ServerOptions,
+    signal: G,
+) -> Result<(), BoxError>
+where
+    F: Fn() -> S + Clone + Send + 'static,
+    S: Service<Request, Error = Infallible> + 'static,
+    S::Response: IntoResponse,
+    S::Future: 'static,
+    G: Future<Output = ()>,
+{
+    let workers = options
+        .workers
+        .unwrap_or_else(|| thread::available_parallelism().map(Into::into).unwrap_or(1));
+
+    let mut listeners = Vec::with_capacity(options.addr.len() + options.listeners.len());
+    for addr in &options.addr {
+        listeners.push(bind(*addr, options.backlog.unwrap_or(DEFAULT_BACKLOG))?);
+    }
+    listeners.extend(std::mem::take(&mut options.listeners));
+    for listener in &listeners {
+        listener.set_nonblocking(true)?;
+    }
+
+    let acceptor = Acceptor::new(&options)?;
+    let options = Arc::new(options);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (done_tx, mut done_rx) = mpsc::unbounded_channel::<Result<(), String>>();
+
+    for idx in 0..workers {
+        let listeners = listeners
+            .iter()
+            .map(StdTcpListener::try_clone)
+            .collect::<Result<Vec<_>, _>>()?;
+        let factory = factory.clone();
+        let options = options.clone();
+        let acceptor = acceptor.clone();
+        let shutdown_rx = shutdown_rx.clone();
+        let done_tx = done_tx.clone();
+
+        thread::Builder::new()
+            .name(format!("mtiny-worker-{idx}"))
+            .spawn(move || {
+                let res = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(|e| e.to_string())
+                    .and_then(|rt| {
+                        LocalSet::new()
+                            .block_on(
+                                &rt,
+                                worker(factory, listeners, acceptor, options, shutdown_rx),
+                            )
+                            .map_err(|e| e.to_string())
+                    });
+                let _ = done_tx.send(res);
+            })?;
+    }
+    drop(done_tx);
+
+    let mut result = Ok(());
+    let mut remaining = workers;
+    tokio::select! {
+        _ = signal => {}
+        Some(res) = done_rx.recv() => {
+            remaining -= 1;
+            result = res;
+        }
+    }
+    let _ = shutdown_tx.send(true);
+
+    while remaining > 0 {
+        match done_rx.recv().await {
+            Some(res) => result = result.and(res),
+            None => break,
+        }
+        remaining -= 1;
+    }
+    result.map_err(Into::into)
+}
+
+fn bind(addr: SocketAddr, backlog: u32) -> std::io::Result<StdTcpListener> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(backlog)?.into_std()
+}
+
+async fn worker<F, S>(
+    factory: F,
+    listeners: Vec<StdTcpListener>,
+    acceptor: Acceptor,
+    options: Arc<ServerOptions>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), BoxError>
+where
+    F: Fn() -> S,
+    S: Service<Request, Error = Infallible> + 'static,
+    S::Response: IntoResponse,
+    S::Future: 'static,
+{
+    let service = Rc::new(HeaderLimit::new(
+        factory(),
+        options.max_headers,
+        options.max_header_size,
+    ));
+    let builder = Rc::new(builder(&options));
+    let permits = options
+        .max_connections
+        .map(|num| Arc::new(Semaphore::new(num)));
+    let (conns_tx, conns_rx) = watch::channel(());
+
+    let mut accepts = Vec::with_capacity(listeners.len());
+    for listener in listeners {
+        let listener = TcpListener::from_std(listener)?;
+        let service = service.clone();
+        let builder = builder.clone();
+        let acceptor = acceptor.clone();
+        let permits = permits.clone();
+        let options = options.clone();
+        let conns_rx = conns_rx.clone();
+        let mut shutdown = shutdown.clone();
+
+        accepts.push(tokio::task::spawn_local(async move {
+            loop {
+                let permit = match &permits {
+                    Some(permits) => tokio::select! {
+                        permit = permits.clone().acquire_owned() => match permit {
+                            Ok(permit) => Some(permit),
+                            Err(_) => break,
+                        },
+                        _ = shutdown.changed() => break,
+                    },
+                    None => None,
+                };
+
+                let (stream, peer_addr) = tokio::select! {
+                    res = listener.accept() => match res {
+                        Ok(conn) => conn,
+                        Err(err) if is_connection_error(&err) => continue,
+                        Err(err) => {
+                            // out of file descriptors and the like, retrying
+                            // straight away would just spin
+                            log::error!("failed to accept connection: {err}");
+                            tokio::select! {
+                                _ = tokio::time::sleep(ACCEPT_BACKOFF) => continue,
+                                _ = shutdown.changed() => break,
+                            }
+                        }
+                    },
+                    _ = shutdown.changed() => break,
+                };
+                if let Some(nodelay) = options.tcp_nodelay {
+                    let _ = stream.set_nodelay(nodelay);
+                }
+
+                let builder = builder.clone();
+                let service = service.clone();
+                let acceptor = acceptor.clone();
+                let options = options.clone();
+                let conns_rx = conns_rx.clone();
+                let mut shutdown = shutdown.clone();
+
+                tokio::task::spawn_local(async move {
+                    let Ok(local_addr) = stream.local_addr() else {
+                        return;
+                    };
+                    let accepted = match options.request_timeout(HEADER_READ_TIMEOUT) {
+                        Some(timeout) => tokio::time::timeout(timeout, acceptor.accept(stream))
+                            .await
+                            .unwrap_or_else(|err| Err(err.into())),
+                        None => acceptor.accept(stream).await,
+                    };
+                    let Ok((stream, proxy)) = accepted else {
+                        return;
+                    };
+
+                    let peer_addr = proxy
+                        .as_ref()
+                        .and_then(ProxyHeader::source)
+                        .unwrap_or(peer_addr);
+                    let info = ConnectionInfo::new(
+                        connection::next_id(),
+                        peer_addr,
+                        local_addr,
+                        acceptor.scheme(),
+                        Version::default(),
+                        0,
+                    );
+                    let state = ConnState::new(info, proxy);
+                    let service = compat::into_hyper_service(service, state.clone());
+
+                    let conn =
+                        builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                    tokio::pin!(conn);
+
+                    let idle = async {
+                        match options.keep_alive {
+                            Some(keep_alive) if !keep_alive.is_zero() => {
+                                state.idle(keep_alive).await
+                            }
+                            _ => std::future::pending().await,
+                        }
+                    };
+                    tokio::select! {
+                        _ = conn.as_mut() => return,
+                        _ = shutdown.wait_for(|stop| *stop) => {}
+                        _ = idle => {}
+                    }
+
+                    conn.as_mut().graceful_shutdown();
+                    match options.disconnect_timeout() {
+                        Some(timeout) => drop(tokio::time::timeout(timeout, conn).await),
+                        None => drop(conn.await),
+                    }
+                    drop((permit, conns_rx));
+                });
+            }
+        }));
+    }
+    drop(conns_rx);
+
+    while !*shutdown.borrow_and_update() {
+        if shutdown.changed().await.is_err() {
+            break;
+        }
+    }
+    for accept in accepts {
+        let _ = accept.await;
+    }
+    let timeout = options.shutdown_timeout.unwrap_or(SHUTDOWN_TIMEOUT);
+    let _ = tokio::time::timeout(timeout, conns_tx.closed()).await;
+    Ok(())
+}
+
+fn is_connection_error(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset
+    )
+}
+
+fn builder(options: &ServerOptions) -> auto::Builder<LocalExecutor> {
+    let mut builder = auto::Builder::new(LocalExecutor);
+
+    let mut http1 = builder.http1();
+    http1.timer(TokioTimer::new());
+    http1.header_read_timeout(options.request_timeout(HEADER_READ_TIMEOUT));
+    if let Some(num) = options.max_headers {
+        http1.max_headers(num);
+    }
+    if matches!(options.keep_alive, Some(keep_alive) if keep_alive.is_zero()) {
+        http1.keep_alive(false);
+    }
+
+    let mut http2 = builder.http2();
+    http2.timer(TokioTimer::new());
+    if let Some(num) = options.http2_max_concurrent_streams {
+        http2.max_concurrent_streams(num);
+    }
+    if let Some(size) = options.http2_initial_stream_window_size {
+        http2.initial_stream_window_size(size);
+    }
+    if let Some(size) = options.http2_initial_connection_window_size {
+        http2.initial_connection_window_size(size);
+    }
+    if let Some(size) = options.max_header_size {
+        http2.max_header_list_size(u32::try_from(size).unwrap_or(u32::MAX));
+    }
+
+    builder
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LocalExecutor;
+
+impl<Fut> hyper::rt::Executor<Fut> for LocalExecutor
+where
+    Fut: Future + 'static,
+{
+    fn execute(&self, fut: Fut) {
+        tokio::task::spawn_local(fut);
+    }
+}
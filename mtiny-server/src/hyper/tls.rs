@@ -0,0 +1,36 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+use mtiny_core::BoxError;
+
+pub(crate) fn acceptor(cert: &Path, key: &Path) -> Result<TlsAcceptor, BoxError> {
+    let certs = rustls_pemfile::certs(&mut reader(cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to read `{}`: {err}", cert.display()))?;
+    if certs.is_empty() {
+        return Err(format!("no certificate found in `{}`", cert.display()).into());
+    }
+    let key = rustls_pemfile::private_key(&mut reader(key)?)
+        .map_err(|err| format!("failed to read `{}`: {err}", key.display()))?
+        .ok_or_else(|| format!("no private key found in `{}`", key.display()))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn reader(path: &Path) -> Result<BufReader<File>, BoxError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| format!("failed to open `{}`: {err}", path.display()).into())
+}
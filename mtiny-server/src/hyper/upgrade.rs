@@ -0,0 +1,64 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use mtiny_core::body::Bytes;
+use mtiny_core::http::upgrade::{Io, OnUpgrade, Upgraded};
+use mtiny_core::http::{header, Version};
+
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+pub(crate) fn on_upgrade(request: &mut hyper::Request<Incoming>) -> Option<OnUpgrade> {
+    if request.version() != Version::HTTP_11 || !request.headers().contains_key(header::UPGRADE) {
+        return None;
+    }
+
+    let on_upgrade = hyper::upgrade::on(request);
+    Some(OnUpgrade::new(async move {
+        let upgraded = on_upgrade.await.map_err(io::Error::other)?;
+        Ok(Upgraded::new(UpgradedIo {
+            io: TokioIo::new(upgraded),
+        }))
+    }))
+}
+
+struct UpgradedIo {
+    io: TokioIo<hyper::upgrade::Upgraded>,
+}
+
+impl Io for UpgradedIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<Bytes>>> {
+        let mut buf = [0; READ_BUFFER_SIZE];
+        let mut buf = ReadBuf::new(&mut buf);
+        if let Err(err) = ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf)) {
+            return Poll::Ready(Some(Err(err)));
+        }
+        match buf.filled() {
+            [] => Poll::Ready(None),
+            data => Poll::Ready(Some(Ok(Bytes::copy_from_slice(data)))),
+        }
+    }
+
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
@@ -1,24 +1,30 @@
-
-use std::{net::SocketAddr, fmt};
-
-#[cfg(feature="actix")]
-mod actix;
-
 #[cfg(feature = "actix")]
-pub use actix::Server;
-
-#[derive(Clone, Copy,PartialEq, PartialOrd,Eq, Ord,Hash)]
-pub struct PeerAddr(pub SocketAddr);
+mod actix;
+#[cfg(feature = "hyper")]
+mod hyper;
 
+#[cfg(all(feature = "config", any(feature = "actix", feature = "hyper")))]
+mod config;
+#[cfg(any(feature = "actix", feature = "hyper"))]
+mod connection;
+#[cfg(any(feature = "actix", feature = "hyper"))]
+mod limit;
+#[cfg(any(feature = "actix", feature = "hyper"))]
+mod options;
+#[cfg(any(feature = "actix", feature = "hyper"))]
+pub mod proxy;
+#[cfg(any(feature = "actix", feature = "hyper"))]
+mod server;
 
-impl std::fmt::Debug for PeerAddr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        <SocketAddr as std::fmt::Debug>::fmt(&self.0, f)
-    }
-}
+#[cfg(all(feature = "config", any(feature = "actix", feature = "hyper")))]
+pub use config::{
+    ConfigError, Http2Config, LimitConfig, ServerConfig, ShutdownConfig, TimeoutConfig, TlsConfig,
+};
+#[cfg(any(feature = "actix", feature = "hyper"))]
+pub use options::{Backend, ServerOptions};
+#[cfg(any(feature = "actix", feature = "hyper"))]
+pub use proxy::ProxyHeader;
+#[cfg(any(feature = "actix", feature = "hyper"))]
+pub use server::Server;
 
-impl std::fmt::Display for PeerAddr {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        <SocketAddr as std::fmt::Display>::fmt(&self.0,f)
-    }
-}
\ No newline at end of file
+pub use mtiny_core::{ConnectionInfo, PeerAddr};
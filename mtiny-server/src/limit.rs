@@ -0,0 +1,96 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use mtiny_core::http::StatusCode;
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::Service;
+use mtiny_core::{Request, Response};
+
+pub(crate) struct HeaderLimit<S> {
+    inner: S,
+    max_headers: Option<usize>,
+    max_header_size: Option<usize>,
+}
+
+impl<S> HeaderLimit<S> {
+    pub(crate) fn new(
+        inner: S,
+        max_headers: Option<usize>,
+        max_header_size: Option<usize>,
+    ) -> Self {
+        Self {
+            inner,
+            max_headers,
+            max_header_size,
+        }
+    }
+
+    fn exceeded(&self, request: &Request) -> bool {
+        let headers = request.headers();
+        if matches!(self.max_headers, Some(max) if headers.len() > max) {
+            return true;
+        }
+        if let Some(max) = self.max_header_size {
+            let size = headers
+                .iter()
+                .map(|(k, v)| k.as_str().len() + v.len())
+                .sum::<usize>();
+            return size > max;
+        }
+        false
+    }
+}
+
+impl<S> Service<Request> for HeaderLimit<S>
+where
+    S: Service<Request, Error = Infallible>,
+    S::Response: IntoResponse,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = HeaderLimitFuture<S::Future>;
+
+    fn call(&self, request: Request) -> Self::Future {
+        if self.exceeded(&request) {
+            HeaderLimitFuture::Reject
+        } else {
+            HeaderLimitFuture::Inner {
+                fut: self.inner.call(request),
+            }
+        }
+    }
+}
+
+pin_project! {
+    #[project = HeaderLimitFutureProj]
+    pub(crate) enum HeaderLimitFuture<F> {
+        Inner {
+            #[pin]
+            fut: F,
+        },
+        Reject,
+    }
+}
+
+impl<F, R> Future for HeaderLimitFuture<F>
+where
+    F: Future<Output = Result<R, Infallible>>,
+    R: IntoResponse,
+{
+    type Output = Result<Response, Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            HeaderLimitFutureProj::Inner { fut } => {
+                fut.poll(cx).map_ok(IntoResponse::into_response)
+            }
+            HeaderLimitFutureProj::Reject => Poll::Ready(Ok(
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE.into_response(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use mtiny_core::BoxError;
+
+pub(crate) const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+pub(crate) const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    // actix-http has no trailer support: response trailers the client asked
+    // for fail the body (over HTTP/2 actix only logs that and ends the
+    // stream), and declared request trailers fail `poll_trailers`
+    #[cfg(feature = "actix")]
+    Actix,
+    #[cfg(feature = "hyper")]
+    Hyper,
+}
+
+impl Default for Backend {
+    #[cfg(feature = "actix")]
+    fn default() -> Self {
+        Backend::Actix
+    }
+
+    #[cfg(not(feature = "actix"))]
+    fn default() -> Self {
+        Backend::Hyper
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ServerOptions {
+    pub(crate) addr: Vec<SocketAddr>,
+    pub(crate) listeners: Vec<TcpListener>,
+    pub(crate) workers: Option<usize>,
+    pub(crate) backend: Backend,
+    pub(crate) keep_alive: Option<Duration>,
+    pub(crate) client_request_timeout: Option<Duration>,
+    pub(crate) client_disconnect_timeout: Option<Duration>,
+    pub(crate) max_headers: Option<usize>,
+    pub(crate) max_header_size: Option<usize>,
+    pub(crate) backlog: Option<u32>,
+    pub(crate) max_connections: Option<usize>,
+    pub(crate) tcp_nodelay: Option<bool>,
+    pub(crate) http2_max_concurrent_streams: Option<u32>,
+    pub(crate) http2_initial_stream_window_size: Option<u32>,
+    pub(crate) http2_initial_connection_window_size: Option<u32>,
+    pub(crate) shutdown_timeout: Option<Duration>,
+    pub(crate) tls: Option<(PathBuf, PathBuf)>,
+    pub(crate) proxy_protocol: bool,
+}
+
+impl ServerOptions {
+    pub fn addr(&self) -> &[SocketAddr] {
+        &self.addr
+    }
+
+    pub fn workers(&self) -> Option<usize> {
+        self.workers
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn keep_alive(&self) -> Option<Duration> {
+        self.keep_alive
+    }
+
+    pub fn client_request_timeout(&self) -> Option<Duration> {
+        self.client_request_timeout
+    }
+
+    pub fn client_disconnect_timeout(&self) -> Option<Duration> {
+        self.client_disconnect_timeout
+    }
+
+    pub fn max_headers(&self) -> Option<usize> {
+        self.max_headers
+    }
+
+    pub fn max_header_size(&self) -> Option<usize> {
+        self.max_header_size
+    }
+
+    pub fn backlog(&self) -> Option<u32> {
+        self.backlog
+    }
+
+    pub fn max_connections(&self) -> Option<usize> {
+        self.max_connections
+    }
+
+    pub fn tcp_nodelay(&self) -> Option<bool> {
+        self.tcp_nodelay
+    }
+
+    pub fn http2_max_concurrent_streams(&self) -> Option<u32> {
+        self.http2_max_concurrent_streams
+    }
+
+    pub fn http2_initial_stream_window_size(&self) -> Option<u32> {
+        self.http2_initial_stream_window_size
+    }
+
+    pub fn http2_initial_connection_window_size(&self) -> Option<u32> {
+        self.http2_initial_connection_window_size
+    }
+
+    pub fn shutdown_timeout(&self) -> Option<Duration> {
+        self.shutdown_timeout
+    }
+
+    pub fn tls_cert(&self) -> Option<&Path> {
+        self.tls.as_ref().map(|(cert, _)| cert.as_path())
+    }
+
+    pub fn tls_key(&self) -> Option<&Path> {
+        self.tls.as_ref().map(|(_, key)| key.as_path())
+    }
+
+    pub fn proxy_protocol(&self) -> bool {
+        self.proxy_protocol
+    }
+
+    // a zero timeout disables it on every backend, the way actix reads it
+    pub(crate) fn request_timeout(&self, default: Duration) -> Option<Duration> {
+        match self.client_request_timeout {
+            Some(timeout) if timeout.is_zero() => None,
+            Some(timeout) => Some(timeout),
+            None => Some(default),
+        }
+    }
+
+    pub(crate) fn disconnect_timeout(&self) -> Option<Duration> {
+        self.client_disconnect_timeout
+            .filter(|timeout| !timeout.is_zero())
+    }
+
+    pub(crate) fn validate(self) -> Result<Self, BoxError> {
+        if self.addr.is_empty() && self.listeners.is_empty() {
+            return Err("no address to bind, call `bind` or `listen` first".into());
+        }
+        Ok(self)
+    }
+}
+
+pub(crate) fn window_size(size: u32, min: u32) -> Result<u32, String> {
+    if (min..=MAX_WINDOW_SIZE).contains(&size) {
+        Ok(size)
+    } else {
+        Err(format!("must be between {min} and {MAX_WINDOW_SIZE}"))
+    }
+}
+
+pub(crate) fn non_zero<T>(value: T) -> Result<T, String>
+where
+    T: Default + PartialEq,
+{
+    if value == T::default() {
+        Err("must be greater than 0".to_owned())
+    } else {
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ServerOptions {
+        ServerOptions {
+            addr: vec![([127, 0, 0, 1], 0).into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_requires_an_address() {
+        assert!(ServerOptions::default().validate().is_err());
+        assert!(options().validate().is_ok());
+    }
+
+    #[test]
+    fn connection_window_may_be_smaller_than_stream_window() {
+        let options = ServerOptions {
+            http2_initial_stream_window_size: Some(1 << 20),
+            http2_initial_connection_window_size: Some(DEFAULT_WINDOW_SIZE),
+            ..options()
+        };
+        assert!(options.validate().is_ok());
+    }
+
+    #[test]
+    fn window_size_bounds() {
+        assert_eq!(window_size(0, 0).unwrap(), 0);
+        assert_eq!(window_size(MAX_WINDOW_SIZE, 0).unwrap(), MAX_WINDOW_SIZE);
+        assert!(window_size(MAX_WINDOW_SIZE + 1, 0).is_err());
+        assert!(window_size(DEFAULT_WINDOW_SIZE - 1, DEFAULT_WINDOW_SIZE).is_err());
+    }
+
+    #[test]
+    fn non_zero_values() {
+        assert_eq!(non_zero(1usize).unwrap(), 1);
+        assert_eq!(non_zero(0usize).unwrap_err(), "must be greater than 0");
+    }
+
+    #[test]
+    fn zero_disables_timeouts() {
+        let default = Duration::from_secs(5);
+        let mut options = options();
+        assert_eq!(options.request_timeout(default), Some(default));
+        assert_eq!(options.disconnect_timeout(), None);
+
+        options.client_request_timeout = Some(Duration::from_millis(250));
+        options.client_disconnect_timeout = Some(Duration::from_secs(1));
+        assert_eq!(
+            options.request_timeout(default),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(options.disconnect_timeout(), Some(Duration::from_secs(1)));
+
+        options.client_request_timeout = Some(Duration::ZERO);
+        options.client_disconnect_timeout = Some(Duration::ZERO);
+        assert_eq!(options.request_timeout(default), None);
+        assert_eq!(options.disconnect_timeout(), None);
+    }
+}
@@ -0,0 +1,486 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use mtiny_core::body::Bytes;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+pub const PP2_TYPE_ALPN: u8 = 0x01;
+pub const PP2_TYPE_AUTHORITY: u8 = 0x02;
+pub const PP2_TYPE_CRC32C: u8 = 0x03;
+pub const PP2_TYPE_NOOP: u8 = 0x04;
+pub const PP2_TYPE_UNIQUE_ID: u8 = 0x05;
+pub const PP2_TYPE_SSL: u8 = 0x20;
+pub const PP2_SUBTYPE_SSL_VERSION: u8 = 0x21;
+pub const PP2_SUBTYPE_SSL_CN: u8 = 0x22;
+pub const PP2_SUBTYPE_SSL_CIPHER: u8 = 0x23;
+pub const PP2_SUBTYPE_SSL_SIG_ALG: u8 = 0x24;
+pub const PP2_SUBTYPE_SSL_KEY_ALG: u8 = 0x25;
+pub const PP2_TYPE_NETNS: u8 = 0x30;
+
+const PP2_CLIENT_SSL: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: u8,
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+    tlvs: Vec<Tlv>,
+}
+
+impl ProxyHeader {
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    pub fn tlvs(&self) -> &[Tlv] {
+        &self.tlvs
+    }
+
+    pub fn tlv(&self, kind: u8) -> Option<&Bytes> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .map(|tlv| &tlv.value)
+    }
+
+    pub fn alpn(&self) -> Option<&[u8]> {
+        self.tlv(PP2_TYPE_ALPN).map(|value| &value[..])
+    }
+
+    pub fn authority(&self) -> Option<&str> {
+        self.tlv(PP2_TYPE_AUTHORITY)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    pub fn unique_id(&self) -> Option<&[u8]> {
+        self.tlv(PP2_TYPE_UNIQUE_ID).map(|value| &value[..])
+    }
+
+    pub fn ssl(&self) -> Option<SslInfo> {
+        let value = self.tlv(PP2_TYPE_SSL)?;
+        if value.len() < 5 {
+            return None;
+        }
+        Some(SslInfo {
+            client: value[0],
+            verify: u32::from_be_bytes([value[1], value[2], value[3], value[4]]),
+            tlvs: parse_tlvs(value.slice(5..)).ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    kind: u8,
+    value: Bytes,
+}
+
+impl Tlv {
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SslInfo {
+    client: u8,
+    verify: u32,
+    tlvs: Vec<Tlv>,
+}
+
+impl SslInfo {
+    pub fn is_ssl(&self) -> bool {
+        self.client & PP2_CLIENT_SSL != 0
+    }
+
+    pub fn client(&self) -> u8 {
+        self.client
+    }
+
+    pub fn verified(&self) -> bool {
+        self.verify == 0
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.sub_str(PP2_SUBTYPE_SSL_VERSION)
+    }
+
+    pub fn common_name(&self) -> Option<&str> {
+        self.sub_str(PP2_SUBTYPE_SSL_CN)
+    }
+
+    pub fn cipher(&self) -> Option<&str> {
+        self.sub_str(PP2_SUBTYPE_SSL_CIPHER)
+    }
+
+    pub fn tlvs(&self) -> &[Tlv] {
+        &self.tlvs
+    }
+
+    fn sub_str(&self, kind: u8) -> Option<&str> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .and_then(|tlv| std::str::from_utf8(&tlv.value).ok())
+    }
+}
+
+pub(crate) async fn read_header(io: &mut TcpStream) -> io::Result<ProxyHeader> {
+    let mut buf = vec![0; V2_SIGNATURE.len()];
+    io.read_exact(&mut buf).await?;
+
+    if buf == V2_SIGNATURE {
+        let mut head = [0; 4];
+        io.read_exact(&mut head).await?;
+        let len = u16::from_be_bytes([head[2], head[3]]) as usize;
+        let mut body = vec![0; len];
+        io.read_exact(&mut body).await?;
+        return parse_v2(head[0], head[1], Bytes::from(body));
+    }
+
+    if !buf.starts_with(V1_PREFIX) {
+        return Err(malformed());
+    }
+    loop {
+        if let Some(end) = buf.windows(2).position(|w| w == b"\r\n") {
+            if end + 2 != buf.len() {
+                return Err(malformed());
+            }
+            return parse_v1(&buf[..end]);
+        }
+        if buf.contains(&b'\n') || buf.len() >= V1_MAX_LEN {
+            return Err(malformed());
+        }
+
+        let mut peek = [0; V1_MAX_LEN];
+        let n = io.peek(&mut peek[..V1_MAX_LEN - buf.len()]).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let take = match peek[..n].iter().position(|&b| b == b'\n') {
+            Some(pos) => pos + 1,
+            None => n,
+        };
+        let start = buf.len();
+        buf.resize(start + take, 0);
+        io.read_exact(&mut buf[start..]).await?;
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "malformed PROXY protocol header",
+    )
+}
+
+fn parse_v1(line: &[u8]) -> io::Result<ProxyHeader> {
+    let line = std::str::from_utf8(line).map_err(|_| malformed())?;
+    let mut parts = line.split(' ');
+    if parts.next() != Some("PROXY") {
+        return Err(malformed());
+    }
+
+    let header = |source, destination| ProxyHeader {
+        version: 1,
+        source,
+        destination,
+        tlvs: Vec::new(),
+    };
+    match parts.next() {
+        Some("UNKNOWN") => Ok(header(None, None)),
+        Some(family @ ("TCP4" | "TCP6")) => {
+            let fields = parts.collect::<Vec<_>>();
+            let [src, dst, sport, dport] = fields[..] else {
+                return Err(malformed());
+            };
+            let addr = |ip: &str, port: &str| -> io::Result<SocketAddr> {
+                let port = port.parse::<u16>().map_err(|_| malformed())?;
+                let ip = if family == "TCP4" {
+                    ip.parse::<Ipv4Addr>().map_err(|_| malformed())?.into()
+                } else {
+                    ip.parse::<Ipv6Addr>().map_err(|_| malformed())?.into()
+                };
+                Ok(SocketAddr::new(ip, port))
+            };
+            Ok(header(Some(addr(src, sport)?), Some(addr(dst, dport)?)))
+        }
+        _ => Err(malformed()),
+    }
+}
+
+fn parse_v2(ver_cmd: u8, family: u8, body: Bytes) -> io::Result<ProxyHeader> {
+    if ver_cmd >> 4 != 2 {
+        return Err(malformed());
+    }
+    let local = match ver_cmd & 0x0f {
+        0x0 => true,
+        0x1 => false,
+        _ => return Err(malformed()),
+    };
+    // only stream transports are served, LOCAL headers carry no usable addresses
+    if !local && !matches!((family >> 4, family & 0x0f), (0x0, 0x0) | (0x1..=0x3, 0x1)) {
+        return Err(malformed());
+    }
+
+    let (addrs, len) = match family >> 4 {
+        0x0 => (None, 0),
+        0x1 if body.len() >= 12 => {
+            let ip = |at: usize| Ipv4Addr::new(body[at], body[at + 1], body[at + 2], body[at + 3]);
+            let port = |at: usize| u16::from_be_bytes([body[at], body[at + 1]]);
+            let src = SocketAddr::new(ip(0).into(), port(8));
+            let dst = SocketAddr::new(ip(4).into(), port(10));
+            (Some((src, dst)), 12)
+        }
+        0x2 if body.len() >= 36 => {
+            let ip = |at: usize| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&body[at..at + 16]);
+                Ipv6Addr::from(octets)
+            };
+            let port = |at: usize| u16::from_be_bytes([body[at], body[at + 1]]);
+            let src = SocketAddr::new(ip(0).into(), port(32));
+            let dst = SocketAddr::new(ip(16).into(), port(34));
+            (Some((src, dst)), 36)
+        }
+        0x3 if body.len() >= 216 => (None, 216),
+        _ => return Err(malformed()),
+    };
+
+    let tlvs = parse_tlvs(body.slice(len..))?;
+    let (source, destination) = match addrs {
+        Some((src, dst)) if !local => (Some(src), Some(dst)),
+        _ => (None, None),
+    };
+    Ok(ProxyHeader {
+        version: 2,
+        source,
+        destination,
+        tlvs,
+    })
+}
+
+fn parse_tlvs(mut buf: Bytes) -> io::Result<Vec<Tlv>> {
+    let mut tlvs = Vec::new();
+    while !buf.is_empty() {
+        if buf.len() < 3 {
+            return Err(malformed());
+        }
+        let kind = buf[0];
+        let len = u16::from_be_bytes([buf[1], buf[2]]) as usize;
+        if buf.len() < 3 + len {
+            return Err(malformed());
+        }
+        let value = buf.slice(3..3 + len);
+        buf = buf.slice(3 + len..);
+        if kind != PP2_TYPE_NOOP {
+            tlvs.push(Tlv { kind, value });
+        }
+    }
+    Ok(tlvs)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn read(header: &[u8]) -> io::Result<(ProxyHeader, Vec<u8>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        client.write_all(header).await.unwrap();
+        client.write_all(b"GET").await.unwrap();
+        drop(client);
+
+        let header = read_header(&mut server).await?;
+        let mut rest = Vec::new();
+        server.read_to_end(&mut rest).await?;
+        Ok((header, rest))
+    }
+
+    fn v2(command: u8, family: u8, body: &[u8]) -> io::Result<ProxyHeader> {
+        parse_v2(0x20 | command, family, Bytes::copy_from_slice(body))
+    }
+
+    fn tlv(kind: u8, value: &[u8]) -> Vec<u8> {
+        let mut tlv = vec![kind];
+        tlv.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        tlv.extend_from_slice(value);
+        tlv
+    }
+
+    fn tcp4() -> Vec<u8> {
+        let mut body = vec![192, 0, 2, 1, 198, 51, 100, 1];
+        body.extend_from_slice(&56324u16.to_be_bytes());
+        body.extend_from_slice(&443u16.to_be_bytes());
+        body
+    }
+
+    #[tokio::test]
+    async fn reads_v1_and_leaves_the_request() {
+        let (header, rest) = read(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n")
+            .await
+            .unwrap();
+        assert_eq!(header.version(), 1);
+        assert_eq!(header.source(), Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET");
+
+        let (header, _) = read(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n")
+            .await
+            .unwrap();
+        assert_eq!(header.source(), None);
+        assert_eq!(header.destination(), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_v1_lines() {
+        let mut line = b"PROXY UNKNOWN ".to_vec();
+        line.resize(V1_MAX_LEN - 2, b'a');
+        line.extend_from_slice(b"\r\n");
+        assert!(read(&line).await.is_ok());
+
+        line.insert(20, b'a');
+        let err = read(&line).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn parses_v1_tcp6() {
+        let header = parse_v1(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443").unwrap();
+        assert_eq!(
+            header.source(),
+            Some("[2001:db8::1]:56324".parse().unwrap())
+        );
+        assert_eq!(
+            header.destination(),
+            Some("[2001:db8::2]:443".parse().unwrap())
+        );
+
+        assert!(parse_v1(b"PROXY TCP6 192.0.2.1 198.51.100.1 1 2").is_err());
+        assert!(parse_v1(b"PROXY TCP4 192.0.2.1 198.51.100.1 1").is_err());
+        assert!(parse_v1(b"PROXY TCP4 192.0.2.1 198.51.100.1 1 65536").is_err());
+    }
+
+    #[test]
+    fn parses_v2_tcp6() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        body.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        body.extend_from_slice(&56324u16.to_be_bytes());
+        body.extend_from_slice(&443u16.to_be_bytes());
+
+        let header = v2(0x1, 0x21, &body).unwrap();
+        assert_eq!(header.version(), 2);
+        assert_eq!(
+            header.source(),
+            Some("[2001:db8::1]:56324".parse().unwrap())
+        );
+        assert_eq!(
+            header.destination(),
+            Some("[2001:db8::2]:443".parse().unwrap())
+        );
+        assert!(v2(0x1, 0x21, &body[..35]).is_err());
+    }
+
+    #[test]
+    fn local_and_unix_headers_have_no_addresses() {
+        let mut body = tcp4();
+        body.extend(tlv(PP2_TYPE_AUTHORITY, b"example.com"));
+        let header = v2(0x0, 0x11, &body).unwrap();
+        assert_eq!(header.source(), None);
+        assert_eq!(header.authority(), Some("example.com"));
+
+        let header = v2(0x0, 0x00, &[]).unwrap();
+        assert_eq!(header.source(), None);
+
+        let mut body = vec![0; 216];
+        body.extend(tlv(PP2_TYPE_UNIQUE_ID, b"id"));
+        let header = v2(0x1, 0x31, &body).unwrap();
+        assert_eq!(header.source(), None);
+        assert_eq!(header.unique_id(), Some(&b"id"[..]));
+        assert!(v2(0x1, 0x31, &body[..215]).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_transports_and_commands() {
+        assert!(v2(0x1, 0x11, &tcp4()).is_ok());
+        // datagram, unknown transport, stream without an address family
+        for family in [0x12, 0x13, 0x22, 0x32, 0x01, 0x41] {
+            assert!(v2(0x1, family, &tcp4()).is_err(), "{family:#x}");
+        }
+        assert!(v2(0x2, 0x11, &tcp4()).is_err());
+        assert!(parse_v2(0x11, 0x11, Bytes::from(tcp4())).is_err());
+    }
+
+    #[test]
+    fn rejects_broken_tlvs() {
+        let mut body = tcp4();
+        body.extend(tlv(PP2_TYPE_ALPN, b"h2"));
+        body.extend(tlv(PP2_TYPE_NOOP, &[0; 3]));
+        let header = v2(0x1, 0x11, &body).unwrap();
+        assert_eq!(header.alpn(), Some(&b"h2"[..]));
+        assert_eq!(header.tlvs().len(), 1);
+
+        // a truncated tlv header, then a value running past the block
+        let mut truncated = tcp4();
+        truncated.extend_from_slice(&[PP2_TYPE_ALPN, 0]);
+        assert!(v2(0x1, 0x11, &truncated).is_err());
+        let mut overrun = tcp4();
+        overrun.extend_from_slice(&[PP2_TYPE_ALPN, 0, 3, b'h', b'2']);
+        assert!(v2(0x1, 0x11, &overrun).is_err());
+    }
+
+    #[test]
+    fn reads_ssl_sub_tlvs() {
+        let mut ssl = vec![PP2_CLIENT_SSL | 0x04, 0, 0, 0, 0];
+        ssl.extend(tlv(PP2_SUBTYPE_SSL_VERSION, b"TLSv1.3"));
+        ssl.extend(tlv(PP2_SUBTYPE_SSL_CN, b"client.example"));
+        ssl.extend(tlv(PP2_SUBTYPE_SSL_CIPHER, b"TLS_AES_128_GCM_SHA256"));
+        let mut body = tcp4();
+        body.extend(tlv(PP2_TYPE_SSL, &ssl));
+
+        let info = v2(0x1, 0x11, &body).unwrap().ssl().unwrap();
+        assert!(info.is_ssl());
+        assert!(info.verified());
+        assert_eq!(info.client(), 0x05);
+        assert_eq!(info.version(), Some("TLSv1.3"));
+        assert_eq!(info.common_name(), Some("client.example"));
+        assert_eq!(info.cipher(), Some("TLS_AES_128_GCM_SHA256"));
+
+        // a failed verification, and sub-tlvs that overrun the ssl value
+        ssl[4] = 1;
+        ssl.push(PP2_SUBTYPE_SSL_SIG_ALG);
+        let mut body = tcp4();
+        body.extend(tlv(PP2_TYPE_SSL, &ssl[..5]));
+        let info = v2(0x1, 0x11, &body).unwrap().ssl().unwrap();
+        assert!(!info.verified());
+        assert!(info.tlvs().is_empty());
+
+        let mut body = tcp4();
+        body.extend(tlv(PP2_TYPE_SSL, &ssl));
+        assert_eq!(v2(0x1, 0x11, &body).unwrap().ssl(), None);
+    }
+}
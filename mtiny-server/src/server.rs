@@ -0,0 +1,248 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener};
+#[cfg(feature = "tls")]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::Service;
+use mtiny_core::{BoxError, Request};
+
+#[cfg(feature = "config")]
+use crate::config::ServerConfig;
+use crate::options::{non_zero, window_size, Backend, ServerOptions, DEFAULT_WINDOW_SIZE};
+
+pub struct Server<F> {
+    factory: F,
+    options: Result<ServerOptions, BoxError>,
+}
+
+impl<F, S> Server<F>
+where
+    F: Fn() -> S + Clone + Send + 'static,
+    S: Service<Request, Error = Infallible> + 'static,
+    S::Response: IntoResponse,
+    S::Future: 'static,
+{
+    pub fn new(factory: F) -> Self {
+        Self {
+            factory,
+            options: Ok(ServerOptions::default()),
+        }
+    }
+
+    #[cfg(feature = "config")]
+    pub fn from_config(factory: F, config: ServerConfig) -> Self {
+        Self {
+            factory,
+            options: config.into_options().map_err(Into::into),
+        }
+    }
+
+    pub fn workers(mut self, num: usize) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.workers = Some(non_zero(num).map_err(invalid("workers"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn bind<T>(mut self, addr: T) -> Self
+    where
+        T: Into<SocketAddr>,
+    {
+        self.options = self.options.map(|mut sp| {
+            sp.addr.push(addr.into());
+            sp
+        });
+        self
+    }
+
+    pub fn listen(mut self, listener: TcpListener) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.listeners.push(listener);
+            sp
+        });
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.backend = backend;
+            sp
+        });
+        self
+    }
+
+    pub fn keep_alive(mut self, dur: Duration) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.keep_alive = Some(dur);
+            sp
+        });
+        self
+    }
+
+    pub fn client_request_timeout(mut self, dur: Duration) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.client_request_timeout = Some(dur);
+            sp
+        });
+        self
+    }
+
+    pub fn client_disconnect_timeout(mut self, dur: Duration) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.client_disconnect_timeout = Some(dur);
+            sp
+        });
+        self
+    }
+
+    pub fn max_headers(mut self, num: usize) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.max_headers = Some(non_zero(num).map_err(invalid("max_headers"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn max_header_size(mut self, size: usize) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.max_header_size = Some(non_zero(size).map_err(invalid("max_header_size"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn backlog(mut self, num: u32) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.backlog = Some(non_zero(num).map_err(invalid("backlog"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn max_connections(mut self, num: usize) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.max_connections = Some(non_zero(num).map_err(invalid("max_connections"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.tcp_nodelay = Some(enabled);
+            sp
+        });
+        self
+    }
+
+    pub fn http2_max_concurrent_streams(mut self, num: u32) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.http2_max_concurrent_streams =
+                Some(non_zero(num).map_err(invalid("http2_max_concurrent_streams"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn http2_initial_stream_window_size(mut self, size: u32) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.http2_initial_stream_window_size =
+                Some(window_size(size, 0).map_err(invalid("http2_initial_stream_window_size"))?);
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn http2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.options = self.options.and_then(|mut sp| {
+            sp.http2_initial_connection_window_size = Some(
+                window_size(size, DEFAULT_WINDOW_SIZE)
+                    .map_err(invalid("http2_initial_connection_window_size"))?,
+            );
+            Ok(sp)
+        });
+        self
+    }
+
+    pub fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.proxy_protocol = enabled;
+            sp
+        });
+        self
+    }
+
+    pub fn shutdown_timeout(mut self, dur: Duration) -> Self {
+        self.options = self.options.map(|mut sp| {
+            sp.shutdown_timeout = Some(dur);
+            sp
+        });
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn tls<C, K>(mut self, cert: C, key: K) -> Self
+    where
+        C: Into<PathBuf>,
+        K: Into<PathBuf>,
+    {
+        self.options = self.options.map(|mut sp| {
+            sp.tls = Some((cert.into(), key.into()));
+            sp
+        });
+        self
+    }
+
+    pub async fn run(self) -> Result<(), BoxError> {
+        self.run_until(shutdown_signal()).await
+    }
+
+    pub async fn run_until<G>(self, signal: G) -> Result<(), BoxError>
+    where
+        G: Future<Output = ()>,
+    {
+        let options = self.options.and_then(ServerOptions::validate)?;
+
+        match options.backend {
+            #[cfg(feature = "actix")]
+            Backend::Actix => crate::actix::run(self.factory, options, signal).await,
+            #[cfg(feature = "hyper")]
+            Backend::Hyper => crate::hyper::run(self.factory, options, signal).await,
+        }
+    }
+}
+
+fn invalid(name: &'static str) -> impl FnOnce(String) -> BoxError {
+    move |reason| format!("`{name}` {reason}").into()
+}
+
+impl<F> std::fmt::Debug for Server<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server").finish()
+    }
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(_) => return std::future::pending().await,
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await
+    }
+}
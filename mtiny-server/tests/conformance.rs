@@ -0,0 +1,1229 @@
+#![cfg(any(feature = "actix", feature = "hyper"))]
+
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::thread::JoinHandle;
+
+use http_body_util::{BodyExt as _, Full};
+use hyper::body::{Bytes, Frame};
+use hyper::{Method, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+
+use mtiny_core::body::{BodyExt, LengthLimitError, StreamBody};
+use mtiny_core::response::IntoResponse;
+use mtiny_core::service::{Service, ServiceExt};
+use mtiny_core::{service_fn, BoxError, Request, Response};
+use mtiny_middleware::compression::compression;
+use mtiny_middleware::core::access_log::{access_log, LogFormat};
+use mtiny_middleware::core::body_limit::body_limit;
+use mtiny_middleware::core::cors::{cors, AllowOrigin};
+use mtiny_middleware::core::from_fn::{from_fn, from_fn_with_state, Next};
+use mtiny_middleware::core::handle_error::handle_error;
+use mtiny_middleware::core::rate_limit::{rate_limit, Quota, RateLimitKey};
+use mtiny_middleware::core::request_id::{request_id, IdFormat, RequestId};
+use mtiny_middleware::core::trace::trace;
+use mtiny_middleware::core::trace_context::{trace_context, TraceContext};
+use mtiny_middleware::decompression::decompression;
+use mtiny_middleware::timeout::timeout;
+use mtiny_router::Router;
+use mtiny_server::{Backend, ConnectionInfo, PeerAddr, ProxyHeader, Server};
+use mtiny_sse::{Event, KeepAlive, Sse};
+use mtiny_ws::{Message, WebSocketUpgrade};
+
+struct TestServer {
+    addr: SocketAddr,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    fn start(backend: Backend) -> Self {
+        Self::start_with(backend, false)
+    }
+
+    fn start_with(backend: Backend, proxy_protocol: bool) -> Self {
+        Self::serve(backend, proxy_protocol, || service_fn(app))
+    }
+
+    fn serve<F, S>(backend: Backend, proxy_protocol: bool, factory: F) -> Self
+    where
+        F: Fn() -> S + Clone + Send + 'static,
+        S: Service<Request, Response = Response, Error = Infallible> + 'static,
+        S::Future: 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+
+        let thread = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(
+                    Server::new(factory)
+                        .listen(listener)
+                        .workers(1)
+                        .backend(backend)
+                        .proxy_protocol(proxy_protocol)
+                        .run_until(async {
+                            let _ = stopped.await;
+                        }),
+                )
+                .unwrap();
+        });
+
+        Self {
+            addr,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.stop.take().unwrap().send(());
+        let _ = self.thread.take().unwrap().join();
+    }
+}
+
+static ACCESS_LOG: std::sync::Mutex<Vec<u8>> = std::sync::Mutex::new(Vec::new());
+
+struct AccessLogSink;
+
+impl std::io::Write for AccessLogSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        ACCESS_LOG.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn access_log_line(token: &str) -> String {
+    for _ in 0..100 {
+        let lines = String::from_utf8(ACCESS_LOG.lock().unwrap().clone()).unwrap();
+        if let Some(line) = lines.lines().find(|line| line.contains(token)) {
+            return line.to_owned();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("no access log line for {token}");
+}
+
+static TRACE_EVENTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+fn trace_event(event: String) {
+    TRACE_EVENTS.lock().unwrap().push(event);
+}
+
+async fn trace_events(token: &str, count: usize) -> Vec<String> {
+    for _ in 0..100 {
+        let events = TRACE_EVENTS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| event.strip_suffix(token))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        if events.len() >= count {
+            return events;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("missing trace events for {token}");
+}
+
+async fn app(mut request: Request) -> Result<Response, Infallible> {
+    let response = match request.uri().path() {
+        "/hello" => "hello world".into_response(),
+        "/echo" => std::mem::take(request.body_mut()).into_response(),
+        "/spawn" => {
+            let mut body = std::mem::take(request.body_mut());
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                while let Some(Ok(chunk)) = body.next().await {
+                    buf.extend_from_slice(&chunk);
+                }
+                buf
+            })
+            .await
+            .unwrap()
+            .into_response()
+        }
+        "/peer" => request
+            .extensions()
+            .get::<PeerAddr>()
+            .map(|addr| addr.0.ip().to_string())
+            .unwrap_or_default()
+            .into_response(),
+        "/header" => {
+            let mut response = ().into_response();
+            if let Some(value) = request.headers().get("x-mtiny") {
+                response.headers_mut().insert("x-mtiny", value.clone());
+            }
+            response
+        }
+        "/stream" => StreamBody::new(futures_util::stream::iter(
+            ["chunk-1,", "chunk-2,", "chunk-3"].map(Ok::<_, Infallible>),
+        ))
+        .boxed()
+        .into_response(),
+        "/trailers" => {
+            let mut len = 0;
+            while let Some(Ok(chunk)) = request.body_mut().next().await {
+                len += chunk.len();
+            }
+            let mut trailers = hyper::HeaderMap::new();
+            trailers.insert("x-length", len.into());
+            let received = request.body_mut().trailers().await;
+            if let Ok(Some(received)) = &received {
+                if let Some(value) = received.get("x-client") {
+                    trailers.insert("x-client", value.clone());
+                }
+            }
+            let mut response = StreamBody::new(futures_util::stream::iter(
+                ["trailers", " follow"].map(Ok::<_, Infallible>),
+            ))
+            .with_trailers(trailers)
+            .boxed()
+            .into_response();
+            response
+                .headers_mut()
+                .insert("trailer", "x-length, x-client".parse().unwrap());
+            if received.is_err() {
+                response
+                    .headers_mut()
+                    .insert("x-trailers-error", "true".parse().unwrap());
+            }
+            response
+        }
+        "/from_fn" => service_fn(|_: Request| async { Ok::<_, Infallible>("inner") })
+            .with(from_fn(|request: Request, next: Next<_>| next.run(request)))
+            .with(from_fn_with_state(
+                "guard",
+                |state: &'static str, request: Request, next: Next<_>| async move {
+                    let mut response = next.run(request).await?;
+                    response
+                        .headers_mut()
+                        .insert("x-state", state.parse().unwrap());
+                    Ok::<_, BoxError>(response)
+                },
+            ))
+            .call(request)
+            .await
+            .unwrap(),
+        "/compress" => {
+            service_fn(|_: Request| async { Ok::<_, Infallible>("compress me ".repeat(256)) })
+                .with(compression())
+                .call(request)
+                .await
+                .unwrap()
+        }
+        "/decompress" => service_fn(|mut request: Request| async move {
+            let encoded = request.headers().contains_key("content-encoding");
+            let mut buf = Vec::new();
+            while let Some(chunk) = request.body_mut().next().await {
+                buf.extend_from_slice(&chunk.unwrap());
+            }
+            Ok::<_, Infallible>(
+                format!("{encoded}:{}", String::from_utf8_lossy(&buf)).into_response(),
+            )
+        })
+        .with(decompression())
+        .call(request)
+        .await
+        .unwrap(),
+        "/cors" => service_fn(|_: Request| async { Ok::<_, Infallible>("cors") })
+            .with(
+                cors()
+                    .allow_origin(AllowOrigin::predicate(|origin| {
+                        origin.as_bytes().ends_with(b".example.com")
+                    }))
+                    .allow_methods([Method::GET, Method::PUT]),
+            )
+            .call(request)
+            .await
+            .unwrap(),
+        "/request_id" => service_fn(|request: Request| async move {
+            let id = request.extensions().get::<RequestId>().unwrap().to_string();
+            Ok::<_, Infallible>(format!("handler:{id}").into_response())
+        })
+        .with(
+            request_id()
+                .header("x-correlation-id".parse().unwrap())
+                .format(IdFormat::Ulid),
+        )
+        .call(request)
+        .await
+        .unwrap(),
+        "/access_log" => service_fn(|_: Request| async {
+            let chunks = ["access ", "logged"].map(|chunk| Ok::<_, Infallible>(Bytes::from(chunk)));
+            Ok::<_, Infallible>(StreamBody::new(futures_util::stream::iter(chunks)).into_response())
+        })
+        .with(
+            access_log()
+                .format(LogFormat::Json)
+                .to_writer(AccessLogSink),
+        )
+        .call(request)
+        .await
+        .unwrap(),
+        "/trace" => {
+            let token = request.uri().query().unwrap_or_default().to_owned();
+            let fail = token.starts_with("fail");
+            let (span_token, response_token, eos_token, error_token) =
+                (token.clone(), token.clone(), token.clone(), token.clone());
+            service_fn(move |_: Request| {
+                let token = token.clone();
+                async move {
+                    trace_event(format!("handler {token}"));
+                    let chunks = [
+                        Ok(Bytes::from("traced")),
+                        Err(std::io::Error::other("broken")),
+                    ];
+                    let chunks = chunks.into_iter().take(if fail { 2 } else { 1 });
+                    Ok::<_, Infallible>(
+                        StreamBody::new(futures_util::stream::iter(chunks)).into_response(),
+                    )
+                }
+            })
+            .with(
+                trace()
+                    .make_span(move |request: &Request| {
+                        trace_event(format!("span {} {span_token}", request.method()));
+                        tracing::Span::none()
+                    })
+                    .on_response(move |response, _, _| {
+                        trace_event(format!("response {} {response_token}", response.status()))
+                    })
+                    .on_eos(move |_, _| trace_event(format!("eos {eos_token}")))
+                    .on_body_error(move |err, _, _| {
+                        trace_event(format!("body error {err} {error_token}"))
+                    }),
+            )
+            .call(request)
+            .await
+            .unwrap()
+        }
+        "/trace_context" => {
+            let handler = service_fn(|request: Request| async move {
+                let context = request.extensions().get::<TraceContext>().unwrap();
+                let mut downstream = mtiny_core::http::HeaderMap::new();
+                context.child().inject(&mut downstream);
+                Ok::<_, Infallible>(
+                    downstream["traceparent"]
+                        .to_str()
+                        .unwrap()
+                        .to_owned()
+                        .into_response(),
+                )
+            });
+            handler.with(trace_context()).call(request).await.unwrap()
+        }
+        "/timeout" => {
+            // the first chunk goes out, then the body stalls past its idle timeout
+            service_fn(|_: Request| async {
+                let stalled = futures_util::StreamExt::chain(
+                    futures_util::stream::iter([Ok::<_, Infallible>(Bytes::from("partial"))]),
+                    futures_util::stream::pending(),
+                );
+                Ok::<_, Infallible>(StreamBody::new(stalled).into_response())
+            })
+            .with(
+                timeout(std::time::Duration::from_secs(5))
+                    .body_timeout(std::time::Duration::from_millis(50)),
+            )
+            .call(request)
+            .await
+            .unwrap()
+        }
+        "/body_limit" => service_fn(|mut request: Request| async move {
+            let mut buf = Vec::new();
+            while let Some(chunk) = request.body_mut().next().await {
+                match chunk {
+                    Ok(chunk) => buf.extend_from_slice(&chunk),
+                    Err(err) => {
+                        let err = err.downcast::<LengthLimitError>().unwrap();
+                        let mut response = err.into_response();
+                        response
+                            .headers_mut()
+                            .insert("x-streamed", "true".parse().unwrap());
+                        return Ok::<_, Infallible>(response);
+                    }
+                }
+            }
+            Ok(buf.into_response())
+        })
+        .with(body_limit(16))
+        .call(request)
+        .await
+        .unwrap(),
+        "/version" => format!("{:?}", request.version()).into_response(),
+        "/conn" => request
+            .extensions()
+            .get::<ConnectionInfo>()
+            .map(|info| {
+                format!(
+                    "{} {} {} {:?} {}",
+                    info.id(),
+                    info.local_addr(),
+                    info.scheme(),
+                    info.version(),
+                    info.request_count()
+                )
+            })
+            .unwrap_or_default()
+            .into_response(),
+        "/proxy" => request
+            .extensions()
+            .get::<ProxyHeader>()
+            .map(|header| {
+                format!(
+                    "{} {}",
+                    header.version(),
+                    header.authority().unwrap_or_default()
+                )
+            })
+            .unwrap_or_default()
+            .into_response(),
+        "/sse" => {
+            let start = mtiny_sse::last_event_id(&request)
+                .and_then(|id| id.parse::<u32>().ok())
+                .map_or(1, |id| id + 1);
+            let events = (start..start + 2).map(|id| {
+                Ok::<_, Infallible>(
+                    Event::new()
+                        .event("tick")
+                        .id(id.to_string())
+                        .data(format!("line {id}\nsecond")),
+                )
+            });
+            Sse::new(futures_util::stream::iter(events))
+                .keep_alive(KeepAlive::new())
+                .into_response()
+        }
+        "/ws" => match WebSocketUpgrade::from_request(&mut request) {
+            Ok(upgrade) => upgrade
+                .protocols(["chat"])
+                .max_message_size(1024)
+                .compression(true)
+                .on_upgrade(|mut socket| async move {
+                    while let Some(Ok(message)) = socket.recv().await {
+                        if let Message::Text(_) | Message::Binary(_) = message {
+                            if socket.send(message).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }),
+            Err(err) => err.into_response(),
+        },
+        _ => StatusCode::NOT_FOUND.into_response(),
+    };
+    Ok(response)
+}
+
+struct TestResponse {
+    status: StatusCode,
+    headers: hyper::HeaderMap,
+    body: Bytes,
+    trailers: Option<hyper::HeaderMap>,
+}
+
+async fn send<B>(server: &TestServer, http2: bool, request: hyper::Request<B>) -> TestResponse
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    send_all(server, http2, vec![request]).await.remove(0)
+}
+
+async fn send_all<B>(
+    server: &TestServer,
+    http2: bool,
+    requests: Vec<hyper::Request<B>>,
+) -> Vec<TestResponse>
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    send_with_prefix(server, http2, &[], requests).await
+}
+
+async fn send_with_prefix<B>(
+    server: &TestServer,
+    http2: bool,
+    prefix: &[u8],
+    requests: Vec<hyper::Request<B>>,
+) -> Vec<TestResponse>
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    stream.write_all(prefix).await.unwrap();
+    let io = TokioIo::new(stream);
+    let mut responses = Vec::with_capacity(requests.len());
+
+    if http2 {
+        let (mut sender, conn) = hyper::client::conn::http2::handshake(TokioExecutor::new(), io)
+            .await
+            .unwrap();
+        let conn = tokio::spawn(conn);
+        for request in requests {
+            let response = sender.send_request(request).await.unwrap();
+            responses.push(into_test_response(response).await);
+        }
+        drop(sender);
+        conn.await.unwrap().unwrap();
+    } else {
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await.unwrap();
+        let conn = tokio::spawn(conn);
+        for request in requests {
+            let response = sender.send_request(request).await.unwrap();
+            responses.push(into_test_response(response).await);
+        }
+        drop(sender);
+        conn.await.unwrap().unwrap();
+    }
+
+    responses
+}
+
+// leaves the connection running so the caller can inspect a failing body,
+// it has to be aborted and awaited before the server is dropped
+#[cfg(feature = "actix")]
+async fn send_raw<B>(
+    server: &TestServer,
+    http2: bool,
+    request: hyper::Request<B>,
+) -> (
+    hyper::Result<hyper::Response<hyper::body::Incoming>>,
+    tokio::task::JoinHandle<()>,
+)
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let io = TokioIo::new(TcpStream::connect(server.addr).await.unwrap());
+    if http2 {
+        let (mut sender, conn) = hyper::client::conn::http2::handshake(TokioExecutor::new(), io)
+            .await
+            .unwrap();
+        let conn = tokio::spawn(async move { drop(conn.await) });
+        (sender.send_request(request).await, conn)
+    } else {
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await.unwrap();
+        let conn = tokio::spawn(async move { drop(conn.await) });
+        (sender.send_request(request).await, conn)
+    }
+}
+
+async fn into_test_response(response: hyper::Response<hyper::body::Incoming>) -> TestResponse {
+    let (parts, body) = response.into_parts();
+    let body = body.collect().await.unwrap();
+    TestResponse {
+        status: parts.status,
+        headers: parts.headers,
+        trailers: body.trailers().cloned(),
+        body: body.to_bytes(),
+    }
+}
+
+fn request(method: Method, path: &str, body: &'static str) -> hyper::Request<Full<Bytes>> {
+    hyper::Request::builder()
+        .method(method)
+        .uri(format!("http://localhost{path}"))
+        .body(Full::new(Bytes::from_static(body.as_bytes())))
+        .unwrap()
+}
+
+async fn plain_text(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/hello", "")).await;
+
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.headers["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(res.body, "hello world");
+}
+
+async fn echo_body(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::POST, "/echo", "ping pong")).await;
+
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body, "ping pong");
+}
+
+async fn spawned_body(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::POST, "/spawn", "ping pong")).await;
+
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body, "ping pong");
+}
+
+async fn status_code(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/missing", "")).await;
+
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert!(res.body.is_empty());
+}
+
+async fn request_headers(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let mut req = request(Method::GET, "/header", "");
+    req.headers_mut()
+        .insert("x-mtiny", "conformance".parse().unwrap());
+    let res = send(&server, http2, req).await;
+
+    assert_eq!(res.headers["x-mtiny"], "conformance");
+}
+
+async fn peer_addr(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/peer", "")).await;
+
+    assert_eq!(res.body, "127.0.0.1");
+}
+
+async fn streamed_body(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/stream", "")).await;
+
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body, "chunk-1,chunk-2,chunk-3");
+}
+
+async fn protocol_version(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/version", "")).await;
+
+    let expected = if http2 { "HTTP/2.0" } else { "HTTP/1.1" };
+    assert_eq!(res.body, expected);
+}
+
+async fn connection_info(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let first = send_all(
+        &server,
+        http2,
+        vec![
+            request(Method::GET, "/conn", ""),
+            request(Method::GET, "/conn", ""),
+        ],
+    )
+    .await;
+    let second = send(&server, http2, request(Method::GET, "/conn", "")).await;
+
+    let fields = |res: &TestResponse| {
+        std::str::from_utf8(&res.body)
+            .unwrap()
+            .split(' ')
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    let (a, b, c) = (fields(&first[0]), fields(&first[1]), fields(&second));
+    let version = if http2 { "HTTP/2.0" } else { "HTTP/1.1" };
+
+    assert_eq!(a[1..], [&server.addr.to_string(), "http", version, "1"]);
+    assert_eq!(b[1..], [&server.addr.to_string(), "http", version, "2"]);
+    assert_eq!(c[4], "1");
+    assert_eq!(a[0], b[0]);
+    assert!(c[0].parse::<u64>().unwrap() > a[0].parse::<u64>().unwrap());
+}
+
+async fn proxy_protocol_v1(backend: Backend, http2: bool) {
+    let server = TestServer::start_with(backend, true);
+    let prefix = b"PROXY TCP4 203.0.113.7 10.0.0.1 5555 80\r\n";
+    let res = send_with_prefix(
+        &server,
+        http2,
+        prefix,
+        vec![request(Method::GET, "/peer", "")],
+    )
+    .await
+    .remove(0);
+
+    assert_eq!(res.body, "203.0.113.7");
+}
+
+async fn proxy_protocol_v2(backend: Backend, http2: bool) {
+    let server = TestServer::start_with(backend, true);
+
+    let authority = b"example.com";
+    let mut prefix = b"\r\n\r\n\0\r\nQUIT\n\x21\x11".to_vec();
+    prefix.extend_from_slice(&(12 + 3 + authority.len() as u16).to_be_bytes());
+    prefix.extend_from_slice(&[198, 51, 100, 9, 10, 0, 0, 1]);
+    prefix.extend_from_slice(&4321u16.to_be_bytes());
+    prefix.extend_from_slice(&80u16.to_be_bytes());
+    prefix.push(0x02);
+    prefix.extend_from_slice(&(authority.len() as u16).to_be_bytes());
+    prefix.extend_from_slice(authority);
+
+    let res = send_with_prefix(
+        &server,
+        http2,
+        &prefix,
+        vec![
+            request(Method::GET, "/peer", ""),
+            request(Method::GET, "/proxy", ""),
+        ],
+    )
+    .await;
+
+    assert_eq!(res[0].body, "198.51.100.9");
+    assert_eq!(res[1].body, "2 example.com");
+}
+
+async fn proxy_protocol_malformed(backend: Backend, _http2: bool) {
+    let server = TestServer::start_with(backend, true);
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    stream
+        .write_all(b"PROXY TCP4 not-an-ip 10.0.0.1 5555 80\r\nGET /hello HTTP/1.1\r\nhost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf).await;
+    assert!(buf.is_empty());
+}
+
+async fn server_sent_events(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let mut req = request(Method::GET, "/sse", "");
+    req.headers_mut()
+        .insert("last-event-id", "41".parse().unwrap());
+    let res = send(&server, http2, req).await;
+
+    assert_eq!(res.headers["content-type"], "text/event-stream");
+    assert_eq!(res.headers["cache-control"], "no-cache");
+    assert_eq!(
+        res.body,
+        "event: tick\ndata: line 42\ndata: second\nid: 42\n\n\
+         event: tick\ndata: line 43\ndata: second\nid: 43\n\n"
+    );
+}
+
+async fn middleware_from_fn(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/from_fn", "")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.headers["x-state"], "guard");
+    assert_eq!(res.body, "inner");
+}
+
+async fn response_compression(backend: Backend, http2: bool) {
+    use std::io::Read;
+
+    let server = TestServer::start(backend);
+    let mut req = request(Method::GET, "/compress", "");
+    req.headers_mut()
+        .insert("accept-encoding", "gzip".parse().unwrap());
+    let res = send(&server, http2, req).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.headers["content-encoding"], "gzip");
+    let mut body = String::new();
+    flate2::read::GzDecoder::new(&res.body[..])
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, "compress me ".repeat(256));
+}
+
+async fn request_decompression(backend: Backend, http2: bool) {
+    use std::io::Write;
+
+    let server = TestServer::start(backend);
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(br#"{"hello":"world"}"#).unwrap();
+    let req = hyper::Request::builder()
+        .method(Method::POST)
+        .uri("http://localhost/decompress")
+        .header("content-encoding", "gzip")
+        .body(Full::new(Bytes::from(gzip.finish().unwrap())))
+        .unwrap();
+    let res = send(&server, http2, req).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body, r#"false:{"hello":"world"}"#);
+}
+
+async fn cors_middleware(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let mut req = request(Method::OPTIONS, "/cors", "");
+    let headers = req.headers_mut();
+    headers.insert("origin", "https://app.example.com".parse().unwrap());
+    headers.insert("access-control-request-method", "PUT".parse().unwrap());
+    let res = send(&server, http2, req).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    assert_eq!(
+        res.headers["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    assert_eq!(res.headers["access-control-allow-methods"], "GET, PUT");
+    assert!(res.body.is_empty());
+}
+
+async fn request_id_propagation(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let res = send(&server, http2, request(Method::GET, "/request_id", "")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let id = res.headers["x-correlation-id"].to_str().unwrap();
+    assert_eq!(id.len(), 26);
+    assert_eq!(res.body, format!("handler:{id}"));
+}
+
+async fn access_logging(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let token = format!("json-{backend:?}-{http2}");
+    let mut req = request(Method::GET, &format!("/access_log?{token}"), "");
+    req.headers_mut()
+        .insert("user-agent", "conformance/1.0".parse().unwrap());
+    let res = send(&server, http2, req).await;
+    assert_eq!(res.body, "access logged");
+    let line = access_log_line(&token).await;
+    assert!(line.starts_with(r#"{"time":""#), "{line}");
+    assert!(line.contains(&format!(r#""method":"GET","path":"/access_log?{token}""#)));
+    assert!(line.contains(r#""status":200,"bytes":13,"#), "{line}");
+    assert!(line.contains(r#""peer":"127.0.0.1""#), "{line}");
+    assert!(line.contains(r#""user_agent":"conformance/1.0""#), "{line}");
+}
+
+async fn tracing_spans(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let token = format!("ok-{backend:?}-{http2}");
+    let res = send(
+        &server,
+        http2,
+        request(Method::GET, &format!("/trace?{token}"), ""),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body, "traced");
+    assert_eq!(
+        trace_events(&token, 4).await,
+        ["span GET ", "handler ", "response 200 OK ", "eos "]
+    );
+
+    let token = format!("fail-{backend:?}-{http2}");
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    stream
+        .write_all(format!("GET /trace?{token} HTTP/1.1\r\nhost: localhost\r\n\r\n").as_bytes())
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf).await;
+    assert_eq!(
+        trace_events(&token, 4).await,
+        [
+            "span GET ",
+            "handler ",
+            "response 200 OK ",
+            "body error broken "
+        ]
+    );
+}
+
+async fn trace_context_propagation(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    let field = |value: &str, i: usize| value.split('-').nth(i).unwrap().to_owned();
+
+    let mut req = request(Method::GET, "/trace_context", "");
+    req.headers_mut().insert(
+        "traceparent",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+            .parse()
+            .unwrap(),
+    );
+    req.headers_mut()
+        .append("tracestate", "congo=t61rcWkgMzE".parse().unwrap());
+    req.headers_mut()
+        .append("tracestate", "rojo=00f067aa0ba902b7".parse().unwrap());
+    let res = send(&server, http2, req).await;
+    let response = res.headers["traceparent"].to_str().unwrap();
+    assert_eq!(field(response, 1), "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(
+        res.headers["tracestate"],
+        "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7"
+    );
+    let downstream = String::from_utf8(res.body.to_vec()).unwrap();
+    assert_eq!(field(&downstream, 1), field(response, 1));
+}
+
+async fn request_timeout(backend: Backend, _http2: bool) {
+    let server = TestServer::start(backend);
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    stream
+        .write_all(b"GET /timeout HTTP/1.1\r\nhost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        stream.read_to_end(&mut buf),
+    )
+    .await
+    .expect("stalled response was not cut off");
+    let buf = String::from_utf8_lossy(&buf);
+    assert!(buf.starts_with("HTTP/1.1 200"), "{buf}");
+    assert!(buf.contains("partial"), "{buf}");
+    assert!(!buf.ends_with("0\r\n\r\n"), "{buf}");
+}
+
+fn rate_limited() -> impl Service<Request, Response = Response, Error = Infallible> {
+    let limit = std::num::NonZeroU32::new(1).unwrap();
+    Router::new()
+        .route(
+            "/rate_limit",
+            service_fn(|_: Request| async { Ok::<_, Infallible>("allowed") }).with(
+                rate_limit(Quota::per_minute(limit))
+                    .key(RateLimitKey::header("x-api-key".parse().unwrap())),
+            ),
+        )
+        .with(handle_error(|err: BoxError| {
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }))
+}
+
+async fn rate_limiting(backend: Backend, http2: bool) {
+    let server = TestServer::serve(backend, false, rate_limited);
+    let limited = || {
+        let mut req = request(Method::GET, "/rate_limit", "");
+        req.headers_mut()
+            .insert("x-api-key", "key".parse().unwrap());
+        req
+    };
+
+    let res = send(&server, http2, limited()).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.body, "allowed");
+    assert_eq!(res.headers["ratelimit-remaining"], "0");
+    let res = send(&server, http2, limited()).await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.headers["retry-after"], "60");
+}
+
+async fn request_body_limit(backend: Backend, _http2: bool) {
+    // a chunked body declares no length, so the limit trips while streaming
+    let server = TestServer::start(backend);
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    stream
+        .write_all(
+            b"POST /body_limit HTTP/1.1\r\nhost: localhost\r\ntransfer-encoding: chunked\r\n\r\n\
+              a\r\n0123456789\r\na\r\n0123456789\r\n0\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut buf = [0; 256];
+    let n = stream.read(&mut buf).await.unwrap();
+    let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+    assert!(head.starts_with("http/1.1 413"), "{head}");
+    assert!(head.contains("x-streamed: true"), "{head}");
+}
+
+#[cfg(feature = "actix")]
+async fn actix_trailers(server: &TestServer, http2: bool) {
+    // actix-http neither parses request trailers nor writes response ones,
+    // so both fail loudly once they are declared or asked for
+    let req = hyper::Request::builder()
+        .method(Method::POST)
+        .uri("http://localhost/trailers")
+        .header("te", "trailers")
+        .header("trailer", "x-client")
+        .body(Full::new(Bytes::from_static(b"abcd")))
+        .unwrap();
+    let (res, conn) = send_raw(server, http2, req).await;
+    if http2 {
+        // the h2 dispatcher logs the body error but still ends the stream
+        let res = res.unwrap();
+        assert_eq!(res.headers()["x-trailers-error"], "true");
+        let body = res.into_body().collect().await.unwrap();
+        assert!(body.trailers().is_none());
+    } else {
+        // a chunked body is cut off before its last chunk
+        match res {
+            Ok(res) => assert!(res.into_body().collect().await.is_err()),
+            Err(err) => assert!(err.is_incomplete_message()),
+        }
+    }
+    conn.abort();
+    let _ = conn.await;
+
+    if !http2 {
+        let res = send(server, http2, request(Method::POST, "/trailers", "abcd")).await;
+        assert_eq!(res.body, "trailers follow");
+        assert!(res.trailers.is_none());
+        assert!(!res.headers.contains_key("x-trailers-error"));
+    }
+}
+
+async fn trailers(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    #[cfg(feature = "actix")]
+    if backend == Backend::Actix {
+        return actix_trailers(&server, http2).await;
+    }
+
+    let mut trailers = hyper::HeaderMap::new();
+    trailers.insert("x-client", "checksum".parse().unwrap());
+    let frames = [
+        Frame::data(Bytes::from_static(b"abc")),
+        Frame::trailers(trailers),
+    ];
+    let req = hyper::Request::builder()
+        .method(Method::POST)
+        .uri("http://localhost/trailers")
+        .header("te", "trailers")
+        .header("trailer", "x-client")
+        .body(http_body_util::StreamBody::new(futures_util::stream::iter(
+            frames.map(Ok::<_, Infallible>),
+        )))
+        .unwrap();
+    let res = send(&server, http2, req).await;
+    assert_eq!(res.body, "trailers follow");
+    let trailers = res.trailers.unwrap();
+    assert_eq!(trailers["x-length"], "3");
+    assert_eq!(trailers["x-client"], "checksum");
+
+    let res = send(&server, http2, request(Method::POST, "/trailers", "abcd")).await;
+    assert_eq!(res.body, "trailers follow");
+    assert_eq!(res.trailers.is_some(), http2);
+}
+
+const WS_KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+const WS_ACCEPT: &str = "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=";
+
+async fn ws_connect(server: &TestServer, headers: &str) -> (TcpStream, String) {
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    let request = format!(
+        "GET /ws HTTP/1.1\r\nhost: localhost\r\nupgrade: websocket\r\nconnection: upgrade\r\n\
+         sec-websocket-version: 13\r\nsec-websocket-key: {WS_KEY}\r\n{headers}\r\n"
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(stream.read_u8().await.unwrap());
+    }
+    (
+        stream,
+        String::from_utf8(head).unwrap().to_ascii_lowercase(),
+    )
+}
+
+async fn ws_write(stream: &mut TcpStream, b0: u8, payload: &[u8]) {
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let mut frame = vec![b0];
+    match payload.len() {
+        len @ 0..=125 => frame.push(0x80 | len as u8),
+        len => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    stream.write_all(&frame).await.unwrap();
+}
+
+async fn ws_read(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let b0 = stream.read_u8().await.unwrap();
+    let len = match stream.read_u8().await.unwrap() {
+        126 => stream.read_u16().await.unwrap() as usize,
+        127 => stream.read_u64().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).await.unwrap();
+    (b0, payload)
+}
+
+async fn websocket_echo(backend: Backend, http2: bool) {
+    let server = TestServer::start(backend);
+    if http2 {
+        let res = send(&server, true, request(Method::GET, "/ws", "")).await;
+        assert_eq!(res.status, StatusCode::BAD_REQUEST);
+        return;
+    }
+
+    let (mut stream, head) =
+        ws_connect(&server, "sec-websocket-protocol: superchat, chat\r\n").await;
+    assert!(head.starts_with("http/1.1 101"));
+    assert!(head.contains(&format!(
+        "sec-websocket-accept: {}",
+        WS_ACCEPT.to_ascii_lowercase()
+    )));
+    assert!(head.contains("sec-websocket-protocol: chat\r\n"));
+    assert!(!head.contains("sec-websocket-extensions"));
+
+    ws_write(&mut stream, 0x81, b"hello").await;
+    assert_eq!(ws_read(&mut stream).await, (0x81, b"hello".to_vec()));
+
+    ws_write(&mut stream, 0x02, b"ab").await;
+    ws_write(&mut stream, 0x89, b"ping").await;
+    ws_write(&mut stream, 0x80, b"cd").await;
+    assert_eq!(ws_read(&mut stream).await, (0x8a, b"ping".to_vec()));
+    assert_eq!(ws_read(&mut stream).await, (0x82, b"abcd".to_vec()));
+
+    ws_write(&mut stream, 0x88, &1000u16.to_be_bytes()).await;
+    assert_eq!(
+        ws_read(&mut stream).await,
+        (0x88, 1000u16.to_be_bytes().to_vec())
+    );
+
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+}
+
+async fn websocket_deflate(backend: Backend, http2: bool) {
+    use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+
+    if http2 {
+        return;
+    }
+    let server = TestServer::start(backend);
+    let (mut stream, head) = ws_connect(
+        &server,
+        "sec-websocket-extensions: permessage-deflate; client_max_window_bits\r\n",
+    )
+    .await;
+    assert!(head.contains("sec-websocket-extensions: permessage-deflate\r\n"));
+
+    let text = "compressed hello, compressed hello, compressed hello";
+    let mut compressed = Vec::with_capacity(256);
+    Compress::new(Compression::default(), false)
+        .compress_vec(text.as_bytes(), &mut compressed, FlushCompress::Sync)
+        .unwrap();
+    compressed.truncate(compressed.len() - 4);
+    ws_write(&mut stream, 0xc1, &compressed).await;
+
+    let (b0, payload) = ws_read(&mut stream).await;
+    assert_eq!(b0, 0xc1);
+    let mut input = payload;
+    input.extend_from_slice(&[0, 0, 0xff, 0xff]);
+    let mut output = Vec::with_capacity(256);
+    Decompress::new(false)
+        .decompress_vec(&input, &mut output, FlushDecompress::Sync)
+        .unwrap();
+    assert_eq!(output, text.as_bytes());
+}
+
+async fn websocket_message_limit(backend: Backend, http2: bool) {
+    if http2 {
+        return;
+    }
+    let server = TestServer::start(backend);
+    let (mut stream, _) = ws_connect(&server, "").await;
+
+    ws_write(&mut stream, 0x82, &[0; 2048]).await;
+    let (b0, payload) = ws_read(&mut stream).await;
+    assert_eq!(b0, 0x88);
+    assert_eq!(payload[..2], 1009u16.to_be_bytes());
+}
+
+macro_rules! conformance {
+    ($backend:ident, $feature:literal, [$($case:ident),* $(,)?]) => {
+        #[cfg(feature = $feature)]
+        #[allow(non_snake_case)]
+        mod $backend {
+            use super::*;
+
+            mod http1 {
+                use super::*;
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        super::super::$case(Backend::$backend, false).await
+                    }
+                )*
+            }
+
+            mod http2 {
+                use super::*;
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        super::super::$case(Backend::$backend, true).await
+                    }
+                )*
+            }
+        }
+    };
+}
+
+conformance!(
+    Actix,
+    "actix",
+    [
+        plain_text,
+        echo_body,
+        spawned_body,
+        status_code,
+        request_headers,
+        peer_addr,
+        streamed_body,
+        protocol_version,
+        connection_info,
+        proxy_protocol_v1,
+        proxy_protocol_v2,
+        proxy_protocol_malformed,
+        server_sent_events,
+        trailers,
+        middleware_from_fn,
+        response_compression,
+        request_decompression,
+        cors_middleware,
+        request_id_propagation,
+        access_logging,
+        tracing_spans,
+        trace_context_propagation,
+        request_timeout,
+        rate_limiting,
+        request_body_limit,
+        websocket_echo,
+        websocket_deflate,
+        websocket_message_limit,
+    ]
+);
+conformance!(
+    Hyper,
+    "hyper",
+    [
+        plain_text,
+        echo_body,
+        spawned_body,
+        status_code,
+        request_headers,
+        peer_addr,
+        streamed_body,
+        protocol_version,
+        connection_info,
+        proxy_protocol_v1,
+        proxy_protocol_v2,
+        proxy_protocol_malformed,
+        server_sent_events,
+        trailers,
+        middleware_from_fn,
+        response_compression,
+        request_decompression,
+        cors_middleware,
+        request_id_propagation,
+        access_logging,
+        tracing_spans,
+        trace_context_propagation,
+        request_timeout,
+        rate_limiting,
+        request_body_limit,
+        websocket_echo,
+        websocket_deflate,
+        websocket_message_limit,
+    ]
+);
@@ -4,6 +4,7 @@
 extern crate alloc;
 
 use core::future::Future;
+use core::task::{Context, Poll};
 
 #[macro_use]
 mod macros;
@@ -12,7 +13,7 @@ mod macros;
 pub mod util;
 
 #[cfg(feature = "util")]
-pub use util::ServiceExt;
+pub use util::{ServiceBuilder, ServiceExt};
 
 //pub use macros::*;
 
@@ -74,6 +75,71 @@ where
     }
 }
 
+#[cfg(feature="alloc")]
+impl<S, Request> Service<Request> for alloc::sync::Arc<S>
+where
+    S: Service<Request> + ?Sized,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+    fn call(&self, request: Request) -> Self::Future {
+        (**self).call(request)
+    }
+}
+
+pub trait ReadyService<Request>: Service<Request> {
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
+}
+
+impl<'a, S, Request> ReadyService<Request> for &'a S
+where
+    S: ReadyService<Request> + ?Sized + 'a,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(cx)
+    }
+}
+
+impl<'a, S, Request> ReadyService<Request> for &'a mut S
+where
+    S: ReadyService<Request> + ?Sized + 'a,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(cx)
+    }
+}
+
+#[cfg(feature="alloc")]
+impl<S, Request> ReadyService<Request> for alloc::boxed::Box<S>
+where
+    S: ReadyService<Request> + ?Sized,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(cx)
+    }
+}
+
+#[cfg(feature="alloc")]
+impl<S, Request> ReadyService<Request> for alloc::rc::Rc<S>
+where
+    S: ReadyService<Request> + ?Sized,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(cx)
+    }
+}
+
+#[cfg(feature="alloc")]
+impl<S, Request> ReadyService<Request> for alloc::sync::Arc<S>
+where
+    S: ReadyService<Request> + ?Sized,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(cx)
+    }
+}
+
 pub trait Wrap<S> {
     type Service;
     fn wrap(self, service: S) -> Self::Service;
@@ -1,5 +1,6 @@
-use crate::Service;
+use crate::{ReadyService, Service};
 use core::future::Future;
+use core::task::{Context, Poll};
 use futures_util::TryFutureExt;
 opaque_future! {
     pub type AndThenFuture<Fut1, Fut2, F> = futures_util::future::AndThen<Fut1, Fut2, F>;
@@ -32,6 +33,17 @@ where
     }
 }
 
+impl<S, F, Req, Res, Fut> ReadyService<Req> for AndThen<S, F>
+where
+    S: ReadyService<Req>,
+    F: FnOnce(S::Response) -> Fut + Clone,
+    Fut: Future<Output = Result<Res, S::Error>>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
 impl<S, F> core::fmt::Debug for AndThen<S, F>
 where
     S: core::fmt::Debug,
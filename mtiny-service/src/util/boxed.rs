@@ -5,17 +5,23 @@ use alloc::boxed::Box;
 
 use crate::{Service, ServiceExt};
 
-pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+pub type LocalBoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
 
 pub struct BoxService<Req, Res, Err> {
-    inner: Box<dyn Service<Req, Response = Res, Error = Err, Future = BoxFuture<Result<Res, Err>>>>,
+    inner: Box<
+        dyn Service<Req, Response = Res, Error = Err, Future = BoxFuture<Result<Res, Err>>>
+            + Send
+            + Sync,
+    >,
 }
 
 impl<Req, Res, Err> BoxService<Req, Res, Err> {
     pub fn new<S>(inner: S) -> Self
     where
-        S: Service<Req, Response = Res, Error = Err> + 'static,
-        S::Future: 'static,
+        S: Service<Req, Response = Res, Error = Err> + Send + Sync + 'static,
+        S::Future: Send + 'static,
     {
         Self {
             inner: Box::new(inner.map_future(|f| Box::pin(f) as _)),
@@ -37,3 +43,100 @@ impl<Req, Res, Err> core::fmt::Debug for BoxService<Req, Res, Err> {
         f.debug_struct("BoxService").finish()
     }
 }
+
+pub struct UnsyncBoxService<Req, Res, Err> {
+    inner: Box<
+        dyn Service<Req, Response = Res, Error = Err, Future = LocalBoxFuture<Result<Res, Err>>>,
+    >,
+}
+
+impl<Req, Res, Err> UnsyncBoxService<Req, Res, Err> {
+    pub fn new<S>(inner: S) -> Self
+    where
+        S: Service<Req, Response = Res, Error = Err> + 'static,
+        S::Future: 'static,
+    {
+        Self {
+            inner: Box::new(inner.map_future(|f| Box::pin(f) as _)),
+        }
+    }
+}
+
+impl<Req, Res, Err> Service<Req> for UnsyncBoxService<Req, Res, Err> {
+    type Response = Res;
+    type Error = Err;
+    type Future = LocalBoxFuture<Result<Res, Err>>;
+    fn call(&self, request: Req) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+impl<Req, Res, Err> core::fmt::Debug for UnsyncBoxService<Req, Res, Err> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnsyncBoxService").finish()
+    }
+}
+
+trait CloneService<Req>: Service<Req> {
+    fn clone_box(
+        &self,
+    ) -> Box<
+        dyn CloneService<Req, Response = Self::Response, Error = Self::Error, Future = Self::Future>
+            + Send,
+    >;
+}
+
+impl<S, Req> CloneService<Req> for S
+where
+    S: Service<Req> + Clone + Send + 'static,
+{
+    fn clone_box(
+        &self,
+    ) -> Box<
+        dyn CloneService<Req, Response = S::Response, Error = S::Error, Future = S::Future> + Send,
+    > {
+        Box::new(self.clone())
+    }
+}
+
+pub struct BoxCloneService<Req, Res, Err> {
+    inner: Box<
+        dyn CloneService<Req, Response = Res, Error = Err, Future = BoxFuture<Result<Res, Err>>>
+            + Send,
+    >,
+}
+
+impl<Req, Res, Err> BoxCloneService<Req, Res, Err> {
+    pub fn new<S>(inner: S) -> Self
+    where
+        S: Service<Req, Response = Res, Error = Err> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+    {
+        Self {
+            inner: Box::new(inner.map_future(|f| Box::pin(f) as _)),
+        }
+    }
+}
+
+impl<Req, Res, Err> Service<Req> for BoxCloneService<Req, Res, Err> {
+    type Response = Res;
+    type Error = Err;
+    type Future = BoxFuture<Result<Res, Err>>;
+    fn call(&self, request: Req) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+impl<Req, Res, Err> Clone for BoxCloneService<Req, Res, Err> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl<Req, Res, Err> core::fmt::Debug for BoxCloneService<Req, Res, Err> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BoxCloneService").finish()
+    }
+}
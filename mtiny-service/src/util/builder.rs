@@ -0,0 +1,158 @@
+use crate::Wrap;
+
+use super::Either;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Identity {
+    _priv: (),
+}
+
+impl Identity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Wrap<S> for Identity {
+    type Service = S;
+    fn wrap(self, service: S) -> Self::Service {
+        service
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<Inner, Outer> Stack<Inner, Outer> {
+    pub fn new(inner: Inner, outer: Outer) -> Self {
+        Self { inner, outer }
+    }
+}
+
+impl<S, Inner, Outer> Wrap<S> for Stack<Inner, Outer>
+where
+    Inner: Wrap<S>,
+    Outer: Wrap<Inner::Service>,
+{
+    type Service = Outer::Service;
+    fn wrap(self, service: S) -> Self::Service {
+        self.outer.wrap(self.inner.wrap(service))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ServiceBuilder<W> {
+    wrap: W,
+}
+
+impl ServiceBuilder<Identity> {
+    pub fn new() -> Self {
+        Self {
+            wrap: Identity::new(),
+        }
+    }
+}
+
+impl Default for ServiceBuilder<Identity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W> ServiceBuilder<W> {
+    pub fn wrap<T>(self, wrap: T) -> ServiceBuilder<Stack<T, W>> {
+        ServiceBuilder {
+            wrap: Stack::new(wrap, self.wrap),
+        }
+    }
+
+    pub fn option_wrap<T>(self, wrap: Option<T>) -> ServiceBuilder<Stack<Either<T, Identity>, W>> {
+        self.wrap(match wrap {
+            Some(wrap) => Either::Left(wrap),
+            None => Either::Right(Identity::new()),
+        })
+    }
+
+    pub fn service<S>(self, service: S) -> W::Service
+    where
+        W: Wrap<S>,
+    {
+        self.wrap.wrap(service)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.wrap
+    }
+}
+
+impl<S, W> Wrap<S> for ServiceBuilder<W>
+where
+    W: Wrap<S>,
+{
+    type Service = W::Service;
+    fn wrap(self, service: S) -> Self::Service {
+        self.wrap.wrap(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::util::service_fn;
+    use crate::Service;
+
+    #[derive(Clone, Copy)]
+    struct Tag(&'static str);
+
+    struct Tagged<S> {
+        inner: S,
+        tag: &'static str,
+    }
+
+    impl<S> Wrap<S> for Tag {
+        type Service = Tagged<S>;
+
+        fn wrap(self, inner: S) -> Self::Service {
+            Tagged { inner, tag: self.0 }
+        }
+    }
+
+    impl<S: Service<Vec<&'static str>>> Service<Vec<&'static str>> for Tagged<S> {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn call(&self, mut request: Vec<&'static str>) -> Self::Future {
+            request.push(self.tag);
+            self.inner.call(request)
+        }
+    }
+
+    #[test]
+    fn wraps_apply_in_order() {
+        let service = ServiceBuilder::new()
+            .wrap(Tag("a"))
+            .option_wrap(None::<Tag>)
+            .wrap(Tag("b"))
+            .option_wrap(Some(Tag("c")))
+            .service(service_fn(|request: Vec<&'static str>| async move {
+                Ok::<_, Infallible>(request)
+            }));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let fut = pin!(service.call(Vec::new()));
+        match fut.poll(&mut cx) {
+            Poll::Ready(Ok(order)) => assert_eq!(order, ["a", "b", "c"]),
+            _ => panic!("service did not complete"),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use crate::{ReadyService, Service, Wrap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B, Req> Service<Req> for Either<A, B>
+where
+    A: Service<Req>,
+    B: Service<Req, Response = A::Response, Error = A::Error>,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = EitherFuture<A::Future, B::Future>;
+
+    fn call(&self, request: Req) -> Self::Future {
+        match self {
+            Either::Left(service) => EitherFuture::Left {
+                future: service.call(request),
+            },
+            Either::Right(service) => EitherFuture::Right {
+                future: service.call(request),
+            },
+        }
+    }
+}
+
+impl<A, B, Req> ReadyService<Req> for Either<A, B>
+where
+    A: ReadyService<Req>,
+    B: ReadyService<Req, Response = A::Response, Error = A::Error>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Either::Left(service) => service.poll_ready(cx),
+            Either::Right(service) => service.poll_ready(cx),
+        }
+    }
+}
+
+impl<A, B, S> Wrap<S> for Either<A, B>
+where
+    A: Wrap<S>,
+    B: Wrap<S>,
+{
+    type Service = Either<A::Service, B::Service>;
+    fn wrap(self, service: S) -> Self::Service {
+        match self {
+            Either::Left(wrap) => Either::Left(wrap.wrap(service)),
+            Either::Right(wrap) => Either::Right(wrap.wrap(service)),
+        }
+    }
+}
+
+pin_project! {
+    #[project = EitherFutureProj]
+    pub enum EitherFuture<A, B> {
+        Left {
+            #[pin]
+            future: A,
+        },
+        Right {
+            #[pin]
+            future: B,
+        },
+    }
+}
+
+impl<A, B> Future for EitherFuture<A, B>
+where
+    A: Future,
+    B: Future<Output = A::Output>,
+{
+    type Output = A::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EitherFutureProj::Left { future } => future.poll(cx),
+            EitherFutureProj::Right { future } => future.poll(cx),
+        }
+    }
+}
+
+impl<A, B> core::fmt::Debug for EitherFuture<A, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EitherFuture::Left { .. } => f.debug_tuple("EitherFuture::Left").finish(),
+            EitherFuture::Right { .. } => f.debug_tuple("EitherFuture::Right").finish(),
+        }
+    }
+}
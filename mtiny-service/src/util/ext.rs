@@ -1,8 +1,9 @@
-use crate::{Service, Wrap};
+use crate::{ReadyService, Service, Wrap};
 
 use super::{
-    boxed::BoxService, map_err::MapErr, map_future::MapFuture, map_request::MapRequest,
-    map_response::MapResponse, map_result::MapResult, then::Then, and_then::AndThen,
+    boxed::{BoxCloneService, BoxService, UnsyncBoxService}, map_err::MapErr, map_future::MapFuture,
+    map_request::MapRequest, map_response::MapResponse, map_result::MapResult, then::Then,
+    and_then::AndThen, ready::{Ready, WaitReady},
 };
 pub trait ServiceExt<Req>: Service<Req> {
     fn map_request<F>(self, f: F) -> MapRequest<Self, F>
@@ -34,11 +35,47 @@ pub trait ServiceExt<Req>: Service<Req> {
     }
 
     fn boxed(self) -> BoxService<Req, Self::Response, Self::Error>
+    where
+        Self: Sized + Send + Sync + 'static,
+        Self::Future: Send + 'static,
+    {
+        BoxService::new(self)
+    }
+
+    fn boxed_unsync(self) -> UnsyncBoxService<Req, Self::Response, Self::Error>
     where
         Self: Sized + 'static,
         Self::Future: 'static,
     {
-        BoxService::new(self)
+        UnsyncBoxService::new(self)
+    }
+
+    fn boxed_clone(self) -> BoxCloneService<Req, Self::Response, Self::Error>
+    where
+        Self: Clone + Sized + Send + 'static,
+        Self::Future: Send + 'static,
+    {
+        BoxCloneService::new(self)
+    }
+    #[cfg(feature = "tower")]
+    fn into_tower(self) -> super::IntoTower<Self>
+    where
+        Self: Sized,
+    {
+        super::IntoTower::new(self)
+    }
+    fn ready(&self) -> Ready<'_, Self, Req>
+    where
+        Self: ReadyService<Req>,
+    {
+        Ready::new(self)
+    }
+
+    fn wait_ready(self) -> WaitReady<Self>
+    where
+        Self: Sized,
+    {
+        WaitReady::new(self)
     }
     fn with<T>(self, wrap: T) -> T::Service
     where
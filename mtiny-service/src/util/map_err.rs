@@ -1,6 +1,8 @@
+use core::task::{Context, Poll};
+
 use futures_util::TryFutureExt;
 
-use crate::Service;
+use crate::{ReadyService, Service};
 
 opaque_future! {
 pub type MapErrFuture<Fut,F> = futures_util::future::MapErr<Fut,F>;
@@ -31,6 +33,16 @@ where
     }
 }
 
+impl<S, F, Req, Err> ReadyService<Req> for MapErr<S, F>
+where
+    S: ReadyService<Req>,
+    F: FnOnce(S::Error) -> Err + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(self.f.clone())
+    }
+}
+
 impl<S, F> core::fmt::Debug for MapErr<S, F>
 where
     S: core::fmt::Debug,
@@ -1,6 +1,7 @@
 use core::future::Future;
+use core::task::{Context, Poll};
 
-use crate::Service;
+use crate::{ReadyService, Service};
 
 #[derive(Copy, Clone)]
 pub struct MapFuture<S, F> {
@@ -28,6 +29,18 @@ where
     }
 }
 
+impl<S, F, Req, Res, Err, Fut> ReadyService<Req> for MapFuture<S, F>
+where
+    S: ReadyService<Req>,
+    S::Error: Into<Err>,
+    F: Fn(S::Future) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+}
+
 impl<S, F> core::fmt::Debug for MapFuture<S, F>
 where
     S: core::fmt::Debug,
@@ -1,5 +1,8 @@
-use crate::Service;
+use core::task::{Context, Poll};
 
+use crate::{ReadyService, Service};
+
+#[derive(Clone, Copy)]
 pub struct MapRequest<S, F> {
     inner: S,
     f: F,
@@ -24,6 +27,16 @@ where
     }
 }
 
+impl<S, F, R1, R2> ReadyService<R1> for MapRequest<S, F>
+where
+    S: ReadyService<R2>,
+    F: Fn(R1) -> R2,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
 impl <S,F> core::fmt::Debug for MapRequest<S,F>
 where
 S:core::fmt::Debug
@@ -1,4 +1,6 @@
-use crate::Service;
+use core::task::{Context, Poll};
+
+use crate::{ReadyService, Service};
 use futures_util::TryFutureExt;
 
 opaque_future! {
@@ -29,6 +31,16 @@ where
     }
 }
 
+impl<S, F, Req, Res> ReadyService<Req> for MapResponse<S, F>
+where
+    S: ReadyService<Req>,
+    F: FnOnce(S::Response) -> Res + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
 impl<S, F> core::fmt::Debug for MapResponse<S, F>
 where
     S: core::fmt::Debug,
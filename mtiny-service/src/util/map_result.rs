@@ -1,6 +1,8 @@
+use core::task::{Context, Poll};
+
 use futures_util::FutureExt;
 
-use crate::Service;
+use crate::{ReadyService, Service};
 
 opaque_future! {
     pub type MapResultFuture<Fut,F> = futures_util::future::Map<Fut,F>;
@@ -31,6 +33,17 @@ where
     }
 }
 
+impl<S, F, Req, Res, Err> ReadyService<Req> for MapResult<S, F>
+where
+    S: ReadyService<Req>,
+    S::Error: Into<Err>,
+    F: FnOnce(Result<S::Response, S::Error>) -> Result<Res, Err> + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+}
+
 impl<S, F> core::fmt::Debug for MapResult<S, F>
 where
     S: core::fmt::Debug,
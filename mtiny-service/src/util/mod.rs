@@ -8,14 +8,24 @@ mod map_err;
 mod map_result;
 mod then;
 mod and_then;
+mod ready;
+mod either;
+mod builder;
+#[cfg(feature = "tower")]
+mod tower;
 
 pub use service_fn::{service_fn,ServiceFn};
 pub use ext::ServiceExt;
-pub use boxed::{BoxService,BoxFuture};
+pub use boxed::{BoxService,BoxCloneService,UnsyncBoxService,BoxFuture,LocalBoxFuture};
 pub use map_request::MapRequest;
 pub use map_response::{MapResponse,MapResponseFuture};
 pub use map_future::MapFuture;
 pub use map_err::{MapErr,MapErrFuture};
 pub use map_result::{MapResult,MapResultFuture};
 pub use then::{Then,ThenFuture};
-pub use and_then::{AndThen,AndThenFuture};
\ No newline at end of file
+pub use and_then::{AndThen,AndThenFuture};
+pub use ready::{Ready, WaitReady, WaitReadyFuture};
+pub use either::{Either, EitherFuture};
+pub use builder::{Identity, ServiceBuilder, Stack};
+#[cfg(feature = "tower")]
+pub use tower::{from_tower, layer, FromTower, FromTowerFuture, IntoTower, LayerWarp};
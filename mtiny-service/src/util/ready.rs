@@ -0,0 +1,213 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use crate::{ReadyService, Service};
+
+pub struct Ready<'a, S: ?Sized, Req> {
+    service: &'a S,
+    _req: PhantomData<fn(Req)>,
+}
+
+impl<'a, S: ?Sized, Req> Ready<'a, S, Req> {
+    pub(crate) fn new(service: &'a S) -> Self {
+        Self {
+            service,
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<'a, S, Req> Future for Ready<'a, S, Req>
+where
+    S: ReadyService<Req> + ?Sized,
+{
+    type Output = Result<&'a S, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        ready!(self.service.poll_ready(cx))?;
+        Poll::Ready(Ok(self.service))
+    }
+}
+
+impl<S: ?Sized, Req> core::fmt::Debug for Ready<'_, S, Req> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ready")
+            .field("service", &core::any::type_name::<S>())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct WaitReady<S> {
+    inner: S,
+}
+
+impl<S> WaitReady<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, Req> Service<Req> for WaitReady<S>
+where
+    S: ReadyService<Req> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = WaitReadyFuture<S, Req>;
+
+    fn call(&self, request: Req) -> Self::Future {
+        WaitReadyFuture {
+            state: State::NotReady {
+                service: self.inner.clone(),
+                request: Some(request),
+            },
+        }
+    }
+}
+
+impl<S, Req> ReadyService<Req> for WaitReady<S>
+where
+    S: ReadyService<Req> + Clone,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+}
+
+impl<S> core::fmt::Debug for WaitReady<S>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WaitReady")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+pin_project! {
+    pub struct WaitReadyFuture<S, Req>
+    where
+        S: Service<Req>,
+    {
+        #[pin]
+        state: State<S, Req, S::Future>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<S, Req, F> {
+        NotReady {
+            service: S,
+            request: Option<Req>,
+        },
+        Called {
+            #[pin]
+            future: F,
+        },
+    }
+}
+
+impl<S, Req> Future for WaitReadyFuture<S, Req>
+where
+    S: ReadyService<Req>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::NotReady { service, request } => {
+                    ready!(service.poll_ready(cx))?;
+                    let request = request.take().expect("polled after completion");
+                    let future = service.call(request);
+                    this.state.set(State::Called { future });
+                }
+                StateProj::Called { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+impl<S, Req> core::fmt::Debug for WaitReadyFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WaitReadyFuture")
+            .field("service", &core::any::type_name::<S>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::sync::Arc;
+    use core::convert::Infallible;
+    use core::future::{ready, Ready};
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use core::task::Waker;
+
+    use super::*;
+    use crate::ServiceExt;
+
+    #[derive(Default)]
+    struct Gate {
+        open: AtomicBool,
+    }
+
+    impl Service<u32> for Gate {
+        type Response = u32;
+        type Error = Infallible;
+        type Future = Ready<Result<u32, Infallible>>;
+
+        fn call(&self, request: u32) -> Self::Future {
+            ready(Ok(request))
+        }
+    }
+
+    impl ReadyService<u32> for Gate {
+        fn poll_ready(&self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.open.load(Ordering::Acquire) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn waits_for_readiness_before_calling() {
+        let gate = Arc::new(Gate::default());
+        let service = gate.clone().wait_ready();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut fut = core::pin::pin!(service.call(7));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+
+        gate.open.store(true, Ordering::Release);
+        assert!(matches!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(7))));
+    }
+
+    #[test]
+    fn boxed_services_wait_when_opted_in() {
+        let gate = Arc::new(Gate::default());
+        let service = gate.clone().wait_ready().boxed();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut fut = service.call(7);
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        gate.open.store(true, Ordering::Release);
+        assert!(matches!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(7))));
+    }
+}
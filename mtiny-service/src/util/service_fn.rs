@@ -1,6 +1,7 @@
+use core::task::{Context, Poll};
 use core::{fmt, future::Future};
 
-use crate::Service;
+use crate::{ReadyService, Service};
 
 #[derive(Clone, Copy)]
 pub struct ServiceFn<F> {
@@ -31,6 +32,16 @@ where
     }
 }
 
+impl<F, Req, Res, Err, Fut> ReadyService<Req> for ServiceFn<F>
+where
+    F: Fn(Req) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    fn poll_ready(&self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
 impl<F> fmt::Debug for ServiceFn<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ServiceFn")
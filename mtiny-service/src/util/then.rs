@@ -1,5 +1,6 @@
-use crate::Service;
+use crate::{ReadyService, Service};
 use core::future::Future;
+use core::task::{Context, Poll};
 use futures_util::FutureExt;
 opaque_future! {
     pub type ThenFuture<Fut1,Fut2,F> = futures_util::future::Then<Fut1,Fut2,F>;
@@ -30,6 +31,19 @@ where
         ThenFuture::new(self.inner.call(request).then(self.f.clone()))
     }
 }
+
+impl<S, F, Req, Res, Err, Fut> ReadyService<Req> for Then<S, F>
+where
+    S: ReadyService<Req>,
+    S::Error: Into<Err>,
+    F: FnOnce(Result<S::Response, S::Error>) -> Fut + Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+}
+
 impl<S, F> core::fmt::Debug for Then<S, F>
 where
     S: core::fmt::Debug,
@@ -0,0 +1,334 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use crate::{ReadyService, Service, Wrap};
+
+#[derive(Clone, Copy)]
+pub struct FromTower<S> {
+    inner: S,
+}
+
+impl<S> FromTower<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Req> Service<Req> for FromTower<S>
+where
+    S: tower_service::Service<Req> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FromTowerFuture<S, Req>;
+
+    fn call(&self, request: Req) -> Self::Future {
+        FromTowerFuture {
+            state: State::NotReady {
+                service: self.inner.clone(),
+                request: Some(request),
+            },
+        }
+    }
+}
+
+// the tower service is driven to readiness by each call, on its own clone
+impl<S, Req> ReadyService<Req> for FromTower<S>
+where
+    S: tower_service::Service<Req> + Clone,
+{
+    fn poll_ready(&self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> core::fmt::Debug for FromTower<S>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FromTower")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+pin_project! {
+    pub struct FromTowerFuture<S, Req>
+    where
+        S: tower_service::Service<Req>,
+    {
+        #[pin]
+        state: State<S, Req, S::Future>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<S, Req, F> {
+        NotReady {
+            service: S,
+            request: Option<Req>,
+        },
+        Called {
+            #[pin]
+            future: F,
+        },
+    }
+}
+
+impl<S, Req> Future for FromTowerFuture<S, Req>
+where
+    S: tower_service::Service<Req>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::NotReady { service, request } => {
+                    ready!(service.poll_ready(cx))?;
+                    let request = request.take().expect("polled after completion");
+                    let future = service.call(request);
+                    this.state.set(State::Called { future });
+                }
+                StateProj::Called { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+impl<S, Req> core::fmt::Debug for FromTowerFuture<S, Req>
+where
+    S: tower_service::Service<Req>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FromTowerFuture")
+            .field("service", &core::any::type_name::<S>())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct IntoTower<S> {
+    inner: S,
+}
+
+impl<S> IntoTower<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Req> tower_service::Service<Req> for IntoTower<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Req) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+impl<S> core::fmt::Debug for IntoTower<S>
+where
+    S: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IntoTower")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LayerWarp<L> {
+    layer: L,
+}
+
+impl<L> LayerWarp<L> {
+    pub fn new(layer: L) -> Self {
+        Self { layer }
+    }
+}
+
+impl<S, L> Wrap<S> for LayerWarp<L>
+where
+    L: tower_layer::Layer<IntoTower<S>>,
+{
+    type Service = FromTower<L::Service>;
+
+    fn wrap(self, service: S) -> Self::Service {
+        FromTower::new(self.layer.layer(IntoTower::new(service)))
+    }
+}
+
+impl<L> core::fmt::Debug for LayerWarp<L>
+where
+    L: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LayerWarp")
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+pub fn from_tower<S>(service: S) -> FromTower<S> {
+    FromTower::new(service)
+}
+
+pub fn layer<L>(layer: L) -> LayerWarp<L> {
+    LayerWarp::new(layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::convert::Infallible;
+    use core::future::{ready, Ready};
+    use core::pin::pin;
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use core::task::Waker;
+
+    use super::*;
+    use crate::util::service_fn;
+    use crate::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct Countdown(Arc<AtomicUsize>);
+
+    impl tower_service::Service<u32> for Countdown {
+        type Response = u32;
+        type Error = Infallible;
+        type Future = Ready<Result<u32, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            match self.0.load(Ordering::Acquire) {
+                0 => Poll::Ready(Ok(())),
+                n => {
+                    self.0.store(n - 1, Ordering::Release);
+                    Poll::Pending
+                }
+            }
+        }
+
+        fn call(&mut self, request: u32) -> Self::Future {
+            ready(Ok(request))
+        }
+    }
+
+    #[derive(Default)]
+    struct Gate(AtomicBool);
+
+    impl Service<u32> for Gate {
+        type Response = u32;
+        type Error = Infallible;
+        type Future = Ready<Result<u32, Infallible>>;
+
+        fn call(&self, request: u32) -> Self::Future {
+            ready(Ok(request))
+        }
+    }
+
+    impl ReadyService<u32> for Gate {
+        fn poll_ready(&self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            if self.0.load(Ordering::Acquire) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct DoubleLayer;
+
+    #[derive(Clone)]
+    struct Double<S>(S);
+
+    impl<S> tower_layer::Layer<S> for DoubleLayer {
+        type Service = Double<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            Double(inner)
+        }
+    }
+
+    impl<S: tower_service::Service<u32>> tower_service::Service<u32> for Double<S> {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, request: u32) -> Self::Future {
+            self.0.call(request * 2)
+        }
+    }
+
+    #[test]
+    fn from_tower_waits_for_readiness() {
+        let countdown = Countdown::default();
+        countdown.0.store(2, Ordering::Release);
+        let service = from_tower(countdown.clone());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut fut = pin!(service.call(5));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        assert!(matches!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(5))));
+    }
+
+    #[test]
+    fn into_tower_waits_when_opted_in() {
+        let gate = Arc::new(Gate::default());
+        let mut service = gate.clone().wait_ready().into_tower();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        assert!(tower_service::Service::poll_ready(&mut service, &mut cx).is_ready());
+        let mut fut = pin!(tower_service::Service::call(&mut service, 5));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        gate.0.store(true, Ordering::Release);
+        assert!(matches!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(5))));
+    }
+
+    #[test]
+    fn layer_wraps_service() {
+        let service = service_fn(|request: u32| ready(Ok::<_, Infallible>(request + 1)))
+            .with(layer(DoubleLayer));
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let fut = pin!(service.call(3));
+        assert!(matches!(fut.poll(&mut cx), Poll::Ready(Ok(7))));
+    }
+}
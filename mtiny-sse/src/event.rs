@@ -0,0 +1,138 @@
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+impl Event {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    // both may come from request input, so newlines are stripped rather than
+    // rejected; they would otherwise start a new field
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        let mut event = event.into();
+        event.retain(|c| !matches!(c, '\r' | '\n'));
+        self.event = Some(event);
+        self
+    }
+
+    // clients ignore an id containing a null character, so it goes as well
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let mut id = id.into();
+        id.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+        self.id = Some(id);
+        self
+    }
+
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut buf = String::new();
+        if let Some(comment) = &self.comment {
+            field(&mut buf, "", comment);
+        }
+        if let Some(event) = &self.event {
+            field(&mut buf, "event", event);
+        }
+        if let Some(data) = &self.data {
+            field(&mut buf, "data", data);
+        }
+        if let Some(id) = &self.id {
+            field(&mut buf, "id", id);
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(buf, "retry: {}", retry.as_millis());
+        }
+        buf.push('\n');
+        Bytes::from(buf)
+    }
+}
+
+pub(crate) fn comment(text: &str) -> Bytes {
+    let mut buf = String::new();
+    field(&mut buf, "", text);
+    buf.push('\n');
+    Bytes::from(buf)
+}
+
+fn field(buf: &mut String, name: &str, value: &str) {
+    for line in value.replace("\r\n", "\n").split(['\n', '\r']) {
+        buf.push_str(name);
+        buf.push(':');
+        if !line.is_empty() || name.is_empty() {
+            buf.push(' ');
+        }
+        buf.push_str(line);
+        buf.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_fields() {
+        let event = Event::new()
+            .comment("hello")
+            .event("update")
+            .data("payload")
+            .id("42")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            event.encode(),
+            ": hello\nevent: update\ndata: payload\nid: 42\nretry: 3000\n\n"
+        );
+    }
+
+    #[test]
+    fn encode_multiline_data() {
+        let event = Event::new().data("one\ntwo\r\nthree\rfour");
+        assert_eq!(
+            event.encode(),
+            "data: one\ndata: two\ndata: three\ndata: four\n\n"
+        );
+    }
+
+    #[test]
+    fn encode_empty_lines() {
+        assert_eq!(Event::new().data("").encode(), "data:\n\n");
+        assert_eq!(Event::new().data("a\n").encode(), "data: a\ndata:\n\n");
+        assert_eq!(Event::new().encode(), "\n");
+    }
+
+    #[test]
+    fn strips_newlines_from_event_and_id() {
+        let event = Event::new().event("up\r\ndata: injected").id("1\n2\0");
+        assert_eq!(event.encode(), "event: updata: injected\nid: 12\n\n");
+    }
+
+    #[test]
+    fn comment_lines() {
+        assert_eq!(comment(""), ": \n\n");
+        assert_eq!(comment("a\nb"), ": a\n: b\n\n");
+    }
+}
@@ -0,0 +1,5 @@
+mod event;
+mod sse;
+
+pub use event::Event;
+pub use sse::{last_event_id, KeepAlive, Sse, SseBody, LAST_EVENT_ID};
@@ -0,0 +1,261 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_core::TryStream;
+use pin_project_lite::pin_project;
+use tokio::time::{Instant, Sleep};
+
+use mtiny_core::body::{Body, BodyExt};
+use mtiny_core::http::{header, HeaderName, HeaderValue};
+use mtiny_core::response::{IntoResponse, Response};
+use mtiny_core::{BoxError, Request};
+
+use crate::event::{self, Event};
+
+pub const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+pub fn last_event_id<B>(request: &Request<B>) -> Option<&str> {
+    request
+        .headers()
+        .get(LAST_EVENT_ID)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[derive(Debug, Clone)]
+pub struct KeepAlive {
+    interval: Duration,
+    text: Bytes,
+}
+
+impl KeepAlive {
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(15),
+            text: event::comment(""),
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn text(mut self, text: impl AsRef<str>) -> Self {
+        self.text = event::comment(text.as_ref());
+        self
+    }
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sse<S> {
+    stream: S,
+    keep_alive: Option<KeepAlive>,
+}
+
+impl<S> Sse<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            keep_alive: None,
+        }
+    }
+
+    pub fn keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    pub fn into_body(self) -> SseBody<S> {
+        SseBody {
+            stream: self.stream,
+            keep_alive: self.keep_alive.map(|keep_alive| KeepAliveTimer {
+                sleep: Box::pin(tokio::time::sleep(keep_alive.interval)),
+                keep_alive,
+            }),
+        }
+    }
+}
+
+impl<S> fmt::Debug for Sse<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sse")
+            .field("stream", &core::any::type_name::<S>())
+            .field("keep_alive", &self.keep_alive)
+            .finish()
+    }
+}
+
+impl<S> IntoResponse for Sse<S>
+where
+    S: TryStream<Ok = Event> + Send + 'static,
+    S::Error: Into<BoxError>,
+{
+    fn into_response(self) -> Response {
+        let mut res = Response::new(self.into_body().boxed());
+        let headers = res.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(mime::TEXT_EVENT_STREAM.as_ref()),
+        );
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        headers.insert(
+            HeaderName::from_static("x-accel-buffering"),
+            HeaderValue::from_static("no"),
+        );
+        res
+    }
+}
+
+struct KeepAliveTimer {
+    keep_alive: KeepAlive,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl KeepAliveTimer {
+    fn reset(&mut self) {
+        self.sleep
+            .as_mut()
+            .reset(Instant::now() + self.keep_alive.interval);
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Bytes> {
+        ready!(self.sleep.as_mut().poll(cx));
+        self.reset();
+        Poll::Ready(self.keep_alive.text.clone())
+    }
+}
+
+pin_project! {
+    pub struct SseBody<S> {
+        #[pin]
+        stream: S,
+        keep_alive: Option<KeepAliveTimer>,
+    }
+}
+
+impl<S> Body for SseBody<S>
+where
+    S: TryStream<Ok = Event>,
+    S::Error: Into<BoxError>,
+{
+    type Error = BoxError;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.project();
+        match this.stream.try_poll_next(cx) {
+            Poll::Ready(Some(Ok(event))) => {
+                if let Some(keep_alive) = this.keep_alive {
+                    keep_alive.reset();
+                }
+                Poll::Ready(Some(Ok(event.encode())))
+            }
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => match this.keep_alive {
+                Some(keep_alive) => keep_alive.poll(cx).map(|text| Some(Ok(text))),
+                None => Poll::Pending,
+            },
+        }
+    }
+}
+
+impl<S> fmt::Debug for SseBody<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseBody")
+            .field("stream", &core::any::type_name::<S>())
+            .field(
+                "keep_alive",
+                &self.keep_alive.as_ref().map(|timer| &timer.keep_alive),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+
+    use futures_core::Stream;
+
+    use super::*;
+
+    // yields its events, then stays pending forever
+    struct Events(VecDeque<Event>);
+
+    impl Stream for Events {
+        type Item = Result<Event, Infallible>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            match self.0.pop_front() {
+                Some(event) => Poll::Ready(Some(Ok(event))),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    fn body(events: Vec<Event>, keep_alive: KeepAlive) -> SseBody<Events> {
+        Sse::new(Events(events.into()))
+            .keep_alive(keep_alive)
+            .into_body()
+    }
+
+    async fn next(body: &mut SseBody<Events>) -> Bytes {
+        BodyExt::next(&mut Box::pin(body)).await.unwrap().unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keep_alive_after_interval() {
+        let keep_alive = KeepAlive::new().interval(Duration::from_secs(5));
+        let mut body = body(vec![Event::new().data("a")], keep_alive);
+
+        let start = Instant::now();
+        assert_eq!(next(&mut body).await, "data: a\n\n");
+        assert_eq!(next(&mut body).await, ": \n\n");
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+        assert_eq!(next(&mut body).await, ": \n\n");
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keep_alive_custom_text() {
+        let keep_alive = KeepAlive::new().text("ping");
+        let mut body = body(Vec::new(), keep_alive);
+
+        let start = Instant::now();
+        assert_eq!(next(&mut body).await, ": ping\n\n");
+        assert_eq!(start.elapsed(), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn response_headers() {
+        let response = Sse::new(Events(VecDeque::new())).into_response();
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_TYPE], "text/event-stream");
+        assert_eq!(headers[header::CACHE_CONTROL], "no-cache");
+        assert_eq!(headers["x-accel-buffering"], "no");
+    }
+
+    #[test]
+    fn last_event_id_header() {
+        let mut request = Request::new(());
+        assert_eq!(last_event_id(&request), None);
+        request
+            .headers_mut()
+            .insert(LAST_EVENT_ID, HeaderValue::from_static("7"));
+        assert_eq!(last_event_id(&request), Some("7"));
+    }
+}
@@ -0,0 +1,287 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::error::WebSocketError;
+
+const EXTENSION: &str = "permessage-deflate";
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+const MAX_WINDOW_BITS: u8 = 15;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: bool,
+}
+
+impl DeflateConfig {
+    pub(crate) fn negotiate(offers: &str) -> Option<Self> {
+        offers.split(',').find_map(parse_offer)
+    }
+
+    pub(crate) fn response(&self) -> String {
+        let mut value = EXTENSION.to_owned();
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+        if self.server_max_window_bits {
+            value.push_str("; server_max_window_bits=15");
+        }
+        value
+    }
+}
+
+fn parse_offer(offer: &str) -> Option<DeflateConfig> {
+    let mut params = offer.split(';').map(str::trim);
+    if params.next() != Some(EXTENSION) {
+        return None;
+    }
+
+    let mut config = DeflateConfig::default();
+    let mut client_max_window_bits = false;
+    for param in params {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        let seen = match (name, value) {
+            ("server_no_context_takeover", None) => {
+                std::mem::replace(&mut config.server_no_context_takeover, true)
+            }
+            ("client_no_context_takeover", None) => {
+                std::mem::replace(&mut config.client_no_context_takeover, true)
+            }
+            ("server_max_window_bits", Some(bits)) if window_bits(bits)? == MAX_WINDOW_BITS => {
+                std::mem::replace(&mut config.server_max_window_bits, true)
+            }
+            ("client_max_window_bits", None) => {
+                std::mem::replace(&mut client_max_window_bits, true)
+            }
+            ("client_max_window_bits", Some(bits)) => {
+                window_bits(bits)?;
+                std::mem::replace(&mut client_max_window_bits, true)
+            }
+            _ => return None,
+        };
+        if seen {
+            return None;
+        }
+    }
+    Some(config)
+}
+
+fn window_bits(value: &str) -> Option<u8> {
+    match value.parse() {
+        Ok(bits @ 8..=15) => Some(bits),
+        _ => None,
+    }
+}
+
+pub(crate) struct Deflate {
+    config: DeflateConfig,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl Deflate {
+    pub(crate) fn new(config: DeflateConfig) -> Self {
+        Self {
+            config,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    pub(crate) fn compress(&mut self, data: &[u8]) -> Result<Vec<u8>, WebSocketError> {
+        let start = self.compress.total_in();
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|e| WebSocketError::Compression(e.into()))?;
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+            out.reserve(out.capacity().max(64));
+        }
+
+        if out.ends_with(&TRAILER) {
+            out.truncate(out.len() - TRAILER.len());
+        }
+        if self.config.server_no_context_takeover {
+            self.compress.reset();
+        }
+        Ok(out)
+    }
+
+    pub(crate) fn decompress(
+        &mut self,
+        data: &[u8],
+        limit: usize,
+    ) -> Result<Vec<u8>, WebSocketError> {
+        let mut input = Vec::with_capacity(data.len() + TRAILER.len());
+        input.extend_from_slice(data);
+        input.extend_from_slice(&TRAILER);
+
+        let start = self.decompress.total_in();
+        let mut out = Vec::with_capacity((data.len() * 2).max(64).min(limit.saturating_add(1)));
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let produced = out.len();
+            let status = self
+                .decompress
+                .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|e| WebSocketError::Compression(e.into()))?;
+            if out.len() > limit {
+                return Err(WebSocketError::MessageTooLarge);
+            }
+
+            let done = (self.decompress.total_in() - start) as usize == input.len();
+            if status == Status::StreamEnd || (done && out.len() < out.capacity()) {
+                break;
+            }
+            if out.len() == out.capacity() {
+                out.reserve(out.capacity().min(limit + 1 - out.len()));
+            } else if out.len() == produced
+                && (self.decompress.total_in() - start) as usize == consumed
+            {
+                return Err(WebSocketError::Compression(std::io::Error::other(
+                    "truncated deflate stream",
+                )));
+            }
+        }
+
+        if self.config.client_no_context_takeover {
+            self.decompress.reset(false);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what a client without context takeover sends, one raw stream per message
+    fn client_message(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + 64);
+        Compress::new(Compression::default(), false)
+            .compress_vec(data, &mut out, FlushCompress::Sync)
+            .unwrap();
+        assert!(out.ends_with(&TRAILER));
+        out.truncate(out.len() - TRAILER.len());
+        out
+    }
+
+    #[test]
+    fn negotiates_the_first_acceptable_offer() {
+        let config = DeflateConfig::negotiate(
+            "x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=10, \
+             permessage-deflate; client_max_window_bits; server_no_context_takeover",
+        )
+        .unwrap();
+        assert_eq!(
+            config.response(),
+            "permessage-deflate; server_no_context_takeover"
+        );
+
+        let config =
+            DeflateConfig::negotiate("permessage-deflate; server_max_window_bits=\"15\"").unwrap();
+        assert_eq!(
+            config.response(),
+            "permessage-deflate; server_max_window_bits=15"
+        );
+
+        assert!(DeflateConfig::negotiate("permessage-deflate; unknown").is_none());
+        assert!(DeflateConfig::negotiate(
+            "permessage-deflate; client_no_context_takeover; client_no_context_takeover"
+        )
+        .is_none());
+        assert!(DeflateConfig::negotiate("permessage-deflate; client_max_window_bits=7").is_none());
+    }
+
+    #[test]
+    fn round_trips_both_directions() {
+        let text = b"compressed hello, compressed hello, compressed hello";
+        let mut deflate = Deflate::new(DeflateConfig::default());
+
+        let compressed = deflate.compress(text).unwrap();
+        assert!(compressed.len() < text.len());
+        let mut input = compressed;
+        input.extend_from_slice(&TRAILER);
+        let mut out = Vec::with_capacity(256);
+        Decompress::new(false)
+            .decompress_vec(&input, &mut out, FlushDecompress::Sync)
+            .unwrap();
+        assert_eq!(out, text);
+
+        let decompressed = deflate
+            .decompress(&client_message(text), text.len())
+            .unwrap();
+        assert_eq!(decompressed, text);
+    }
+
+    #[test]
+    fn keeps_the_window_between_messages_unless_asked_not_to() {
+        let text = b"the same message, the same message, the same message";
+
+        let mut takeover = Deflate::new(DeflateConfig::default());
+        let first = takeover.compress(text).unwrap();
+        let second = takeover.compress(text).unwrap();
+        assert!(second.len() < first.len());
+
+        let mut reset = Deflate::new(DeflateConfig {
+            server_no_context_takeover: true,
+            ..DeflateConfig::default()
+        });
+        assert_eq!(reset.compress(text).unwrap(), first);
+        assert_eq!(reset.compress(text).unwrap(), first);
+    }
+
+    #[test]
+    fn client_messages_may_reference_earlier_ones() {
+        let text = b"repeated payload, repeated payload";
+        let mut client = Compress::new(Compression::default(), false);
+        let mut messages = Vec::new();
+        for _ in 0..2 {
+            let mut out = Vec::with_capacity(256);
+            client
+                .compress_vec(text, &mut out, FlushCompress::Sync)
+                .unwrap();
+            out.truncate(out.len() - TRAILER.len());
+            messages.push(out);
+        }
+
+        let mut deflate = Deflate::new(DeflateConfig::default());
+        for message in &messages {
+            assert_eq!(deflate.decompress(message, 1024).unwrap(), text);
+        }
+
+        // without takeover every message stands alone
+        let mut reset = Deflate::new(DeflateConfig {
+            client_no_context_takeover: true,
+            ..DeflateConfig::default()
+        });
+        assert_eq!(reset.decompress(&messages[0], 1024).unwrap(), text);
+        assert_eq!(reset.decompress(&client_message(text), 1024).unwrap(), text);
+    }
+
+    #[test]
+    fn limits_and_rejects_broken_input() {
+        let mut deflate = Deflate::new(DeflateConfig::default());
+        assert!(matches!(
+            deflate.decompress(&client_message(&[0; 4096]), 1024),
+            Err(WebSocketError::MessageTooLarge)
+        ));
+
+        let mut deflate = Deflate::new(DeflateConfig::default());
+        assert!(matches!(
+            deflate.decompress(&[0xff, 0xff, 0xff], 1024),
+            Err(WebSocketError::Compression(_))
+        ));
+    }
+}
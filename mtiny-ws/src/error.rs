@@ -0,0 +1,116 @@
+use std::fmt;
+use std::io;
+
+use mtiny_core::http::{header, HeaderValue, StatusCode};
+use mtiny_core::response::{IntoResponse, Response};
+
+use crate::message::close_code;
+
+#[derive(Debug)]
+pub enum WebSocketError {
+    Io(io::Error),
+    Protocol(&'static str),
+    InvalidUtf8,
+    FrameTooLarge,
+    MessageTooLarge,
+    Compression(io::Error),
+    ConnectionClosed,
+}
+
+impl WebSocketError {
+    pub(crate) fn close_code(&self) -> Option<u16> {
+        match self {
+            WebSocketError::Protocol(_) => Some(close_code::PROTOCOL),
+            WebSocketError::InvalidUtf8 | WebSocketError::Compression(_) => {
+                Some(close_code::INVALID)
+            }
+            WebSocketError::FrameTooLarge | WebSocketError::MessageTooLarge => {
+                Some(close_code::SIZE)
+            }
+            WebSocketError::Io(_) | WebSocketError::ConnectionClosed => None,
+        }
+    }
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebSocketError::Io(e) => write!(f, "websocket io error ({})", e),
+            WebSocketError::Protocol(reason) => write!(f, "websocket protocol error ({})", reason),
+            WebSocketError::InvalidUtf8 => f.write_str("websocket text is not valid utf-8"),
+            WebSocketError::FrameTooLarge => f.write_str("websocket frame too large"),
+            WebSocketError::MessageTooLarge => f.write_str("websocket message too large"),
+            WebSocketError::Compression(e) => write!(f, "websocket compression error ({})", e),
+            WebSocketError::ConnectionClosed => f.write_str("websocket connection closed"),
+        }
+    }
+}
+
+impl std::error::Error for WebSocketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WebSocketError::Io(e) | WebSocketError::Compression(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WebSocketError {
+    fn from(e: io::Error) -> Self {
+        WebSocketError::Io(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum WebSocketUpgradeError {
+    MethodNotGet,
+    MissingUpgrade,
+    MissingConnectionUpgrade,
+    UnsupportedVersion,
+    InvalidKey,
+    NotUpgradable,
+}
+
+impl fmt::Display for WebSocketUpgradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebSocketUpgradeError::MethodNotGet => {
+                f.write_str("websocket handshake requires the GET method")
+            }
+            WebSocketUpgradeError::MissingUpgrade => {
+                f.write_str("missing `upgrade: websocket` header")
+            }
+            WebSocketUpgradeError::MissingConnectionUpgrade => {
+                f.write_str("missing `connection: upgrade` header")
+            }
+            WebSocketUpgradeError::UnsupportedVersion => {
+                f.write_str("unsupported `sec-websocket-version`")
+            }
+            WebSocketUpgradeError::InvalidKey => {
+                f.write_str("missing or invalid `sec-websocket-key` header")
+            }
+            WebSocketUpgradeError::NotUpgradable => f.write_str("connection is not upgradable"),
+        }
+    }
+}
+
+impl std::error::Error for WebSocketUpgradeError {}
+
+impl IntoResponse for WebSocketUpgradeError {
+    fn into_response(self) -> Response {
+        let mut response = self.to_string().into_response();
+        *response.status_mut() = match self {
+            WebSocketUpgradeError::MethodNotGet => StatusCode::METHOD_NOT_ALLOWED,
+            WebSocketUpgradeError::UnsupportedVersion => {
+                response.headers_mut().insert(
+                    header::SEC_WEBSOCKET_VERSION,
+                    HeaderValue::from_static("13"),
+                );
+                StatusCode::UPGRADE_REQUIRED
+            }
+            WebSocketUpgradeError::NotUpgradable => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        response
+    }
+}
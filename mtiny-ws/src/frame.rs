@@ -0,0 +1,186 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::error::WebSocketError;
+
+pub(crate) const OP_CONTINUE: u8 = 0x0;
+pub(crate) const OP_TEXT: u8 = 0x1;
+pub(crate) const OP_BINARY: u8 = 0x2;
+pub(crate) const OP_CLOSE: u8 = 0x8;
+pub(crate) const OP_PING: u8 = 0x9;
+pub(crate) const OP_PONG: u8 = 0xa;
+
+pub(crate) const MAX_CONTROL_PAYLOAD: usize = 125;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Header {
+    pub(crate) fin: bool,
+    pub(crate) rsv1: bool,
+    pub(crate) opcode: u8,
+    pub(crate) mask: Option<[u8; 4]>,
+    pub(crate) len: u64,
+}
+
+impl Header {
+    pub(crate) fn is_control(&self) -> bool {
+        self.opcode & 0x8 != 0
+    }
+}
+
+pub(crate) fn parse_header(buf: &[u8]) -> Result<Option<(Header, usize)>, WebSocketError> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let (b0, b1) = (buf[0], buf[1]);
+    if b0 & 0x30 != 0 {
+        return Err(WebSocketError::Protocol("reserved bits set"));
+    }
+
+    let mut at = 2;
+    let len = match b1 & 0x7f {
+        126 => {
+            let Some(ext) = buf.get(at..at + 2) else {
+                return Ok(None);
+            };
+            at += 2;
+            u16::from_be_bytes([ext[0], ext[1]]) as u64
+        }
+        127 => {
+            let Some(ext) = buf.get(at..at + 8) else {
+                return Ok(None);
+            };
+            at += 8;
+            let len = u64::from_be_bytes(ext.try_into().unwrap());
+            if len >> 63 != 0 {
+                return Err(WebSocketError::Protocol("invalid payload length"));
+            }
+            len
+        }
+        len => len as u64,
+    };
+
+    let mask = if b1 & 0x80 != 0 {
+        let Some(key) = buf.get(at..at + 4) else {
+            return Ok(None);
+        };
+        at += 4;
+        Some([key[0], key[1], key[2], key[3]])
+    } else {
+        None
+    };
+
+    let header = Header {
+        fin: b0 & 0x80 != 0,
+        rsv1: b0 & 0x40 != 0,
+        opcode: b0 & 0x0f,
+        mask,
+        len,
+    };
+    Ok(Some((header, at)))
+}
+
+pub(crate) fn apply_mask(buf: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte ^= mask[i & 3];
+    }
+}
+
+pub(crate) fn write_frame(dst: &mut BytesMut, opcode: u8, rsv1: bool, payload: &[u8]) {
+    dst.reserve(payload.len() + 10);
+    dst.put_u8(0x80 | if rsv1 { 0x40 } else { 0 } | opcode);
+    match payload.len() {
+        len @ 0..=125 => dst.put_u8(len as u8),
+        len @ 126..=0xffff => {
+            dst.put_u8(126);
+            dst.put_u16(len as u16);
+        }
+        len => {
+            dst.put_u8(127);
+            dst.put_u64(len as u64);
+        }
+    }
+    dst.put_slice(payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(buf: &[u8]) -> (Header, usize) {
+        parse_header(buf).unwrap().unwrap()
+    }
+
+    #[test]
+    fn masks_like_the_rfc_example() {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut data = *b"Hello";
+        apply_mask(&mut data, mask);
+        assert_eq!(data, [0x7f, 0x9f, 0x4d, 0x51, 0x58]);
+        apply_mask(&mut data, mask);
+        assert_eq!(&data, b"Hello");
+
+        let (header, len) = header(&[0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d]);
+        assert_eq!(len, 6);
+        assert_eq!(header.mask, Some(mask));
+        assert_eq!(header.len, 5);
+    }
+
+    #[test]
+    fn parses_every_length_form() {
+        let (short, len) = header(&[0x81, 0x05]);
+        assert_eq!((short.len, len), (5, 2));
+        assert!(short.fin && !short.rsv1 && short.mask.is_none());
+        assert_eq!(short.opcode, OP_TEXT);
+
+        let (medium, len) = header(&[0x82, 0x7e, 0x01, 0x00]);
+        assert_eq!((medium.len, len), (256, 4));
+
+        let (long, len) = header(&[0x02, 0x7f, 0, 0, 0, 0, 0, 0x01, 0, 0]);
+        assert_eq!((long.len, len), (65536, 10));
+        assert!(!long.fin);
+    }
+
+    #[test]
+    fn waits_for_the_whole_header() {
+        let full = [0x82, 0xfe, 0x01, 0x00, 1, 2, 3, 4];
+        for end in 0..full.len() {
+            assert!(parse_header(&full[..end]).unwrap().is_none(), "{end}");
+        }
+        assert_eq!(header(&full).1, full.len());
+    }
+
+    #[test]
+    fn rejects_reserved_bits_and_huge_lengths() {
+        assert!(parse_header(&[0xa1, 0x00]).is_err());
+        assert!(parse_header(&[0x91, 0x00]).is_err());
+        assert!(parse_header(&[0xc1, 0x00]).unwrap().unwrap().0.rsv1);
+
+        let huge = [0x82, 0x7f, 0x80, 0, 0, 0, 0, 0, 0, 0];
+        assert!(parse_header(&huge).is_err());
+    }
+
+    #[test]
+    fn writes_the_shortest_length_form() {
+        for (payload, header_len) in [(0, 2), (125, 2), (126, 4), (0xffff, 4), (0x10000, 10)] {
+            let mut buf = BytesMut::new();
+            write_frame(&mut buf, OP_BINARY, payload == 126, &vec![7; payload]);
+            let (header, len) = header(&buf);
+            assert_eq!(len, header_len, "{payload}");
+            assert_eq!(header.len, payload as u64);
+            assert_eq!(header.rsv1, payload == 126);
+            assert!(header.fin && header.mask.is_none());
+            assert_eq!(buf.len(), header_len + payload);
+        }
+    }
+
+    #[test]
+    fn control_opcodes() {
+        for opcode in [OP_CLOSE, OP_PING, OP_PONG] {
+            let (header, _) = header(&[0x80 | opcode, 0x7d]);
+            assert!(header.is_control());
+            assert_eq!(header.len, MAX_CONTROL_PAYLOAD as u64);
+        }
+        for opcode in [OP_CONTINUE, OP_TEXT, OP_BINARY] {
+            assert!(!header(&[0x80 | opcode, 0x00]).0.is_control());
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod deflate;
+mod error;
+mod frame;
+mod message;
+mod socket;
+mod upgrade;
+
+pub use error::{WebSocketError, WebSocketUpgradeError};
+pub use message::{close_code, CloseFrame, Message};
+pub use socket::WebSocket;
+pub use upgrade::WebSocketUpgrade;
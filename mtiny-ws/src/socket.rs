@@ -0,0 +1,624 @@
+use std::fmt;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf, Bytes, BytesMut};
+use futures_core::Stream;
+use futures_sink::Sink;
+use mtiny_core::http::upgrade::Upgraded;
+
+use crate::deflate::Deflate;
+use crate::error::WebSocketError;
+use crate::frame::{self, Header};
+use crate::message::{close_code, CloseFrame, Message};
+
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Config {
+    pub(crate) max_frame_size: usize,
+    pub(crate) max_message_size: usize,
+    pub(crate) compression: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_frame_size: 16 << 20,
+            max_message_size: 64 << 20,
+            compression: false,
+        }
+    }
+}
+
+struct Partial {
+    opcode: u8,
+    compressed: bool,
+    data: BytesMut,
+}
+
+pub struct WebSocket {
+    io: Upgraded,
+    config: Config,
+    protocol: Option<String>,
+    deflate: Option<Deflate>,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    partial: Option<Partial>,
+    error: Option<WebSocketError>,
+    sent_close: bool,
+    received_close: bool,
+    terminated: bool,
+}
+
+impl WebSocket {
+    pub(crate) fn new(
+        io: Upgraded,
+        config: Config,
+        protocol: Option<String>,
+        deflate: Option<Deflate>,
+    ) -> Self {
+        Self {
+            io,
+            config,
+            protocol,
+            deflate,
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+            partial: None,
+            error: None,
+            sent_close: false,
+            received_close: false,
+            terminated: false,
+        }
+    }
+
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    pub async fn recv(&mut self) -> Option<Result<Message, WebSocketError>> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    pub async fn send(&mut self, message: impl Into<Message>) -> Result<(), WebSocketError> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_ready(cx)).await?;
+        Pin::new(&mut *self).start_send(message.into())?;
+        poll_fn(|cx| Pin::new(&mut *self).poll_flush(cx)).await
+    }
+
+    pub async fn close(&mut self, frame: Option<CloseFrame>) -> Result<(), WebSocketError> {
+        if !self.sent_close {
+            self.write_close(frame)?;
+        }
+        poll_fn(|cx| Pin::new(&mut *self).poll_close(cx)).await
+    }
+
+    fn write_message(&mut self, message: Message) -> Result<(), WebSocketError> {
+        if self.sent_close {
+            return Err(WebSocketError::ConnectionClosed);
+        }
+        match message {
+            Message::Text(text) => self.write_data(frame::OP_TEXT, text.as_bytes()),
+            Message::Binary(data) => self.write_data(frame::OP_BINARY, &data),
+            Message::Ping(data) => self.write_control(frame::OP_PING, &data),
+            Message::Pong(data) => self.write_control(frame::OP_PONG, &data),
+            Message::Close(frame) => self.write_close(frame),
+        }
+    }
+
+    fn write_data(&mut self, opcode: u8, data: &[u8]) -> Result<(), WebSocketError> {
+        match &mut self.deflate {
+            Some(deflate) => {
+                let data = deflate.compress(data)?;
+                frame::write_frame(&mut self.write_buf, opcode, true, &data);
+            }
+            None => frame::write_frame(&mut self.write_buf, opcode, false, data),
+        }
+        Ok(())
+    }
+
+    fn write_control(&mut self, opcode: u8, data: &[u8]) -> Result<(), WebSocketError> {
+        if data.len() > frame::MAX_CONTROL_PAYLOAD {
+            return Err(WebSocketError::Protocol("control frame too large"));
+        }
+        frame::write_frame(&mut self.write_buf, opcode, false, data);
+        Ok(())
+    }
+
+    fn write_close(&mut self, frame: Option<CloseFrame>) -> Result<(), WebSocketError> {
+        let mut payload = Vec::new();
+        if let Some(frame) = frame {
+            payload.extend_from_slice(&frame.code.to_be_bytes());
+            payload.extend_from_slice(frame.reason.as_bytes());
+        }
+        self.write_control(frame::OP_CLOSE, &payload)?;
+        self.sent_close = true;
+        Ok(())
+    }
+
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), WebSocketError>> {
+        while !self.write_buf.is_empty() {
+            let n = ready!(self.io.poll_write(cx, &self.write_buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(WebSocketError::Io(
+                    std::io::ErrorKind::WriteZero.into(),
+                )));
+            }
+            self.write_buf.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_terminate(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.terminated {
+            if ready!(self.poll_write_buf(cx)).is_ok() {
+                let _ = ready!(self.io.poll_flush(cx));
+            }
+            let _ = ready!(self.io.poll_close(cx));
+            self.terminated = true;
+        }
+        Poll::Ready(())
+    }
+
+    fn fail(&mut self, err: WebSocketError) {
+        if let Some(code) = err.close_code() {
+            if !self.sent_close {
+                let _ = self.write_close(Some(CloseFrame::new(code, "")));
+            }
+        }
+        self.received_close = true;
+        self.error = Some(err);
+    }
+
+    fn read_frame(&mut self) -> Result<Option<Message>, WebSocketError> {
+        loop {
+            let Some((header, header_len)) = frame::parse_header(&self.read_buf)? else {
+                return Ok(None);
+            };
+            self.check_header(&header)?;
+
+            // the buffer grows with the data that actually arrives instead of
+            // trusting the announced length
+            let len = header.len as usize;
+            if self.read_buf.len() < header_len + len {
+                return Ok(None);
+            }
+            self.read_buf.advance(header_len);
+            let mut payload = self.read_buf.split_to(len);
+            if let Some(mask) = header.mask {
+                frame::apply_mask(&mut payload, mask);
+            }
+
+            if let Some(message) = self.on_frame(header, payload)? {
+                return Ok(Some(message));
+            }
+        }
+    }
+
+    fn check_header(&self, header: &Header) -> Result<(), WebSocketError> {
+        if header.mask.is_none() {
+            return Err(WebSocketError::Protocol("client frame is not masked"));
+        }
+        if header.rsv1
+            && (self.deflate.is_none()
+                || header.is_control()
+                || header.opcode == frame::OP_CONTINUE)
+        {
+            return Err(WebSocketError::Protocol("unexpected compressed frame"));
+        }
+        if header.is_control() {
+            if !header.fin {
+                return Err(WebSocketError::Protocol("fragmented control frame"));
+            }
+            if header.len > frame::MAX_CONTROL_PAYLOAD as u64 {
+                return Err(WebSocketError::Protocol("control frame too large"));
+            }
+        }
+        if header.len > self.config.max_frame_size as u64 {
+            return Err(WebSocketError::FrameTooLarge);
+        }
+        // data frames are rejected on their announced length, before the
+        // payload is buffered
+        if !header.is_control() {
+            let buffered = self
+                .partial
+                .as_ref()
+                .map_or(0, |partial| partial.data.len());
+            if header.len > self.config.max_message_size.saturating_sub(buffered) as u64 {
+                return Err(WebSocketError::MessageTooLarge);
+            }
+        }
+        Ok(())
+    }
+
+    fn on_frame(
+        &mut self,
+        header: Header,
+        payload: BytesMut,
+    ) -> Result<Option<Message>, WebSocketError> {
+        match header.opcode {
+            frame::OP_CONTINUE => {
+                let Some(partial) = &mut self.partial else {
+                    return Err(WebSocketError::Protocol("unexpected continuation frame"));
+                };
+                partial.data.extend_from_slice(&payload);
+                if !header.fin {
+                    return Ok(None);
+                }
+                let partial = self.partial.take().unwrap();
+                self.on_message(partial.opcode, partial.compressed, partial.data.freeze())
+                    .map(Some)
+            }
+            frame::OP_TEXT | frame::OP_BINARY => {
+                if self.partial.is_some() {
+                    return Err(WebSocketError::Protocol("expected continuation frame"));
+                }
+                if !header.fin {
+                    self.partial = Some(Partial {
+                        opcode: header.opcode,
+                        compressed: header.rsv1,
+                        data: payload,
+                    });
+                    return Ok(None);
+                }
+                self.on_message(header.opcode, header.rsv1, payload.freeze())
+                    .map(Some)
+            }
+            frame::OP_PING => {
+                let payload = payload.freeze();
+                if !self.sent_close {
+                    self.write_control(frame::OP_PONG, &payload)?;
+                }
+                Ok(Some(Message::Ping(payload)))
+            }
+            frame::OP_PONG => Ok(Some(Message::Pong(payload.freeze()))),
+            frame::OP_CLOSE => {
+                let frame = parse_close(&payload)?;
+                if !self.sent_close {
+                    let reply = frame.as_ref().map(|frame| CloseFrame::new(frame.code, ""));
+                    self.write_close(reply)?;
+                }
+                self.received_close = true;
+                Ok(Some(Message::Close(frame)))
+            }
+            _ => Err(WebSocketError::Protocol("unknown opcode")),
+        }
+    }
+
+    fn on_message(
+        &mut self,
+        opcode: u8,
+        compressed: bool,
+        data: Bytes,
+    ) -> Result<Message, WebSocketError> {
+        let data = if compressed {
+            let deflate = self.deflate.as_mut().unwrap();
+            Bytes::from(deflate.decompress(&data, self.config.max_message_size)?)
+        } else {
+            data
+        };
+        if opcode == frame::OP_TEXT {
+            String::from_utf8(data.into())
+                .map(Message::Text)
+                .map_err(|_| WebSocketError::InvalidUtf8)
+        } else {
+            Ok(Message::Binary(data))
+        }
+    }
+}
+
+fn parse_close(payload: &[u8]) -> Result<Option<CloseFrame>, WebSocketError> {
+    match payload {
+        [] => Ok(None),
+        [_] => Err(WebSocketError::Protocol("invalid close frame")),
+        [hi, lo, reason @ ..] => {
+            let code = u16::from_be_bytes([*hi, *lo]);
+            if !close_code::is_valid(code) {
+                return Err(WebSocketError::Protocol("invalid close code"));
+            }
+            let reason = std::str::from_utf8(reason).map_err(|_| WebSocketError::InvalidUtf8)?;
+            Ok(Some(CloseFrame::new(code, reason)))
+        }
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.received_close {
+                ready!(this.poll_terminate(cx));
+                return Poll::Ready(this.error.take().map(Err));
+            }
+
+            match this.read_frame() {
+                Ok(Some(message)) => {
+                    let _ = this.poll_write_buf(cx);
+                    return Poll::Ready(Some(Ok(message)));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    this.fail(err);
+                    continue;
+                }
+            }
+
+            if let Poll::Ready(Err(err)) = this.poll_write_buf(cx) {
+                this.received_close = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+
+            match ready!(this.io.poll_read(cx)) {
+                Some(Ok(data)) => this.read_buf.extend_from_slice(&data),
+                Some(Err(err)) => {
+                    this.received_close = true;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                None => {
+                    this.received_close = true;
+                    if !this.sent_close {
+                        return Poll::Ready(Some(Err(WebSocketError::Io(
+                            std::io::ErrorKind::UnexpectedEof.into(),
+                        ))));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Sink<Message> for WebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.sent_close {
+            return Poll::Ready(Err(WebSocketError::ConnectionClosed));
+        }
+        if this.write_buf.len() >= WRITE_BUFFER_SIZE {
+            ready!(this.poll_write_buf(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        self.get_mut().write_message(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        this.io.poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if !this.sent_close {
+            this.write_close(Some(CloseFrame::new(close_code::NORMAL, "")))?;
+        }
+        ready!(this.poll_write_buf(cx))?;
+        ready!(this.io.poll_flush(cx))?;
+        ready!(this.io.poll_close(cx))?;
+        this.terminated = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl fmt::Debug for WebSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocket")
+            .field("protocol", &self.protocol)
+            .field("compressed", &self.deflate.is_some())
+            .field("sent_close", &self.sent_close)
+            .field("received_close", &self.received_close)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::task::Waker;
+
+    use super::*;
+
+    // replays `incoming` and then stays pending, keeping whatever is written
+    struct Script {
+        incoming: VecDeque<Bytes>,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl mtiny_core::http::upgrade::Io for Script {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<io::Result<Bytes>>> {
+            match self.incoming.pop_front() {
+                Some(data) => Poll::Ready(Some(Ok(data))),
+                None => Poll::Pending,
+            }
+        }
+
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn connect(config: Config, incoming: Vec<Vec<u8>>) -> (WebSocket, Arc<Mutex<Vec<u8>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let io = Script {
+            incoming: incoming.into_iter().map(Bytes::from).collect(),
+            written: written.clone(),
+        };
+        let socket = WebSocket::new(Upgraded::new(io), config, None, None);
+        (socket, written)
+    }
+
+    // a masked client frame, or just its header when `payload` is longer
+    // than what is sent
+    fn client_frame(b0: u8, len: usize, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![b0];
+        match len {
+            0..=125 => frame.push(0x80 | len as u8),
+            _ => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    fn next(socket: &mut WebSocket) -> Poll<Option<Result<Message, WebSocketError>>> {
+        let mut cx = Context::from_waker(Waker::noop());
+        Pin::new(socket).poll_next(&mut cx)
+    }
+
+    fn close_code(written: &Mutex<Vec<u8>>) -> u16 {
+        let written = written.lock().unwrap();
+        assert_eq!(written[..2], [0x88, 2]);
+        u16::from_be_bytes([written[2], written[3]])
+    }
+
+    #[test]
+    fn echoes_the_close_code() {
+        let close = client_frame(0x88, 4, &[0x03, 0xe8, b'o', b'k']);
+        let (mut socket, written) = connect(Config::default(), vec![close]);
+
+        let Poll::Ready(Some(Ok(Message::Close(Some(frame))))) = next(&mut socket) else {
+            panic!("expected a close frame");
+        };
+        assert_eq!(frame, CloseFrame::new(close_code::NORMAL, "ok"));
+        assert!(matches!(next(&mut socket), Poll::Ready(None)));
+        assert_eq!(close_code(&written), close_code::NORMAL);
+        assert!(socket.terminated);
+    }
+
+    #[test]
+    fn waits_for_the_reply_to_its_own_close() {
+        let (mut socket, written) = connect(Config::default(), vec![]);
+        socket
+            .write_close(Some(CloseFrame::new(close_code::AWAY, "")))
+            .unwrap();
+        assert!(next(&mut socket).is_pending());
+        assert_eq!(close_code(&written), close_code::AWAY);
+
+        socket
+            .read_buf
+            .extend_from_slice(&client_frame(0x88, 2, &[0x03, 0xe9]));
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Ok(Message::Close(_))))
+        ));
+        assert!(matches!(next(&mut socket), Poll::Ready(None)));
+        // the reply is not answered again
+        assert_eq!(written.lock().unwrap().len(), 4);
+        assert!(matches!(
+            socket.write_message(Message::Text("late".into())),
+            Err(WebSocketError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn rejects_unmasked_frames() {
+        let (mut socket, written) = connect(Config::default(), vec![vec![0x81, 0x01, b'a']]);
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Err(WebSocketError::Protocol(_))))
+        ));
+        assert_eq!(close_code(&written), close_code::PROTOCOL);
+    }
+
+    #[test]
+    fn rejects_oversized_messages_by_their_header() {
+        let config = Config {
+            max_frame_size: 1024,
+            max_message_size: 1000,
+            compression: false,
+        };
+        // only the header arrives, the announced length alone is too much
+        let (mut socket, written) = connect(config, vec![client_frame(0x82, 1001, &[])]);
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Err(WebSocketError::MessageTooLarge)))
+        ));
+        assert_eq!(close_code(&written), close_code::SIZE);
+
+        let (mut socket, _) = connect(config, vec![client_frame(0x82, 1025, &[])]);
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Err(WebSocketError::FrameTooLarge)))
+        ));
+    }
+
+    #[test]
+    fn counts_buffered_fragments_against_the_limit() {
+        let config = Config {
+            max_frame_size: 1024,
+            max_message_size: 1000,
+            compression: false,
+        };
+        let first = client_frame(0x02, 600, &[0; 600]);
+        let (mut socket, _) = connect(config, vec![first, client_frame(0x80, 401, &[])]);
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Err(WebSocketError::MessageTooLarge)))
+        ));
+
+        let first = client_frame(0x02, 600, &[1; 600]);
+        let last = client_frame(0x80, 400, &[2; 400]);
+        let (mut socket, _) = connect(config, vec![first, last]);
+        let Poll::Ready(Some(Ok(Message::Binary(data)))) = next(&mut socket) else {
+            panic!("expected the reassembled message");
+        };
+        assert_eq!(data.len(), 1000);
+    }
+
+    #[test]
+    fn control_frames_do_not_count_as_message_data() {
+        let config = Config {
+            max_frame_size: 1024,
+            max_message_size: 4,
+            compression: false,
+        };
+        let first = client_frame(0x01, 4, b"full");
+        let ping = client_frame(0x89, 5, b"still");
+        let (mut socket, written) = connect(config, vec![first, ping]);
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Ok(Message::Ping(data)))) if data == "still"
+        ));
+        assert_eq!(written.lock().unwrap()[..2], [0x8a, 5]);
+
+        let (mut socket, _) = connect(config, vec![client_frame(0x89, 126, &[])]);
+        assert!(matches!(
+            next(&mut socket),
+            Poll::Ready(Some(Err(WebSocketError::Protocol(_))))
+        ));
+    }
+}
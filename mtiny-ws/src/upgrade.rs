@@ -0,0 +1,286 @@
+use std::fmt;
+use std::future::Future;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use sha1::{Digest, Sha1};
+
+use mtiny_core::http::upgrade::OnUpgrade;
+use mtiny_core::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use mtiny_core::response::{IntoResponse, Response};
+use mtiny_core::Request;
+
+use crate::deflate::{Deflate, DeflateConfig};
+use crate::error::WebSocketUpgradeError;
+use crate::socket::{Config, WebSocket};
+
+const GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub struct WebSocketUpgrade {
+    key: HeaderValue,
+    on_upgrade: OnUpgrade,
+    requested_protocols: Vec<String>,
+    protocol: Option<String>,
+    extensions: Option<String>,
+    config: Config,
+}
+
+impl WebSocketUpgrade {
+    pub fn from_request<B>(request: &mut Request<B>) -> Result<Self, WebSocketUpgradeError> {
+        if request.method() != Method::GET {
+            return Err(WebSocketUpgradeError::MethodNotGet);
+        }
+
+        let headers = request.headers();
+        if !header_contains(headers, header::UPGRADE, "websocket") {
+            return Err(WebSocketUpgradeError::MissingUpgrade);
+        }
+        if !header_contains(headers, header::CONNECTION, "upgrade") {
+            return Err(WebSocketUpgradeError::MissingConnectionUpgrade);
+        }
+        if headers
+            .get(header::SEC_WEBSOCKET_VERSION)
+            .map(HeaderValue::as_bytes)
+            != Some(b"13")
+        {
+            return Err(WebSocketUpgradeError::UnsupportedVersion);
+        }
+        let key = headers
+            .get(header::SEC_WEBSOCKET_KEY)
+            .filter(|key| matches!(STANDARD.decode(key.as_bytes()), Ok(key) if key.len() == 16))
+            .cloned()
+            .ok_or(WebSocketUpgradeError::InvalidKey)?;
+
+        let requested_protocols = header_tokens(headers, header::SEC_WEBSOCKET_PROTOCOL)
+            .map(str::to_owned)
+            .collect();
+        let extensions = headers
+            .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>();
+        let extensions = (!extensions.is_empty()).then(|| extensions.join(","));
+
+        let on_upgrade = request
+            .extensions_mut()
+            .remove::<OnUpgrade>()
+            .ok_or(WebSocketUpgradeError::NotUpgradable)?;
+
+        Ok(Self {
+            key,
+            on_upgrade,
+            requested_protocols,
+            protocol: None,
+            extensions,
+            config: Config::default(),
+        })
+    }
+
+    pub fn requested_protocols(&self) -> impl Iterator<Item = &str> {
+        self.requested_protocols.iter().map(String::as_str)
+    }
+
+    pub fn protocols<I, P>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        self.protocol = protocols.into_iter().find_map(|protocol| {
+            let protocol = protocol.as_ref();
+            self.requested_protocols
+                .iter()
+                .find(|requested| requested.eq_ignore_ascii_case(protocol))
+                .cloned()
+        });
+        self
+    }
+
+    pub fn max_frame_size(mut self, size: usize) -> Self {
+        self.config.max_frame_size = size;
+        self
+    }
+
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.config.max_message_size = size;
+        self
+    }
+
+    pub fn compression(mut self, enable: bool) -> Self {
+        self.config.compression = enable;
+        self
+    }
+
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> Response
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (response, upgrade) = self.into_parts();
+        tokio::spawn(async move {
+            if let Some(socket) = upgrade.await {
+                callback(socket).await;
+            }
+        });
+        response
+    }
+
+    // for callbacks holding `!Send` state; the task is spawned with
+    // `spawn_local`, so this panics unless called inside a `LocalSet`, which
+    // both server backends run their handlers on
+    pub fn on_upgrade_local<F, Fut>(self, callback: F) -> Response
+    where
+        F: FnOnce(WebSocket) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let (response, upgrade) = self.into_parts();
+        tokio::task::spawn_local(async move {
+            if let Some(socket) = upgrade.await {
+                callback(socket).await;
+            }
+        });
+        response
+    }
+
+    fn into_parts(self) -> (Response, impl Future<Output = Option<WebSocket>> + Send) {
+        let deflate = self
+            .extensions
+            .as_deref()
+            .filter(|_| self.config.compression)
+            .and_then(DeflateConfig::negotiate);
+
+        let mut response = StatusCode::SWITCHING_PROTOCOLS.into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept_key(&self.key));
+        if let Some(protocol) = &self.protocol {
+            if let Ok(value) = HeaderValue::from_str(protocol) {
+                headers.insert(header::SEC_WEBSOCKET_PROTOCOL, value);
+            }
+        }
+        if let Some(deflate) = &deflate {
+            if let Ok(value) = HeaderValue::from_str(&deflate.response()) {
+                headers.insert(header::SEC_WEBSOCKET_EXTENSIONS, value);
+            }
+        }
+
+        let Self {
+            on_upgrade,
+            protocol,
+            config,
+            ..
+        } = self;
+        let upgrade = async move {
+            let upgraded = on_upgrade.await.ok()?;
+            let deflate = deflate.map(Deflate::new);
+            Some(WebSocket::new(upgraded, config, protocol, deflate))
+        };
+
+        (response, upgrade)
+    }
+}
+
+impl fmt::Debug for WebSocketUpgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketUpgrade")
+            .field("requested_protocols", &self.requested_protocols)
+            .field("protocol", &self.protocol)
+            .field("extensions", &self.extensions)
+            .finish()
+    }
+}
+
+fn accept_key(key: &HeaderValue) -> HeaderValue {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(GUID);
+    let accept = STANDARD.encode(sha1.finalize());
+    HeaderValue::from_str(&accept).unwrap()
+}
+
+fn header_tokens(headers: &HeaderMap, name: header::HeaderName) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn header_contains(headers: &HeaderMap, name: header::HeaderName, token: &str) -> bool {
+    header_tokens(headers, name).any(|value| value.eq_ignore_ascii_case(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use bytes::Bytes;
+    use mtiny_core::http::upgrade::{Io, Upgraded};
+
+    use super::*;
+
+    struct Idle;
+
+    impl Io for Idle {
+        fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<io::Result<Bytes>>> {
+            Poll::Pending
+        }
+
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn request() -> Request<()> {
+        let mut request = Request::new(());
+        let headers = request.headers_mut();
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+        headers.insert(
+            header::SEC_WEBSOCKET_VERSION,
+            HeaderValue::from_static("13"),
+        );
+        headers.insert(
+            header::SEC_WEBSOCKET_KEY,
+            HeaderValue::from_static("dGhlIHNhbXBsZSBub25jZQ=="),
+        );
+        request
+            .extensions_mut()
+            .insert(OnUpgrade::new(async { Ok(Upgraded::new(Idle)) }));
+        request
+    }
+
+    #[test]
+    fn accept_key_matches_rfc() {
+        let key = HeaderValue::from_static("dGhlIHNhbXBsZSBub25jZQ==");
+        assert_eq!(accept_key(&key), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn on_upgrade_outside_local_set() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let response = WebSocketUpgrade::from_request(&mut request())
+            .unwrap()
+            .on_upgrade(|socket| async move {
+                let _ = tx.send(socket.protocol().map(str::to_owned));
+            });
+        assert_eq!(*response.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(rx.await.unwrap(), None);
+    }
+}
@@ -1,31 +1,109 @@
 use bytes::{Buf, BufMut, Bytes};
 use mtiny_core::body::{Body, BodyExt};
 use mtiny_core::{BoxError, Request};
+use mtiny_middleware::core::body_limit::BodyLimit;
 
-pub async fn bytes(request: &mut Request) -> Result<Bytes, BoxError> {
-    let mut body = std::mem::take(request.body_mut());
+pub const DEFAULT_LIMIT: u64 = 2 * 1024 * 1024;
 
+pub async fn bytes<B>(request: &mut Request<B>) -> Result<Bytes, BoxError>
+where
+    B: Body + Default + Unpin,
+    B::Error: Into<BoxError>,
+{
+    bytes_with_limit(request, DEFAULT_LIMIT).await
+}
+
+// a limit set by the `body_limit` middleware takes precedence over `default`
+pub async fn bytes_with_limit<B>(request: &mut Request<B>, default: u64) -> Result<Bytes, BoxError>
+where
+    B: Body + Default + Unpin,
+    B::Error: Into<BoxError>,
+{
+    let limit = request
+        .extensions()
+        .get::<BodyLimit>()
+        .map_or(default, |limit| limit.0);
+    collect(std::mem::take(request.body_mut()).limit(limit), limit).await
+}
+
+async fn collect<B>(mut body: B, limit: u64) -> Result<Bytes, BoxError>
+where
+    B: Body + Unpin,
+    B::Error: Into<BoxError>,
+{
     let mut buf1 = if let Some(buf) = body.next().await {
-        buf?
+        buf.map_err(Into::into)?
     } else {
         return Ok(Bytes::new());
     };
 
     let buf2 = if let Some(buf) = body.next().await {
-        buf?
+        buf.map_err(Into::into)?
     } else {
         return Ok(buf1.copy_to_bytes(buf1.remaining()));
     };
 
-    let cap = buf1.remaining() + buf2.remaining() + body.size_hint().lower() as usize;
-    let mut vec = Vec::with_capacity(cap);
+    // the size hint comes from the client, never reserve more than the limit
+    let cap = (buf1.remaining() + buf2.remaining()) as u64 + body.size_hint().lower();
+    let mut vec = Vec::with_capacity(cap.min(limit) as usize);
 
     vec.put(buf1);
     vec.put(buf2);
 
     while let Some(buf) = body.next().await {
-        vec.put(buf?);
+        vec.put(buf.map_err(Into::into)?);
     }
 
     Ok(vec.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use mtiny_core::body::LengthLimitError;
+
+    use super::*;
+
+    fn ready<F: Future>(fut: F) -> F::Output {
+        match std::pin::pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("extractor is pending"),
+        }
+    }
+
+    fn limit_error(result: Result<Bytes, BoxError>) -> u64 {
+        result
+            .unwrap_err()
+            .downcast::<LengthLimitError>()
+            .unwrap()
+            .limit()
+    }
+
+    #[test]
+    fn applies_default_limit() {
+        let mut request = Request::new(Bytes::from(vec![0; DEFAULT_LIMIT as usize]));
+        assert_eq!(
+            ready(bytes(&mut request)).unwrap().len() as u64,
+            DEFAULT_LIMIT
+        );
+
+        let mut request = Request::new(Bytes::from(vec![0; DEFAULT_LIMIT as usize + 1]));
+        assert_eq!(limit_error(ready(bytes(&mut request))), DEFAULT_LIMIT);
+    }
+
+    #[test]
+    fn body_limit_takes_precedence() {
+        let request = || {
+            let mut request = Request::new(Bytes::from_static(b"more than eight"));
+            request.extensions_mut().insert(BodyLimit(8));
+            request
+        };
+        assert_eq!(limit_error(ready(bytes(&mut request()))), 8);
+        assert_eq!(
+            limit_error(ready(bytes_with_limit(&mut request(), 1024))),
+            8
+        );
+    }
+}
@@ -0,0 +1,5 @@
+use mtiny_core::{ConnectionInfo, Request};
+
+pub fn connection_info<B>(request: &Request<B>) -> Option<&ConnectionInfo> {
+    request.extensions().get::<ConnectionInfo>()
+}
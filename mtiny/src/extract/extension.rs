@@ -1,15 +1,15 @@
 use mtiny_core::Request;
 
-pub fn extension<T>(request: &Request) -> Option<&T>
+pub fn extension<T>(request: &Request<impl Sized>) -> Option<&T>
 where
-    T: 'static,
+    T: Send + Sync + 'static,
 {
     request.extensions().get::<T>()
 }
 
-pub fn extension_mut<T>(request: &mut Request) -> Option<&mut T>
+pub fn extension_mut<T>(request: &mut Request<impl Sized>) -> Option<&mut T>
 where
-    T: 'static,
+    T: Send + Sync + 'static,
 {
     request.extensions_mut().get_mut::<T>()
 }
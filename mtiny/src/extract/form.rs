@@ -0,0 +1,121 @@
+use mtiny_core::{
+    body::{Body, LengthLimitError},
+    http::header,
+    BoxError, Request,
+};
+use serde::de::DeserializeOwned;
+
+pub const DEFAULT_LIMIT: u64 = 2 * 1024 * 1024;
+
+pub async fn form<T>(
+    request: &mut Request<impl Body<Error: Into<BoxError>> + Default + Unpin>,
+) -> Result<T, ExtractFormError>
+where
+    T: DeserializeOwned,
+{
+    if !is_form_content_type(request) {
+        return Err(ExtractFormError::UnsupportedContentType);
+    }
+
+    let bytes = crate::extract::bytes::bytes_with_limit(request, DEFAULT_LIMIT)
+        .await
+        .map_err(|err| match err.downcast::<LengthLimitError>() {
+            Ok(err) => ExtractFormError::LengthLimitExceeded(*err),
+            Err(err) => ExtractFormError::FailedReadBody(err),
+        })?;
+    serde_urlencoded::from_bytes(&bytes).map_err(ExtractFormError::FailedToDeserialize)
+}
+
+fn is_form_content_type<B>(request: &Request<B>) -> bool {
+    request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+        .is_some_and(|mime| {
+            mime.type_() == mime::APPLICATION && mime.subtype() == mime::WWW_FORM_URLENCODED
+        })
+}
+
+#[derive(Debug)]
+pub enum ExtractFormError {
+    UnsupportedContentType,
+    LengthLimitExceeded(LengthLimitError),
+    FailedReadBody(BoxError),
+    FailedToDeserialize(serde::de::value::Error),
+}
+
+impl core::fmt::Display for ExtractFormError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractFormError::UnsupportedContentType => f.write_str("unsupported content type"),
+            ExtractFormError::LengthLimitExceeded(e) => e.fmt(f),
+            ExtractFormError::FailedReadBody(e) => {
+                write!(f, "failed to read body ({})", e)
+            }
+            ExtractFormError::FailedToDeserialize(e) => {
+                write!(f, "failed to deserialize form ({})", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractFormError {}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use bytes::Bytes;
+    use mtiny_middleware::core::body_limit::BodyLimit;
+
+    use super::*;
+
+    fn ready<F: Future>(fut: F) -> F::Output {
+        match std::pin::pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("extractor is pending"),
+        }
+    }
+
+    fn sized(len: usize) -> Request<Bytes> {
+        let mut request = Request::new(Bytes::from(vec![b' '; len]));
+        request.headers_mut().insert(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded".parse().unwrap(),
+        );
+        request
+    }
+
+    #[test]
+    fn rejects_bodies_over_the_default_limit() {
+        let result = ready(form::<()>(&mut sized(DEFAULT_LIMIT as usize + 1)));
+        match result {
+            Err(ExtractFormError::LengthLimitExceeded(err)) => {
+                assert_eq!(err.limit(), DEFAULT_LIMIT)
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn body_limit_overrides_the_default() {
+        let mut request = sized(DEFAULT_LIMIT as usize + 1);
+        request
+            .extensions_mut()
+            .insert(BodyLimit(DEFAULT_LIMIT * 2));
+        let result = ready(form::<()>(&mut request));
+        assert!(!matches!(
+            result,
+            Err(ExtractFormError::LengthLimitExceeded(_))
+        ));
+
+        let mut request = sized(16);
+        request.extensions_mut().insert(BodyLimit(8));
+        match ready(form::<()>(&mut request)) {
+            Err(ExtractFormError::LengthLimitExceeded(err)) => assert_eq!(err.limit(), 8),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::net::IpAddr;
+
+use mtiny_core::http::uri::{Authority, Scheme};
+use mtiny_core::Request;
+use mtiny_middleware::core::trusted_proxy::{EffectiveHost, EffectiveScheme, RealIp};
+
+pub fn real_ip<B>(request: &Request<B>) -> Option<IpAddr> {
+    request.extensions().get::<RealIp>().map(|ip| ip.0)
+}
+
+pub fn effective_scheme<B>(request: &Request<B>) -> Option<&Scheme> {
+    request
+        .extensions()
+        .get::<EffectiveScheme>()
+        .map(|scheme| &scheme.0)
+}
+
+pub fn effective_host<B>(request: &Request<B>) -> Option<&Authority> {
+    request
+        .extensions()
+        .get::<EffectiveHost>()
+        .map(|host| &host.0)
+}
@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use mtiny_core::{http::HeaderName, BoxError, Request};
 
-pub fn header<T>(request: &Request, name: HeaderName) -> Result<T, ExtractHeaderError>
+pub fn header<T>(request: &Request<impl Sized>, name: HeaderName) -> Result<T, ExtractHeaderError>
 where
     T: FromStr,
     T::Err: Into<BoxError>,
@@ -1,7 +1,15 @@
-use mtiny_core::{http::header, BoxError, Request};
+use mtiny_core::{
+    body::{Body, LengthLimitError},
+    http::header,
+    BoxError, Request,
+};
 use serde::de::DeserializeOwned;
 
-pub async fn json<T>(request: &mut Request) -> Result<T, ExtractJsonError>
+pub const DEFAULT_LIMIT: u64 = 2 * 1024 * 1024;
+
+pub async fn json<T>(
+    request: &mut Request<impl Body<Error: Into<BoxError>> + Default + Unpin>,
+) -> Result<T, ExtractJsonError>
 where
     T: DeserializeOwned,
 {
@@ -9,12 +17,15 @@ where
         return Err(ExtractJsonError::UnsupportedContentType);
     }
 
-    let bytes = crate::extract::bytes(request)
+    let bytes = crate::extract::bytes::bytes_with_limit(request, DEFAULT_LIMIT)
         .await
-        .map_err(|e| ExtractJsonError::FailedReadBody(e.into()))?;
+        .map_err(|err| match err.downcast::<LengthLimitError>() {
+            Ok(err) => ExtractJsonError::LengthLimitExceeded(*err),
+            Err(err) => ExtractJsonError::FailedReadBody(err),
+        })?;
     serde_json::from_slice(&bytes).map_err(ExtractJsonError::FailedToDeserialize)
 }
-fn is_json_content_type(request: &Request) -> bool {
+fn is_json_content_type<B>(request: &Request<B>) -> bool {
     let content_type = if let Some(content_type) = request.headers().get(header::CONTENT_TYPE) {
         content_type
     } else {
@@ -39,6 +50,7 @@ fn is_json_content_type(request: &Request) -> bool {
 #[derive(Debug)]
 pub enum ExtractJsonError {
     UnsupportedContentType,
+    LengthLimitExceeded(LengthLimitError),
     FailedReadBody(BoxError),
     FailedToDeserialize(serde_json::Error),
 }
@@ -47,6 +59,7 @@ impl core::fmt::Display for ExtractJsonError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ExtractJsonError::UnsupportedContentType => f.write_str("snsupported content type"),
+            ExtractJsonError::LengthLimitExceeded(e) => e.fmt(f),
             ExtractJsonError::FailedReadBody(e) => {
                 write!(f, "failed to read body ({})", e)
             }
@@ -58,3 +71,60 @@ impl core::fmt::Display for ExtractJsonError {
 }
 
 impl std::error::Error for ExtractJsonError {}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use bytes::Bytes;
+    use mtiny_middleware::core::body_limit::BodyLimit;
+
+    use super::*;
+
+    fn ready<F: Future>(fut: F) -> F::Output {
+        match std::pin::pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("extractor is pending"),
+        }
+    }
+
+    fn sized(len: usize) -> Request<Bytes> {
+        let mut request = Request::new(Bytes::from(vec![b' '; len]));
+        request
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn rejects_bodies_over_the_default_limit() {
+        let result = ready(json::<()>(&mut sized(DEFAULT_LIMIT as usize + 1)));
+        match result {
+            Err(ExtractJsonError::LengthLimitExceeded(err)) => {
+                assert_eq!(err.limit(), DEFAULT_LIMIT)
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn body_limit_overrides_the_default() {
+        let mut request = sized(DEFAULT_LIMIT as usize + 1);
+        request
+            .extensions_mut()
+            .insert(BodyLimit(DEFAULT_LIMIT * 2));
+        let result = ready(json::<()>(&mut request));
+        assert!(!matches!(
+            result,
+            Err(ExtractJsonError::LengthLimitExceeded(_))
+        ));
+
+        let mut request = sized(16);
+        request.extensions_mut().insert(BodyLimit(8));
+        match ready(json::<()>(&mut request)) {
+            Err(ExtractJsonError::LengthLimitExceeded(err)) => assert_eq!(err.limit(), 8),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
@@ -4,6 +4,9 @@ pub use self::bytes::bytes;
 pub mod json;
 pub use self::json::json;
 
+pub mod form;
+pub use self::form::form;
+
 pub mod header;
 pub use self::header::header;
 
@@ -19,10 +22,38 @@ pub use self::stream::stream;
 pub mod param;
 pub use self::param::{param,params,param_raw};
 
+pub mod connection;
+pub use self::connection::connection_info;
+
+pub mod forwarded;
+pub use self::forwarded::{effective_host, effective_scheme, real_ip};
+
+pub mod request_id;
+pub use self::request_id::request_id;
+
+#[cfg(feature = "sse")]
+pub mod sse;
+#[cfg(feature = "sse")]
+pub use self::sse::last_event_id;
+
+#[cfg(feature = "multipart")]
+pub mod multipart;
+#[cfg(feature = "multipart")]
+pub use self::multipart::multipart;
+
+#[cfg(feature = "ws")]
+pub mod websocket;
+#[cfg(feature = "ws")]
+pub use self::websocket::websocket;
+
 pub mod error {
-   // pub use super::form::ExtractFormError;
+    pub use super::form::ExtractFormError;
     pub use super::header::ExtractHeaderError;
     pub use super::json::ExtractJsonError;
     pub use super::param::ExtractParamError;
     pub use super::query::ExtractQueryError;
+    #[cfg(feature = "multipart")]
+    pub use mtiny_multipart::MultipartError;
+    #[cfg(feature = "ws")]
+    pub use super::websocket::WebSocketUpgradeError;
 }
@@ -0,0 +1,78 @@
+use mtiny_core::body::{Body, BodyExt};
+use mtiny_core::{BoxError, Request};
+use mtiny_middleware::core::body_limit::BodyLimit;
+use mtiny_multipart::{Multipart, MultipartError};
+
+pub const DEFAULT_LIMIT: u64 = 10 * 1024 * 1024;
+
+pub fn multipart<B>(request: &mut Request<B>) -> Result<Multipart, MultipartError>
+where
+    B: Body + Default + 'static,
+    B::Error: Into<BoxError>,
+{
+    let limit = request
+        .extensions()
+        .get::<BodyLimit>()
+        .map_or(DEFAULT_LIMIT, |limit| limit.0);
+    let body = std::mem::take(request.body_mut())
+        .limit(limit)
+        .map_err(|err| -> Box<dyn std::error::Error> { err });
+    Multipart::new(request.headers(), body.stream())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll, Waker};
+
+    use bytes::Bytes;
+    use futures_util::Stream;
+    use mtiny_core::body::LengthLimitError;
+    use mtiny_core::http::header;
+
+    use super::*;
+
+    fn upload(len: usize) -> Request<Bytes> {
+        let mut body = b"--X\r\ncontent-disposition: form-data; name=\"file\"\r\n\r\n".to_vec();
+        body.resize(body.len() + len, b'x');
+        body.extend_from_slice(b"\r\n--X--\r\n");
+        let mut request = Request::new(Bytes::from(body));
+        request.headers_mut().insert(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=X".parse().unwrap(),
+        );
+        request
+    }
+
+    fn first_field(mut request: Request<Bytes>) -> Result<(), MultipartError> {
+        let multipart = std::pin::pin!(multipart(&mut request)?);
+        match multipart.poll_next(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(Some(field)) => field.map(drop),
+            _ => panic!("no field"),
+        }
+    }
+
+    fn limit_error(result: Result<(), MultipartError>) -> u64 {
+        match result {
+            Err(MultipartError::Other(err)) => {
+                err.downcast_ref::<LengthLimitError>().unwrap().limit()
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn applies_default_limit() {
+        assert!(first_field(upload(1024)).is_ok());
+        assert_eq!(
+            limit_error(first_field(upload(DEFAULT_LIMIT as usize))),
+            DEFAULT_LIMIT
+        );
+    }
+
+    #[test]
+    fn body_limit_overrides_the_default() {
+        let mut request = upload(1024);
+        request.extensions_mut().insert(BodyLimit(512));
+        assert_eq!(limit_error(first_field(request)), 512);
+    }
+}
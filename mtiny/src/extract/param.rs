@@ -3,13 +3,13 @@ use std::{collections::HashMap, str::FromStr};
 use mtiny_core::{BoxError, Request};
 use mtiny_router::Params;
 
-pub fn params(request: &Request) -> Option<&HashMap<String, String>> {
+pub fn params(request: &Request<impl Sized>) -> Option<&HashMap<String, String>> {
     crate::extract::extension(request).map(|params: &Params| params.get_ref())
 }
-pub fn param_raw<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+pub fn param_raw<'a>(request: &'a Request<impl Sized>, name: &str) -> Option<&'a str> {
     params(request).and_then(|params| params.get(name).map(|v| v.as_str()))
 }
-pub fn param<T>(request: &Request, name: &str) -> Result<T, ExtractParamError>
+pub fn param<T>(request: &Request<impl Sized>, name: &str) -> Result<T, ExtractParamError>
 where
     T: FromStr,
     T::Err: Into<BoxError>,
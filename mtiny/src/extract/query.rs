@@ -1,7 +1,7 @@
 use mtiny_core::Request;
 use serde::Deserialize;
 
-pub fn query<'de, T>(request: &'de Request) -> Result<T, ExtractQueryError>
+pub fn query<'de, T>(request: &'de Request<impl Sized>) -> Result<T, ExtractQueryError>
 where
     T: Deserialize<'de>,
 {
@@ -0,0 +1,6 @@
+use mtiny_core::Request;
+use mtiny_middleware::core::request_id::RequestId;
+
+pub fn request_id<B>(request: &Request<B>) -> Option<RequestId> {
+    request.extensions().get::<RequestId>().cloned()
+}
@@ -0,0 +1,5 @@
+use mtiny_core::Request;
+
+pub fn last_event_id<B>(request: &Request<B>) -> Option<&str> {
+    mtiny_sse::last_event_id(request)
+}
@@ -1,5 +1,8 @@
-use mtiny_core::{Request, body::{BodyStream, BoxBody, BodyExt}};
+use mtiny_core::{Request, body::{Body, BodyStream, BodyExt}};
 
-pub fn stream(request: &mut Request)->BodyStream<BoxBody>{
+pub fn stream<B>(request: &mut Request<B>)->BodyStream<B>
+where
+    B: Body + Default,
+{
     std::mem::take(request.body_mut()).stream()
 }
\ No newline at end of file
@@ -0,0 +1,7 @@
+use mtiny_core::Request;
+
+pub use mtiny_ws::{WebSocketUpgrade, WebSocketUpgradeError};
+
+pub fn websocket<B>(request: &mut Request<B>) -> Result<WebSocketUpgrade, WebSocketUpgradeError> {
+    WebSocketUpgrade::from_request(request)
+}
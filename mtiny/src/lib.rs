@@ -8,12 +8,34 @@ pub mod route{
 }
 pub use route::Router;
 
+#[cfg(feature = "ws")]
+pub mod ws{
+    pub use mtiny_ws::*;
+}
+
+#[cfg(any(feature = "actix", feature = "hyper"))]
 pub mod server{
     pub use mtiny_server::*;
 }
 
+#[cfg(any(feature = "actix", feature = "hyper"))]
 pub use server::Server;
 
 pub mod middleware{
-    pub use mtiny_middleware::core::{add_extension,handle_error};
+    pub use mtiny_middleware::core::{access_log,add_extension,body_limit,cors,from_fn,handle_error,handle_error_with,rate_limit,request_id,trace_context,trusted_proxy};
+    pub use mtiny_middleware::core::request_id::{IdFormat,RequestId};
+    pub use mtiny_middleware::core::access_log::LogFormat;
+    pub use mtiny_middleware::core::body_limit::BodyLimit;
+    pub use mtiny_middleware::core::trace_context::TraceContext;
+    pub use mtiny_middleware::core::cors::AllowOrigin;
+    pub use mtiny_middleware::core::rate_limit::{InvalidQuota,MemoryStore,Quota,RateLimitKey,RateLimitStore};
+    pub use mtiny_middleware::core::from_fn::{from_fn_with,from_fn_with_state,Next};
+    #[cfg(feature = "tracing")]
+    pub use mtiny_middleware::core::trace::{self,trace};
+    #[cfg(feature = "compression-any")]
+    pub use mtiny_middleware::compression::{self, compression};
+    #[cfg(feature = "decompression-any")]
+    pub use mtiny_middleware::decompression::{self, decompression};
+    #[cfg(feature = "timeout")]
+    pub use mtiny_middleware::timeout::{self, timeout, timeout_override, RequestTimeout};
 }
\ No newline at end of file
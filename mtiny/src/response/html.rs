@@ -12,7 +12,7 @@ pub struct Html<B>(pub B);
 
 impl<B> IntoResponse for Html<B>
 where
-    B: Body + 'static,
+    B: Body + Send + 'static,
     B::Error: Into<BoxError>,
 {
     fn into_response(self) -> Response {
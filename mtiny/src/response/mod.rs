@@ -6,4 +6,9 @@ pub use self::html::html;
 pub mod json;
 pub use self::json::json;
 
-pub mod stream;
\ No newline at end of file
+pub mod stream;
+
+#[cfg(feature = "sse")]
+pub mod sse;
+#[cfg(feature = "sse")]
+pub use self::sse::sse;
@@ -0,0 +1,5 @@
+pub use mtiny_sse::{Event, KeepAlive, Sse, SseBody};
+
+pub fn sse<S>(stream: S) -> Sse<S> {
+    Sse::new(stream)
+}